@@ -0,0 +1,266 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! A catalogue of the effective leakage areas of building components, after
+//! the table of component leakage areas in the ASHRAE Handbook of
+//! Fundamentals (Chapter 16). It allows estimating the leakage of a building
+//! from the bottom up when no blower-door test is available.
+//!
+//! The effective leakage areas are given at 4 Pa with a discharge
+//! coefficient of 1, in $`cm^2`$ (per component, per $`m^2`$ or per $`m`$
+//! of crack, depending on the component).
+
+use crate::elements::{PowerLaw, REFERENCE_DENSITY};
+use crate::Float;
+
+/// The pressure difference at which the effective leakage areas are given, in Pa
+pub const ELA_REFERENCE_PRESSURE: Float = 4.;
+
+/// The flow exponent assumed for leaks whose exponent is not known
+pub const DEFAULT_LEAKAGE_EXPONENT: Float = 0.65;
+
+/// What the leakage area of a component is given per
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeakageUnit {
+    /// $`cm^2`$ per component
+    Each,
+    /// $`cm^2`$ per $`m^2`$ of component
+    PerArea,
+    /// $`cm^2`$ per $`m`$ of crack or joint
+    PerLength,
+}
+
+/// The leakage of a kind of building component
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComponentLeakage {
+    /// The name used for selecting the component
+    pub name: &'static str,
+    /// What the leakage area is given per
+    pub unit: LeakageUnit,
+    /// The best estimate of the effective leakage area, in $`cm^2`$ per unit
+    pub best: Float,
+    /// The minimum effective leakage area reported, in $`cm^2`$ per unit
+    pub min: Float,
+    /// The maximum effective leakage area reported, in $`cm^2`$ per unit
+    pub max: Float,
+}
+
+macro_rules! component {
+    ($name:expr, $unit:ident, $best:expr, $min:expr, $max:expr) => {
+        ComponentLeakage {
+            name: $name,
+            unit: LeakageUnit::$unit,
+            best: $best,
+            min: $min,
+            max: $max,
+        }
+    };
+}
+
+/// The components in the catalogue
+pub const COMPONENT_LEAKAGES: &[ComponentLeakage] = &[
+    // Ceilings and ceiling penetrations
+    component!("ceiling", PerArea, 1.8, 0.79, 2.8),
+    component!("ceiling_wall_joint", PerLength, 1.5, 0.075, 3.0),
+    component!("recessed_light", Each, 10., 1.5, 21.),
+    component!("whole_house_fan_louvres_closed", Each, 20., 1.6, 21.),
+    component!("attic_hatch_not_weatherstripped", Each, 30., 10., 37.),
+    component!("attic_hatch_weatherstripped", Each, 18., 3.0, 30.),
+    component!("pipe_penetration", Each, 2.0, 0.5, 6.2),
+    component!("chimney", Each, 29., 21., 36.),
+    // Doors
+    component!("door_frame", Each, 12., 2.4, 25.),
+    component!("exterior_door_not_weatherstripped", Each, 21., 12., 53.),
+    component!("exterior_door_weatherstripped", Each, 12., 0.8, 27.),
+    component!("double_door_not_weatherstripped", Each, 22., 8., 60.),
+    component!("storm_door", Each, 6., 3., 6.),
+    // Electrical
+    component!("outlet_without_gasket", Each, 2.5, 0.5, 6.2),
+    component!("outlet_with_gasket", Each, 0.15, 0.08, 3.5),
+    // Walls
+    component!("wall_cast_in_place_concrete", PerArea, 0.5, 0.05, 1.8),
+    component!("wall_precast_concrete_panel", PerArea, 1.2, 0.35, 2.3),
+    component!("wall_clay_brick_cavity", PerArea, 0.68, 0.1, 2.3),
+    component!(
+        "wall_heavy_concrete_block_unfinished",
+        PerArea,
+        2.5,
+        0.52,
+        4.0
+    ),
+    component!(
+        "wall_light_concrete_block_unfinished",
+        PerArea,
+        3.5,
+        1.3,
+        4.5
+    ),
+    component!("wall_continuous_air_barrier", PerArea, 0.7, 0.2, 1.3),
+    component!("sill_foundation_not_caulked", PerLength, 12., 3.8, 12.),
+    component!("sill_foundation_caulked", PerLength, 2.4, 0.8, 2.4),
+    // Windows
+    component!("window_casement_weatherstripped", PerLength, 0.24, 0.1, 0.4),
+    component!(
+        "window_double_hung_not_weatherstripped",
+        PerLength,
+        2.5,
+        0.7,
+        6.1
+    ),
+    component!(
+        "window_double_hung_weatherstripped",
+        PerLength,
+        0.65,
+        0.2,
+        1.2
+    ),
+    component!(
+        "window_horizontal_slider_weatherstripped",
+        PerLength,
+        0.6,
+        0.3,
+        1.1
+    ),
+    component!("window_fixed", PerLength, 0.1, 0.05, 0.2),
+    // Ducts, fans and heating equipment
+    component!("ducts_in_unconditioned_space", Each, 90., 20., 200.),
+    component!("bathroom_fan_damper_closed", Each, 10., 2.5, 20.),
+    component!("bathroom_fan_damper_open", Each, 20., 6.5, 22.),
+    component!("kitchen_hood_damper_closed", Each, 5., 1.1, 7.),
+    component!("kitchen_hood_damper_open", Each, 40., 14., 72.),
+    component!("dryer_vent", Each, 3., 2.9, 7.),
+    component!("fireplace_damper_closed", Each, 40., 10., 90.),
+    component!("fireplace_damper_open", Each, 350., 140., 580.),
+];
+
+/// Finds a component in the catalogue by name
+pub fn find_component(name: &str) -> Result<&'static ComponentLeakage, String> {
+    COMPONENT_LEAKAGES
+        .iter()
+        .find(|c| c.name == name)
+        .ok_or_else(|| {
+            format!(
+                "Could not find component '{}' in the leakage catalogue",
+                name
+            )
+        })
+}
+
+/// Converts an effective leakage area (in $`cm^2`$, at 4 Pa) into a power law
+/// with a certain flow exponent, with
+/// $`C = ELA \sqrt{2 \Delta P_r / \rho}\ /\ \Delta P_r^n`$
+pub fn ela_to_power_law(area: Float, exponent: Float) -> PowerLaw {
+    let flow = area / 1e4 * (2. * ELA_REFERENCE_PRESSURE / REFERENCE_DENSITY).sqrt();
+    PowerLaw {
+        coefficient: flow / ELA_REFERENCE_PRESSURE.powf(exponent),
+        exponent,
+    }
+}
+
+/// A number of components of the same kind (e.g., 12 recessed lights,
+/// or 25 m of window crack)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComponentLeak {
+    /// The name of the component in the catalogue
+    pub name: String,
+    /// The number of components, or their area (m2) or length (m),
+    /// depending on the `unit` of the component
+    pub quantity: Float,
+}
+
+impl ComponentLeak {
+    /// The best estimate of the effective leakage area, in $`cm^2`$
+    pub fn leakage_area(&self) -> Result<Float, String> {
+        Ok(find_component(&self.name)?.best * self.quantity)
+    }
+
+    /// The power law that describes this leak
+    pub fn power_law(&self, exponent: Float) -> Result<PowerLaw, String> {
+        Ok(ela_to_power_law(self.leakage_area()?, exponent))
+    }
+}
+
+/// Adds up the effective leakage area (in $`cm^2`$, at 4 Pa) of a set of
+/// components, so that it can be used as an
+/// `Infiltration::EffectiveAirLeakageArea`
+pub fn total_leakage_area(leaks: &[ComponentLeak]) -> Result<Float, String> {
+    let mut area = 0.0;
+    for leak in leaks {
+        area += leak.leakage_area()?;
+    }
+    Ok(area)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catalogue() {
+        for (i, c) in COMPONENT_LEAKAGES.iter().enumerate() {
+            assert!(c.min <= c.best && c.best <= c.max, "{}", c.name);
+            // Names are unique
+            assert!(
+                COMPONENT_LEAKAGES[i + 1..].iter().all(|o| o.name != c.name),
+                "{}",
+                c.name
+            );
+        }
+        let light = find_component("recessed_light").unwrap();
+        assert_eq!(light.unit, LeakageUnit::Each);
+        assert!((light.best - 10.).abs() < 1e-12);
+        assert!(find_component("some unknown component").is_err());
+    }
+
+    #[test]
+    fn test_total_leakage_area() {
+        let leaks = vec![
+            ComponentLeak {
+                name: "recessed_light".to_string(),
+                quantity: 6.,
+            },
+            ComponentLeak {
+                name: "window_double_hung_weatherstripped".to_string(),
+                quantity: 20.,
+            },
+            ComponentLeak {
+                name: "ceiling".to_string(),
+                quantity: 100.,
+            },
+        ];
+        let area = total_leakage_area(&leaks).unwrap();
+        assert!((area - (60. + 13. + 180.)).abs() < 1e-9);
+
+        let unknown = vec![ComponentLeak {
+            name: "nope".to_string(),
+            quantity: 1.,
+        }];
+        assert!(total_leakage_area(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_ela_to_power_law() {
+        // At 4 Pa, the flow of an orifice with Cd = 1
+        let p = ela_to_power_law(100., DEFAULT_LEAKAGE_EXPONENT);
+        let q = p.coefficient * ELA_REFERENCE_PRESSURE.powf(DEFAULT_LEAKAGE_EXPONENT);
+        let exp = 0.01 * (2. * 4. / REFERENCE_DENSITY).sqrt();
+        assert!((q - exp).abs() < 1e-12);
+    }
+}
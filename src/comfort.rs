@@ -0,0 +1,170 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Adaptive thermal comfort, as used for operating windows in
+//! naturally ventilated buildings
+
+use crate::Float;
+
+/// The weight given to the previous running mean when a new day starts.
+/// EN 16798-1 recommends $`\alpha = 0.8`$.
+const RUNNING_MEAN_ALPHA: Float = 0.8;
+
+/// The adaptive comfort models and categories available
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdaptiveComfort {
+    /// EN 16798-1, Category I (high level of expectation)
+    En16798CategoryI,
+    /// EN 16798-1, Category II (normal level of expectation)
+    En16798CategoryII,
+    /// EN 16798-1, Category III (moderate level of expectation)
+    En16798CategoryIII,
+    /// ASHRAE 55, 80% acceptability limits
+    Ashrae55Acceptability80,
+    /// ASHRAE 55, 90% acceptability limits
+    Ashrae55Acceptability90,
+}
+
+impl AdaptiveComfort {
+    /// Calculates the upper limit of the operative temperature (in C)
+    /// for a certain running mean (or prevailing mean) outdoor
+    /// temperature (in C).
+    ///
+    /// The running mean is clamped to the range in which each
+    /// standard is applicable.
+    pub fn upper_limit(&self, running_mean: Float) -> Float {
+        match self {
+            AdaptiveComfort::En16798CategoryI
+            | AdaptiveComfort::En16798CategoryII
+            | AdaptiveComfort::En16798CategoryIII => {
+                let delta = match self {
+                    AdaptiveComfort::En16798CategoryI => 2.,
+                    AdaptiveComfort::En16798CategoryII => 3.,
+                    _ => 4.,
+                };
+                let rm = running_mean.max(10.).min(30.);
+                0.33 * rm + 18.8 + delta
+            }
+            AdaptiveComfort::Ashrae55Acceptability80 | AdaptiveComfort::Ashrae55Acceptability90 => {
+                let delta = if let AdaptiveComfort::Ashrae55Acceptability80 = self {
+                    3.5
+                } else {
+                    2.5
+                };
+                let rm = running_mean.max(10.).min(33.5);
+                0.31 * rm + 17.8 + delta
+            }
+        }
+    }
+}
+
+/// Keeps track of the exponentially weighted running mean of the daily
+/// mean outdoor temperature, $`\theta_{rm} = (1-\alpha)\theta_{ed-1} + \alpha\theta_{rm-1}`$
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunningMeanTemperature {
+    /// The running mean at the beginning of the current day
+    running_mean: Option<Float>,
+
+    /// The (month, day) being accumulated
+    current_day: Option<(usize, usize)>,
+
+    /// The sum of the outdoor temperatures registered during the current day
+    day_sum: Float,
+
+    /// The number of outdoor temperatures registered during the current day
+    day_count: usize,
+}
+
+impl RunningMeanTemperature {
+    /// Registers an outdoor temperature (in C) for a certain day.
+    pub fn update(&mut self, month: usize, day: usize, outdoor_temperature: Float) {
+        match self.current_day {
+            Some(d) if d == (month, day) => {}
+            Some(_) => {
+                // The day is over
+                let daily_mean = self.day_sum / self.day_count as Float;
+                self.running_mean = Some(match self.running_mean {
+                    Some(rm) => (1. - RUNNING_MEAN_ALPHA) * daily_mean + RUNNING_MEAN_ALPHA * rm,
+                    None => daily_mean,
+                });
+                self.day_sum = 0.0;
+                self.day_count = 0;
+                self.current_day = Some((month, day));
+            }
+            None => self.current_day = Some((month, day)),
+        }
+        self.day_sum += outdoor_temperature;
+        self.day_count += 1;
+    }
+
+    /// Returns the current running mean outdoor temperature, in C.
+    ///
+    /// Until a whole day has been registered, the mean of the
+    /// temperatures registered so far is returned.
+    pub fn value(&self) -> Option<Float> {
+        match self.running_mean {
+            Some(rm) => Some(rm),
+            None if self.day_count > 0 => Some(self.day_sum / self.day_count as Float),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upper_limit() {
+        let c = AdaptiveComfort::En16798CategoryII;
+        assert!((c.upper_limit(20.) - (0.33 * 20. + 21.8)).abs() < 1e-9);
+        // Clamped
+        assert!((c.upper_limit(35.) - c.upper_limit(30.)).abs() < 1e-9);
+        assert!((c.upper_limit(0.) - c.upper_limit(10.)).abs() < 1e-9);
+        assert!(
+            AdaptiveComfort::En16798CategoryI.upper_limit(20.)
+                < AdaptiveComfort::En16798CategoryIII.upper_limit(20.)
+        );
+
+        let c = AdaptiveComfort::Ashrae55Acceptability80;
+        assert!((c.upper_limit(20.) - (0.31 * 20. + 21.3)).abs() < 1e-9);
+        assert!(AdaptiveComfort::Ashrae55Acceptability90.upper_limit(20.) < c.upper_limit(20.));
+    }
+
+    #[test]
+    fn test_running_mean() {
+        let mut rm = RunningMeanTemperature::default();
+        assert!(rm.value().is_none());
+
+        // First day: 10 and 20
+        rm.update(1, 1, 10.);
+        assert!((rm.value().unwrap() - 10.).abs() < 1e-9);
+        rm.update(1, 1, 20.);
+        assert!((rm.value().unwrap() - 15.).abs() < 1e-9);
+
+        // Second day starts... running mean is the first daily mean
+        rm.update(1, 2, 30.);
+        assert!((rm.value().unwrap() - 15.).abs() < 1e-9);
+
+        // Third day
+        rm.update(1, 3, 0.);
+        assert!((rm.value().unwrap() - (0.2 * 30. + 0.8 * 15.)).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,513 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Design (i.e., peak) infiltration and ventilation heat losses, for sizing
+//! heating systems without marching through time.
+//!
+//! Spaces with [`En12831Infiltration`] parameters follow EN 12831. The rest
+//! solve the same balances as when simulating (i.e., their `Infiltration`,
+//! the leaks of their envelope or their EN 16798-7 zone, and the whole-house
+//! fan of their dwelling) at the design temperature difference and wind
+//! speed (e.g., ASHRAE design-day conditions). In both cases, their
+//! `mechanical_ventilation`, exhaust fans and DOAS outlets are added at full
+//! capacity, while windows are closed. The heat of whole-house fans is
+//! neglected, which errs on the safe side.
+
+use crate::doas::condition;
+use crate::en16798::{solve_building, En16798Flows, En16798Zone};
+use crate::eplus::{
+    design_flow_rate_at, effective_leakage_area_at, BLAST_COEFFICIENTS, DOE2_COEFFICIENTS,
+};
+use crate::leakage::solve_leaks;
+use crate::mechanical::{superpose, MakeUpAir};
+use crate::model::{distribute_leakage, group_standard_zones};
+use crate::options::AirFlowModelOptions;
+use crate::physics::{air_density, humidity_ratio, AIR_SPECIFIC_HEAT};
+use crate::resolvers::resolve_leakage_coefficients;
+use crate::Float;
+use simple_model::{Infiltration, SimpleModel};
+
+/// The volumetric heat capacity of air used by EN 12831 (0.34 Wh/(m3 K)), in J/(m3 K)
+const EN12831_HEAT_CAPACITY: Float = 0.34 * 3600.;
+
+/// The conditions for which heating systems are sized
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DesignConditions {
+    /// The indoor design temperature, in C
+    pub indoor_temperature: Float,
+
+    /// The outdoor design temperature (e.g., the 99.6% heating dry
+    /// bulb temperature of ASHRAE), in C
+    pub outdoor_temperature: Float,
+
+    /// The wind speed coincident with the outdoor design
+    /// temperature, in m/s
+    pub wind_speed: Float,
+
+    /// The wind direction coincident with the outdoor design temperature,
+    /// in degrees (clockwise from North). It is used by the Spaces whose
+    /// infiltration is calculated through the leaks of their envelope.
+    pub wind_direction: Float,
+}
+
+/// How shielded a Space is from the wind, after EN 12831
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum En12831Shielding {
+    /// Buildings in windy areas, high-rise buildings in city centres
+    None,
+    /// Buildings in the country with trees or other buildings around them, suburbs
+    Moderate,
+    /// Average height buildings in city centres, buildings in forests
+    Heavy,
+}
+
+/// The parameters EN 12831 needs for calculating the design
+/// infiltration of a Space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct En12831Infiltration {
+    /// The internal volume of the Space, in m3
+    pub volume: Float,
+
+    /// The air change rate at 50 Pa of the building, in 1/h
+    pub n50: Float,
+
+    /// How shielded the Space is from the wind
+    pub shielding: En12831Shielding,
+
+    /// The number of facades of the Space with openings to outdoors
+    pub exposed_openings: usize,
+
+    /// The height of the centre of the Space above the ground, in m
+    pub height_above_ground: Float,
+
+    /// The minimum air change rate required for hygienic reasons, in 1/h
+    pub minimum_air_change_rate: Float,
+}
+
+impl En12831Infiltration {
+    /// The shielding coefficient $`e`$
+    pub fn shielding_coefficient(&self) -> Float {
+        let (one, more) = match self.shielding {
+            En12831Shielding::None => (0.03, 0.05),
+            En12831Shielding::Moderate => (0.02, 0.03),
+            En12831Shielding::Heavy => (0.01, 0.02),
+        };
+        match self.exposed_openings {
+            0 => 0.0,
+            1 => one,
+            _ => more,
+        }
+    }
+
+    /// The height correction factor $`\epsilon`$
+    pub fn height_correction(&self) -> Float {
+        if self.height_above_ground <= 10. {
+            1.0
+        } else if self.height_above_ground <= 30. {
+            1.2
+        } else {
+            1.5
+        }
+    }
+
+    /// The design infiltration $`V_{inf} = 2 V n_{50} e \epsilon`$, in m3/s
+    pub fn infiltration(&self) -> Float {
+        2. * self.volume * self.n50 * self.shielding_coefficient() * self.height_correction()
+            / 3600.
+    }
+
+    /// The minimum outdoor airflow, in m3/s
+    pub fn minimum_airflow(&self) -> Float {
+        self.volume * self.minimum_air_change_rate / 3600.
+    }
+}
+
+/// The design outdoor airflows and heat loss of a Space
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpaceDesignLoad {
+    /// The name of the Space
+    pub space: String,
+
+    /// The name of the Building of the Space, if any
+    pub building: Option<String>,
+
+    /// The outdoor air entering through the envelope, in m3/s. As when
+    /// simulating, it includes the air of whole-house and EN 16798-7 fans.
+    pub infiltration: Float,
+
+    /// The outdoor air supplied by fans (i.e., by the mechanical
+    /// ventilation and DOAS outlets), in m3/s
+    pub ventilation: Float,
+
+    /// The heat lost by warming up the outdoor air, in W
+    pub heat_loss: Float,
+}
+
+/// The design outdoor airflows and heat loss of a Building
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildingDesignLoad {
+    /// The name of the Building
+    pub building: String,
+
+    /// The outdoor air entering through the envelope of its Spaces, in m3/s
+    pub infiltration: Float,
+
+    /// The outdoor air supplied by fans to its Spaces, in m3/s
+    pub ventilation: Float,
+
+    /// The heat lost by warming up the outdoor air, in W
+    pub heat_loss: Float,
+}
+
+/// The design loads of every Space and Building of a model
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DesignReport {
+    /// The loads of each Space
+    pub spaces: Vec<SpaceDesignLoad>,
+
+    /// The loads of each Building, adding up those of its Spaces
+    pub buildings: Vec<BuildingDesignLoad>,
+}
+
+/// Calculates the design infiltration and ventilation heat
+/// losses of the Spaces and Buildings of a model
+pub fn design_loads(
+    model: &SimpleModel,
+    options: &AirFlowModelOptions,
+    conditions: &DesignConditions,
+) -> Result<DesignReport, String> {
+    let t_in = conditions.indoor_temperature;
+    let t_out = conditions.outdoor_temperature;
+    let delta_t = t_in - t_out;
+    let wind_speed = conditions.wind_speed;
+
+    // The EN 16798-7 zones of each Building, with closed windows and
+    // fans at full capacity
+    let mut standard_flows: Vec<Option<En16798Flows>> = vec![None; model.spaces.len()];
+    for standard in group_standard_zones(model, options)?.iter() {
+        let mut zones = Vec::with_capacity(standard.zones.len());
+        for j in standard.zones.iter() {
+            let zone = &options.en16798_zones[*j];
+            zone.check()?;
+            zones.push(En16798Zone {
+                windows: Vec::new(),
+                schedule: None,
+                ..zone.clone()
+            });
+        }
+        let with_temperatures: Vec<(&En16798Zone, Float)> =
+            zones.iter().map(|zone| (zone, t_in)).collect();
+        let results = solve_building(
+            &with_temperatures,
+            0.0,
+            wind_speed,
+            t_out,
+            &standard.building,
+        );
+        for (zone, res) in zones.iter().zip(results.into_iter()) {
+            if let Some(i) = model.spaces.iter().position(|s| s.name == zone.space) {
+                standard_flows[i] = Some(res);
+            }
+        }
+    }
+    let leaky_spaces = distribute_leakage(model, options)?;
+
+    let mut report = DesignReport::default();
+    for (i, space) in model.spaces.iter().enumerate() {
+        let space_options = options.spaces.get(&space.name);
+
+        // The air supplied and extracted by fans, and the supplied air
+        // times its temperature
+        let (mut supply, mut exhaust, mut supply_heat) = (0.0, 0.0, 0.0);
+        if let Some(v) = space_options.and_then(|o| o.mechanical_ventilation.as_ref()) {
+            let (s, e) = v.flows(1.0);
+            let temperature = v.supply_temperature.unwrap_or(t_out);
+            let rise = v
+                .supply_fan
+                .map_or(0.0, |fan| fan.temperature_rise(temperature));
+            supply += s;
+            exhaust += e;
+            supply_heat += s * (temperature + rise);
+        }
+        for fan in space_options.iter().flat_map(|o| o.exhaust_fans.iter()) {
+            // Make-up air from a neighbour is already warm
+            if fan.make_up_air == MakeUpAir::Infiltration {
+                exhaust += fan.flow;
+            }
+        }
+        for doas in options.doas.iter() {
+            for outlet in doas.outlets.iter().filter(|o| o.space == space.name) {
+                let flow = outlet.flow_at(1.0);
+                let temperature = condition(flow, t_out, humidity_ratio(t_out), &doas.setpoint)
+                    .supply_temperature;
+                supply += flow;
+                exhaust += flow;
+                supply_heat += flow * (temperature + doas.temperature_rise(temperature));
+            }
+        }
+
+        let en12831 = space_options.and_then(|o| o.en12831);
+        let (infiltration, heat_loss) = if let Some(params) = en12831 {
+            // Unbalanced exhaust is made up through the envelope
+            let mut envelope = params.infiltration() + (exhaust - supply).max(0.0);
+            envelope += (params.minimum_airflow() - envelope - supply).max(0.0);
+            let heat_loss =
+                EN12831_HEAT_CAPACITY * (envelope * delta_t + supply * t_in - supply_heat);
+            (envelope, heat_loss)
+        } else {
+            let leaky = leaky_spaces.iter().find(|l| l.space_index == i);
+            let natural = if let Some(res) = &standard_flows[i] {
+                res.infiltration + res.supply
+            } else if let Some(leaky) = leaky {
+                solve_leaks(
+                    &leaky.leaks,
+                    leaky.local_wind_speed(wind_speed),
+                    conditions.wind_direction,
+                    t_in,
+                    t_out,
+                )
+                .infiltration()
+            } else {
+                match space.infiltration() {
+                    Ok(Infiltration::Constant { flow }) => *flow,
+                    Ok(Infiltration::Blast { flow }) => {
+                        let (a, b, c, d) = BLAST_COEFFICIENTS;
+                        design_flow_rate_at(*flow, a, b, c, d, delta_t, wind_speed)
+                    }
+                    Ok(Infiltration::Doe2 { flow }) => {
+                        let (a, b, c, d) = DOE2_COEFFICIENTS;
+                        design_flow_rate_at(*flow, a, b, c, d, delta_t, wind_speed)
+                    }
+                    Ok(Infiltration::DesignFlowRate { a, b, c, d, phi }) => {
+                        design_flow_rate_at(*phi, *a, *b, *c, *d, delta_t, wind_speed)
+                    }
+                    Ok(Infiltration::EffectiveAirLeakageArea { area }) => {
+                        let coefficients = resolve_leakage_coefficients(i, space, model, options)?;
+                        effective_leakage_area_at(
+                            *area,
+                            coefficients.wind,
+                            coefficients.stack,
+                            delta_t,
+                            wind_speed,
+                        )
+                    }
+                    Err(_) => 0.0,
+                }
+            };
+            // Whole-house fans deliver whatever the infiltration does not
+            for dwelling in options.dwellings.iter().filter(|d| d.space == space.name) {
+                let (s, e) = dwelling.fan.flows(dwelling.fan_flow(natural));
+                supply += s;
+                exhaust += e;
+                supply_heat += s * (t_out + dwelling.fan.temperature_rise(t_out));
+            }
+            let flows = superpose(natural, supply, exhaust);
+            let rho_cp = air_density(t_out) * AIR_SPECIFIC_HEAT;
+            let heat_loss = rho_cp * (flows.envelope * delta_t + supply * t_in - supply_heat);
+            (flows.envelope, heat_loss)
+        };
+
+        report.spaces.push(SpaceDesignLoad {
+            space: space.name.clone(),
+            building: space.building().ok().map(|b| b.to_string()),
+            infiltration,
+            ventilation: supply,
+            heat_loss,
+        });
+    }
+
+    for load in report.spaces.iter() {
+        let name = match &load.building {
+            Some(name) => name,
+            None => continue,
+        };
+        let index = match report.buildings.iter().position(|b| &b.building == name) {
+            Some(index) => index,
+            None => {
+                report.buildings.push(BuildingDesignLoad {
+                    building: name.clone(),
+                    infiltration: 0.0,
+                    ventilation: 0.0,
+                    heat_loss: 0.0,
+                });
+                report.buildings.len() - 1
+            }
+        };
+        let building = &mut report.buildings[index];
+        building.infiltration += load.infiltration;
+        building.ventilation += load.ventilation;
+        building.heat_loss += load.heat_loss;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doas::{Doas, DoasOutlet, DoasSetpoint};
+    use crate::mechanical::{ExhaustFan, FanOperation, MechanicalVentilation};
+    use crate::options::SpaceAirFlowOptions;
+    use crate::profile::HourlyProfile;
+    use crate::residential_ventilation::{Dwelling, WholeHouseFan, WholeHouseFanKind};
+    use simple_model::Space;
+
+    const CONDITIONS: DesignConditions = DesignConditions {
+        indoor_temperature: 20.,
+        outdoor_temperature: -10.,
+        wind_speed: 4.47,
+        wind_direction: 0.,
+    };
+
+    fn en12831() -> En12831Infiltration {
+        En12831Infiltration {
+            volume: 100.,
+            n50: 6.,
+            shielding: En12831Shielding::Moderate,
+            exposed_openings: 2,
+            height_above_ground: 5.,
+            minimum_air_change_rate: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_en12831_infiltration() {
+        let params = en12831();
+        // 2 * 100 * 6 * 0.03 = 36 m3/h
+        assert!((params.infiltration() - 0.01).abs() < 1e-12);
+        assert!((params.minimum_airflow() - 50. / 3600.).abs() < 1e-12);
+
+        let mut tall = params;
+        tall.height_above_ground = 40.;
+        assert!((tall.height_correction() - 1.5).abs() < 1e-12);
+        tall.exposed_openings = 0;
+        assert!(tall.infiltration().abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_design_loads() {
+        let mut simple_model = SimpleModel::default();
+        let mut space = Space::new("ashrae".to_string());
+        space.set_infiltration(Infiltration::Doe2 { flow: 1. });
+        simple_model.add_space(space);
+        simple_model.add_space(Space::new("en12831".to_string()));
+
+        let mut options = AirFlowModelOptions::default();
+        let mut space_options = SpaceAirFlowOptions::default();
+        space_options.en12831 = Some(en12831());
+        options.spaces.insert("en12831".to_string(), space_options);
+
+        let report = design_loads(&simple_model, &options, &CONDITIONS).unwrap();
+        assert!(report.buildings.is_empty());
+
+        // DOE-2 coefficients at the design wind speed
+        let ashrae = &report.spaces[0];
+        let exp = 0.224 * 4.47;
+        assert!((ashrae.infiltration - exp).abs() < 1e-12);
+        let rho_cp = air_density(-10.) * AIR_SPECIFIC_HEAT;
+        assert!((ashrae.heat_loss - rho_cp * exp * 30.).abs() < 1e-6);
+
+        // The minimum air change rate governs
+        let en = &report.spaces[1];
+        assert!((en.infiltration - 50. / 3600.).abs() < 1e-12);
+        assert!((en.heat_loss - 0.34 * 50. * 30.).abs() < 1e-9);
+
+        // Supply air at 16 C adds what it takes to bring it to 20 C
+        let space_options = options.spaces.get_mut("en12831").unwrap();
+        space_options.mechanical_ventilation = Some(MechanicalVentilation {
+            supply_flow: 0.02,
+            exhaust_flow: 0.03,
+            supply_temperature: Some(16.),
+            ..MechanicalVentilation::default()
+        });
+        let report = design_loads(&simple_model, &options, &CONDITIONS).unwrap();
+        let en = &report.spaces[1];
+        assert!((en.infiltration - 0.02).abs() < 1e-12);
+        let exp = EN12831_HEAT_CAPACITY * (0.02 * 30. + 0.02 * 4.);
+        assert!((en.heat_loss - exp).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_design_fans() {
+        let mut simple_model = SimpleModel::default();
+        let mut space = Space::new("house".to_string());
+        space.set_infiltration(Infiltration::Constant { flow: 0.01 });
+        simple_model.add_space(space);
+
+        // A kitchen hood at full capacity...
+        let mut options = AirFlowModelOptions::default();
+        let mut space_options = SpaceAirFlowOptions::default();
+        space_options.exhaust_fans.push(ExhaustFan {
+            flow: 0.02,
+            operation: FanOperation::Occupancy,
+            fan: None,
+            make_up_air: MakeUpAir::Infiltration,
+        });
+        options.spaces.insert("house".to_string(), space_options);
+        let report = design_loads(&simple_model, &options, &CONDITIONS).unwrap();
+        let house = &report.spaces[0];
+        let exp = (0.01 as Float).hypot(0.02);
+        assert!((house.infiltration - exp).abs() < 1e-12);
+
+        // ... and a whole-house exhaust fan topping the infiltration up
+        options.dwellings.push(Dwelling {
+            space: "house".to_string(),
+            floor_area: 150.,
+            bedrooms: 3,
+            height: 2.5,
+            weather_shielding_factor: 0.5,
+            leakage: None,
+            exposed_fraction: 1.0,
+            fan: WholeHouseFan {
+                kind: WholeHouseFanKind::Exhaust,
+                capacity: None,
+                fan: None,
+            },
+            local_exhaust: Vec::new(),
+        });
+        let report = design_loads(&simple_model, &options, &CONDITIONS).unwrap();
+        let house = &report.spaces[0];
+        // The exhaust fan moves sqrt(0.0365^2 - 0.01^2), which adds to the local exhaust
+        let fan_flow = ((0.0365 as Float).powi(2) - 0.01 * 0.01).sqrt();
+        let exp = (0.01 as Float).hypot(fan_flow + 0.02);
+        assert!((house.infiltration - exp).abs() < 1e-12);
+
+        // A DOAS supplies (and extracts) air at 18 C
+        options.dwellings.clear();
+        options.doas.push(Doas {
+            setpoint: DoasSetpoint::neutral(18., 22.),
+            outlets: vec![DoasOutlet {
+                space: "house".to_string(),
+                flow: 0.05,
+                minimum_flow: 0.0,
+            }],
+            fan: None,
+            schedule: Some(HourlyProfile::constant(0.)),
+        });
+        let report = design_loads(&simple_model, &options, &CONDITIONS).unwrap();
+        let house = &report.spaces[0];
+        assert!((house.ventilation - 0.05).abs() < 1e-12);
+        let flows = superpose(0.01, 0.05, 0.07);
+        assert!((house.infiltration - flows.envelope).abs() < 1e-12);
+        let rho_cp = air_density(-10.) * AIR_SPECIFIC_HEAT;
+        let exp = rho_cp * (flows.envelope * 30. + 0.05 * 2.);
+        assert!((house.heat_loss - exp).abs() < 1e-6);
+    }
+}
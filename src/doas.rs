@@ -0,0 +1,340 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Dedicated outdoor air systems (DOAS), which condition outdoor air
+//! before distributing it to the Spaces they serve.
+
+use crate::mechanical::Fan;
+use crate::physics::{air_density, dew_point, enthalpy, humidity_ratio, moist_air_specific_heat};
+use crate::profile::HourlyProfile;
+use crate::Float;
+
+/// The conditions to which a DOAS brings the outdoor air
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DoasSetpoint {
+    /// The minimum supply temperature, in C
+    pub min_temperature: Float,
+
+    /// The maximum supply temperature, in C
+    pub max_temperature: Float,
+
+    /// The maximum supply humidity ratio, in kg/kg. If `None`,
+    /// the air is not dehumidified.
+    pub max_humidity_ratio: Option<Float>,
+}
+
+impl DoasSetpoint {
+    /// Supplies air at a temperature close to the room's, without
+    /// dehumidifying it
+    pub fn neutral(min_temperature: Float, max_temperature: Float) -> Self {
+        Self {
+            min_temperature,
+            max_temperature,
+            max_humidity_ratio: None,
+        }
+    }
+
+    /// Supplies cold and dry air, at a certain temperature and
+    /// dew point temperature (in C)
+    pub fn cold_dry(temperature: Float, dew_point_temperature: Float) -> Self {
+        Self {
+            min_temperature: temperature,
+            max_temperature: temperature,
+            max_humidity_ratio: Some(humidity_ratio(dew_point_temperature)),
+        }
+    }
+}
+
+/// The result of conditioning the outdoor air during a timestep
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CoilLoads {
+    /// The heat added by the heating coil, in W
+    pub heating: Float,
+
+    /// The heat removed by the cooling coil (sensible and latent), in W
+    pub cooling: Float,
+
+    /// The temperature of the supplied air, in C
+    pub supply_temperature: Float,
+
+    /// The humidity ratio of the supplied air, in kg/kg
+    pub supply_humidity_ratio: Float,
+}
+
+/// The energy used by a DOAS, accumulated over the timesteps
+/// simulated so far
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DoasEnergy {
+    /// The heat added by the heating coil, in J
+    pub heating: Float,
+
+    /// The heat removed by the cooling coil (sensible and latent), in J
+    pub cooling: Float,
+
+    /// The electricity used by the fan, in J
+    pub fan: Float,
+}
+
+impl DoasEnergy {
+    /// Adds the loads (in W) and the fan power (in W) of a timestep
+    /// lasting `dt` seconds
+    pub fn add(&mut self, loads: &CoilLoads, fan_power: Float, dt: Float) {
+        self.heating += loads.heating * dt;
+        self.cooling += loads.cooling * dt;
+        self.fan += fan_power * dt;
+    }
+}
+
+/// Brings a certain flow (in m3/s) of outdoor air at a certain temperature
+/// (in C) and humidity ratio (in kg/kg) to a setpoint.
+///
+/// Air that is too humid is cooled down to the dew point of the maximum
+/// humidity ratio and then reheated if needed. Otherwise, it is only heated
+/// or cooled sensibly.
+pub fn condition(
+    flow: Float,
+    outdoor_temperature: Float,
+    outdoor_humidity_ratio: Float,
+    setpoint: &DoasSetpoint,
+) -> CoilLoads {
+    let m = air_density(outdoor_temperature) * flow;
+    let clamp = |t: Float| {
+        t.max(setpoint.min_temperature)
+            .min(setpoint.max_temperature)
+    };
+
+    // Dehumidification
+    let (coil_temperature, supply_humidity_ratio, mut cooling) = match setpoint.max_humidity_ratio {
+        Some(w_max) if outdoor_humidity_ratio > w_max => {
+            let t_coil = dew_point(w_max).min(outdoor_temperature);
+            let q = m
+                * (enthalpy(outdoor_temperature, outdoor_humidity_ratio) - enthalpy(t_coil, w_max));
+            (t_coil, w_max, q.max(0.0))
+        }
+        _ => (outdoor_temperature, outdoor_humidity_ratio, 0.0),
+    };
+
+    // Sensible heating or cooling
+    let supply_temperature = clamp(coil_temperature);
+    let cp = moist_air_specific_heat(supply_humidity_ratio);
+    let sensible = m * cp * (supply_temperature - coil_temperature);
+    let mut heating = 0.0;
+    if sensible > 0.0 {
+        heating = sensible;
+    } else {
+        cooling -= sensible;
+    }
+
+    CoilLoads {
+        heating,
+        cooling,
+        supply_temperature,
+        supply_humidity_ratio,
+    }
+}
+
+/// The outdoor air delivered by a DOAS to one of its Spaces
+#[derive(Debug, Clone, PartialEq)]
+pub struct DoasOutlet {
+    /// The name of the Space
+    pub space: String,
+    /// The design flow delivered to the Space, in m3/s
+    pub flow: Float,
+    /// The flow (in m3/s) delivered to the Space whatever the `schedule`
+    /// of the system (e.g., the minimum required by ASHRAE 62.1)
+    pub minimum_flow: Float,
+}
+
+impl DoasOutlet {
+    /// The flow (in m3/s) delivered when the system runs a certain
+    /// fraction of the time
+    pub fn flow_at(&self, running_fraction: Float) -> Float {
+        (self.flow * running_fraction).max(self.minimum_flow)
+    }
+}
+
+/// A dedicated outdoor air system
+#[derive(Debug, Clone, PartialEq)]
+pub struct Doas {
+    /// The conditions to which the outdoor air is brought
+    pub setpoint: DoasSetpoint,
+
+    /// The Spaces served by the system
+    pub outlets: Vec<DoasOutlet>,
+
+    /// The supply fan, if its power and heat are to be accounted for.
+    /// It is placed after the coils, so its heat reaches the Spaces.
+    pub fan: Option<Fan>,
+
+    /// The fraction of each hour of the day during which the system runs.
+    /// If `None`, the system always runs.
+    pub schedule: Option<HourlyProfile>,
+}
+
+impl Doas {
+    /// Returns the fraction of a certain hour of the day during
+    /// which the system runs
+    pub fn running_fraction(&self, hour: Float) -> Float {
+        match &self.schedule {
+            Some(profile) => profile.at(hour),
+            None => 1.0,
+        }
+    }
+
+    /// The design flow of the system, in m3/s
+    pub fn design_flow(&self) -> Float {
+        self.outlets.iter().map(|o| o.flow).sum()
+    }
+
+    /// The temperature rise (in K) caused by the fan in the air leaving
+    /// the coils at a certain temperature (in C)
+    pub fn temperature_rise(&self, supply_temperature: Float) -> Float {
+        self.fan
+            .map_or(0.0, |fan| fan.temperature_rise(supply_temperature))
+    }
+
+    /// The flow of outdoor air (in m3/s) conditioned when the system
+    /// runs a certain fraction of the time
+    pub fn flow(&self, running_fraction: Float) -> Float {
+        self.outlets
+            .iter()
+            .map(|o| o.flow_at(running_fraction))
+            .sum()
+    }
+
+    /// The electric power of the fan (in W) when the system runs a
+    /// certain fraction of the time
+    pub fn power(&self, running_fraction: Float) -> Float {
+        self.fan
+            .map_or(0.0, |fan| fan.power(self.flow(running_fraction)))
+    }
+
+    /// Checks whether the system makes sense
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if let Some(fan) = &self.fan {
+            fan.check("a DOAS")?;
+        }
+        if let Some(profile) = &self.schedule {
+            profile.check("The schedule of a DOAS")?;
+        }
+        if self
+            .outlets
+            .iter()
+            .any(|o| o.flow < 0.0 || o.minimum_flow < 0.0)
+        {
+            return Err("A DOAS has an outlet with a negative flow".to_string());
+        }
+        if self.setpoint.min_temperature > self.setpoint.max_temperature {
+            return Err(
+                "The minimum supply temperature of a DOAS is higher than its maximum".to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mechanical::FanPower;
+
+    #[test]
+    fn test_neutral() {
+        let setpoint = DoasSetpoint::neutral(18., 22.);
+
+        // Winter: heating only
+        let w = humidity_ratio(-5.);
+        let loads = condition(1., 0., w, &setpoint);
+        assert!((loads.supply_temperature - 18.).abs() < 1e-12);
+        assert!(loads.cooling.abs() < 1e-12);
+        let exp = air_density(0.) * moist_air_specific_heat(w) * 18.;
+        assert!((loads.heating - exp).abs() < 1e-6);
+
+        // Mild: nothing to do
+        let loads = condition(1., 20., w, &setpoint);
+        assert!((loads.supply_temperature - 20.).abs() < 1e-12);
+        assert!(loads.heating.abs() < 1e-12 && loads.cooling.abs() < 1e-12);
+
+        // Summer: sensible cooling, no dehumidification
+        let w = humidity_ratio(20.);
+        let loads = condition(1., 30., w, &setpoint);
+        assert!((loads.supply_temperature - 22.).abs() < 1e-12);
+        assert!((loads.supply_humidity_ratio - w).abs() < 1e-12);
+        assert!(loads.cooling > 0.);
+    }
+
+    #[test]
+    fn test_cold_dry() {
+        let setpoint = DoasSetpoint::cold_dry(13., 10.);
+        let w_out = humidity_ratio(22.);
+        let loads = condition(1., 32., w_out, &setpoint);
+        let w_max = humidity_ratio(10.);
+        assert!((loads.supply_humidity_ratio - w_max).abs() < 1e-12);
+        assert!((loads.supply_temperature - 13.).abs() < 1e-12);
+
+        // Cooled to the dew point and reheated to 13 C
+        let m = air_density(32.);
+        let cooling = m * (enthalpy(32., w_out) - enthalpy(10., w_max));
+        assert!((loads.cooling - cooling).abs() < 1e-3);
+        let heating = m * moist_air_specific_heat(w_max) * 3.;
+        assert!((loads.heating - heating).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_check() {
+        let mut doas = Doas {
+            setpoint: DoasSetpoint::neutral(18., 22.),
+            outlets: vec![
+                DoasOutlet {
+                    space: "a".to_string(),
+                    flow: 0.1,
+                    minimum_flow: 0.0,
+                },
+                DoasOutlet {
+                    space: "b".to_string(),
+                    flow: 0.2,
+                    minimum_flow: 0.12,
+                },
+            ],
+            fan: None,
+            schedule: None,
+        };
+        assert!(doas.check().is_ok());
+        assert!((doas.design_flow() - 0.3).abs() < 1e-12);
+        assert!(doas.power(1.).abs() < 1e-12);
+
+        doas.fan = Some(Fan {
+            power: FanPower::SpecificFanPower(1000.),
+            heat_to_air: 1.0,
+        });
+        assert!((doas.power(0.5) - 170.).abs() < 1e-9);
+        assert!((doas.flow(0.) - 0.12).abs() < 1e-12);
+        assert!(doas.temperature_rise(13.) > 0.);
+        let mut energy = DoasEnergy::default();
+        let loads = condition(0.3, 0., humidity_ratio(-5.), &doas.setpoint);
+        energy.add(&loads, doas.power(1.), 3600.);
+        energy.add(&loads, doas.power(1.), 3600.);
+        assert!((energy.heating - 7200. * loads.heating).abs() < 1e-6);
+        assert!((energy.fan - 7200. * 300.).abs() < 1e-6);
+
+        doas.setpoint = DoasSetpoint::neutral(22., 18.);
+        assert!(doas.check().is_err());
+    }
+}
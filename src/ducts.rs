@@ -0,0 +1,384 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Leakage of air distribution ducts. Supply air that leaks outside of the
+//! conditioned volume is lost, and return air that leaks in is drawn from
+//! wherever the ducts are. When the two do not match, the served Space is
+//! depressurised (or pressurised), which changes its infiltration.
+
+use crate::catalogue::{ela_to_power_law, ComponentLeak, DEFAULT_LEAKAGE_EXPONENT};
+use crate::mechanical::Fan;
+use crate::physics::{air_density, AIR_SPECIFIC_HEAT};
+use crate::profile::HourlyProfile;
+use crate::Float;
+
+/// Where a duct runs
+#[derive(Debug, Clone, PartialEq)]
+pub enum DuctLocation {
+    /// Within the conditioned volume, so leaks do not matter
+    Conditioned,
+    /// Within an unconditioned Space (e.g., an attic or a crawlspace),
+    /// given by its name
+    Space(String),
+    /// Outdoors
+    Outdoors,
+}
+
+/// How much a duct leaks
+#[derive(Debug, Clone, PartialEq)]
+pub enum DuctLeakage {
+    /// A fraction of the flow through the duct
+    Fraction(Float),
+    /// An effective leakage area (in $`cm^2`$, at 4 Pa) under a certain
+    /// operating pressure difference between the duct and its surroundings
+    Area {
+        /// The effective leakage area, in $`cm^2`$
+        area: Float,
+        /// The operating pressure difference, in Pa
+        pressure: Float,
+    },
+    /// An entry of the [`catalogue`](crate::catalogue) (e.g.,
+    /// `"ducts_in_unconditioned_space"`) under a certain operating pressure
+    /// difference between the duct and its surroundings
+    Catalogue {
+        /// The catalogue entry and its quantity
+        leak: ComponentLeak,
+        /// The operating pressure difference, in Pa
+        pressure: Float,
+    },
+}
+
+/// The flow (in m3/s) leaked by an effective leakage area (in $`cm^2`$, at
+/// 4 Pa) under a certain pressure difference (in Pa) while the system runs
+/// a fraction of the timestep, which cannot exceed the `flow` through the duct
+fn area_leak(area: Float, pressure: Float, flow: Float, running_fraction: Float) -> Float {
+    let p = ela_to_power_law(area, DEFAULT_LEAKAGE_EXPONENT);
+    (running_fraction * p.coefficient * pressure.powf(p.exponent)).min(flow)
+}
+
+impl DuctLeakage {
+    /// Calculates the leaked flow (in m3/s) for a certain flow through the
+    /// duct (m3/s), averaged over a timestep during which the system runs a
+    /// certain fraction of the time
+    pub fn leaked_flow(&self, flow: Float, running_fraction: Float) -> Float {
+        match self {
+            DuctLeakage::Fraction(f) => flow * f,
+            DuctLeakage::Area { area, pressure } => {
+                area_leak(*area, *pressure, flow, running_fraction)
+            }
+            DuctLeakage::Catalogue { leak, pressure } => {
+                // Unknown names are rejected when the model is built
+                let area = leak.leakage_area().unwrap_or(0.0);
+                area_leak(area, *pressure, flow, running_fraction)
+            }
+        }
+    }
+}
+
+/// The air distribution system serving a Space
+#[derive(Debug, Clone, PartialEq)]
+pub struct DuctSystem {
+    /// The name of the Space served by the system
+    pub space: String,
+
+    /// The flow supplied by the system when running, in m3/s
+    pub supply_flow: Float,
+
+    /// The temperature of the supplied air before the heat of the `fan`
+    /// is added, in C
+    pub supply_temperature: Float,
+
+    /// The fan moving the air, if its power is to be accounted for
+    pub fan: Option<Fan>,
+
+    /// The leakage of the supply ducts
+    pub supply_leakage: DuctLeakage,
+
+    /// Where the supply ducts run
+    pub supply_location: DuctLocation,
+
+    /// The leakage of the return ducts
+    pub return_leakage: DuctLeakage,
+
+    /// Where the return ducts run
+    pub return_location: DuctLocation,
+
+    /// The fraction of each hour of the day during which the system runs.
+    /// If `None`, the system always runs.
+    pub schedule: Option<HourlyProfile>,
+}
+
+/// The flows caused by the leakage of a duct system during a timestep
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DuctLeakageFlows {
+    /// The supply air lost outside of the conditioned volume, in m3/s
+    pub supply_leak: Float,
+
+    /// The air drawn into the return ducts from outside of the
+    /// conditioned volume, in m3/s
+    pub return_leak: Float,
+
+    /// The imbalance between the two leaks, in m3/s. Positive values
+    /// depressurise the served Space.
+    pub imbalance: Float,
+
+    /// The heat lost (positive) or gained (negative) because of the
+    /// leaks, in W
+    pub energy_loss: Float,
+}
+
+impl DuctSystem {
+    /// Returns the fraction of a certain hour of the day during
+    /// which the system runs
+    pub fn running_fraction(&self, hour: Float) -> Float {
+        match &self.schedule {
+            Some(profile) => profile.at(hour),
+            None => 1.0,
+        }
+    }
+
+    /// The temperature of the supplied air (in C), including the heat
+    /// of the fan
+    pub fn delivered_temperature(&self) -> Float {
+        self.supply_temperature
+            + self
+                .fan
+                .map_or(0.0, |fan| fan.temperature_rise(self.supply_temperature))
+    }
+
+    /// The electric power of the fan (in W) when the system runs a
+    /// certain fraction of the time
+    pub fn power(&self, running_fraction: Float) -> Float {
+        self.fan
+            .map_or(0.0, |fan| fan.power(self.supply_flow * running_fraction))
+    }
+
+    /// Checks whether the system makes sense
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if let Some(fan) = &self.fan {
+            fan.check(&format!("the duct system serving Space '{}'", self.space))?;
+        }
+        if let Some(profile) = &self.schedule {
+            profile.check(&format!(
+                "The schedule of the duct system serving Space '{}'",
+                self.space
+            ))?;
+        }
+        for leakage in [&self.supply_leakage, &self.return_leakage].iter() {
+            match leakage {
+                DuctLeakage::Fraction(f) => {
+                    if !(0.0..=1.0).contains(f) {
+                        return Err(format!(
+                            "The duct leakage fraction of the system serving Space '{}' must be between 0 and 1",
+                            self.space
+                        ));
+                    }
+                }
+                DuctLeakage::Catalogue { leak, pressure } => {
+                    leak.leakage_area()?;
+                    self.check_pressure(*pressure)?;
+                }
+                DuctLeakage::Area { area, pressure } => {
+                    if *area < 0.0 {
+                        return Err(format!(
+                            "The duct leakage area of the system serving Space '{}' cannot be negative",
+                            self.space
+                        ));
+                    }
+                    self.check_pressure(*pressure)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the operating pressure difference of a leaky duct
+    fn check_pressure(&self, pressure: Float) -> Result<(), String> {
+        if pressure < 0.0 {
+            return Err(format!(
+                "The duct operating pressure of the system serving Space '{}' cannot be negative",
+                self.space
+            ));
+        }
+        Ok(())
+    }
+
+    /// Calculates the leakage flows and the resulting energy loss.
+    ///
+    /// * `running_fraction`: The fraction of the timestep during which the system runs
+    /// * `space_temperature`: The temperature of the served Space, in C
+    /// * `return_surroundings_temperature`: The temperature around the return ducts, in C
+    pub fn leakage_flows(
+        &self,
+        running_fraction: Float,
+        space_temperature: Float,
+        return_surroundings_temperature: Float,
+    ) -> DuctLeakageFlows {
+        let flow = self.supply_flow * running_fraction;
+        let supply_leak = match self.supply_location {
+            DuctLocation::Conditioned => 0.0,
+            _ => self.supply_leakage.leaked_flow(flow, running_fraction),
+        };
+        let return_leak = match self.return_location {
+            DuctLocation::Conditioned => 0.0,
+            _ => self.return_leakage.leaked_flow(flow, running_fraction),
+        };
+
+        // Lost supply air would have been delivered at the supply temperature,
+        // and return air drawn from outside must be brought to the Space's
+        let rho_cp = air_density(space_temperature) * AIR_SPECIFIC_HEAT;
+        let energy_loss = rho_cp
+            * (supply_leak * (self.delivered_temperature() - space_temperature)
+                + return_leak * (space_temperature - return_surroundings_temperature));
+
+        DuctLeakageFlows {
+            supply_leak,
+            return_leak,
+            imbalance: supply_leak - return_leak,
+            energy_loss,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn system() -> DuctSystem {
+        DuctSystem {
+            space: "house".to_string(),
+            supply_flow: 0.3,
+            supply_temperature: 40.,
+            fan: None,
+            supply_leakage: DuctLeakage::Fraction(0.1),
+            supply_location: DuctLocation::Space("attic".to_string()),
+            return_leakage: DuctLeakage::Fraction(0.05),
+            return_location: DuctLocation::Space("attic".to_string()),
+            schedule: None,
+        }
+    }
+
+    #[test]
+    fn test_leaked_flow() {
+        assert!((DuctLeakage::Fraction(0.1).leaked_flow(0.3, 1.) - 0.03).abs() < 1e-12);
+
+        // 100 cm2 at 4 Pa leaks as an orifice with Cd = 1
+        let leak = DuctLeakage::Area {
+            area: 100.,
+            pressure: 4.,
+        };
+        let exp = 0.01 * (2. * 4. / crate::elements::REFERENCE_DENSITY).sqrt();
+        assert!((leak.leaked_flow(1., 1.) - exp).abs() < 1e-9);
+        // ... but never more than what flows through the duct
+        assert!((leak.leaked_flow(0.001, 1.) - 0.001).abs() < 1e-12);
+        // ... and only while the system runs
+        assert!((leak.leaked_flow(0.1, 0.1) - 0.1 * exp).abs() < 1e-9);
+
+        // Catalogue entries are converted into their area
+        let catalogue = DuctLeakage::Catalogue {
+            leak: ComponentLeak {
+                name: "ducts_in_unconditioned_space".to_string(),
+                quantity: 1.,
+            },
+            pressure: 4.,
+        };
+        let area = DuctLeakage::Area {
+            area: 90.,
+            pressure: 4.,
+        };
+        assert!((catalogue.leaked_flow(1., 1.) - area.leaked_flow(1., 1.)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_leakage_flows() {
+        let attic = system();
+        assert!(attic.check().is_ok());
+        // Heating, with a cold attic
+        let res = attic.leakage_flows(1.0, 20., 0.);
+        assert!((res.supply_leak - 0.03).abs() < 1e-12);
+        assert!((res.return_leak - 0.015).abs() < 1e-12);
+        assert!((res.imbalance - 0.015).abs() < 1e-12);
+        let rho_cp = air_density(20.) * AIR_SPECIFIC_HEAT;
+        let exp = rho_cp * (0.03 * 20. + 0.015 * 20.);
+        assert!((res.energy_loss - exp).abs() < 1e-9);
+
+        // Not running
+        let res = attic.leakage_flows(0.0, 20., 0.);
+        assert!(res.supply_leak.abs() < 1e-12);
+        assert!(res.energy_loss.abs() < 1e-12);
+
+        // Ducts within the conditioned volume do not matter
+        let mut conditioned = attic;
+        conditioned.supply_location = DuctLocation::Conditioned;
+        conditioned.return_location = DuctLocation::Conditioned;
+        let res = conditioned.leakage_flows(1.0, 20., 0.);
+        assert!(res.imbalance.abs() < 1e-12);
+        assert!(res.energy_loss.abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_fan() {
+        let mut heated = system();
+        assert!(heated.power(1.).abs() < 1e-12);
+        assert!((heated.delivered_temperature() - 40.).abs() < 1e-12);
+        heated.fan = Some(Fan {
+            power: crate::mechanical::FanPower::SpecificFanPower(1000.),
+            heat_to_air: 1.0,
+        });
+        assert!(heated.check().is_ok());
+        assert!((heated.power(0.5) - 150.).abs() < 1e-9);
+        let rise = 1000. / (air_density(40.) * AIR_SPECIFIC_HEAT);
+        assert!((heated.delivered_temperature() - 40. - rise).abs() < 1e-9);
+        // The lost supply air carries the heat of the fan
+        let res = heated.leakage_flows(1.0, 20., 20.);
+        let exp = air_density(20.) * AIR_SPECIFIC_HEAT * 0.03 * (20. + rise);
+        assert!((res.energy_loss - exp).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_check() {
+        let mut leaky = system();
+        leaky.supply_leakage = DuctLeakage::Fraction(1.5);
+        assert!(leaky.check().is_err());
+        let mut unscheduled = system();
+        unscheduled.schedule = Some(vec![1.; 3].into());
+        assert!(unscheduled.check().is_err());
+        let mut unknown = system();
+        unknown.return_leakage = DuctLeakage::Catalogue {
+            leak: ComponentLeak {
+                name: "nope".to_string(),
+                quantity: 1.,
+            },
+            pressure: 25.,
+        };
+        assert!(unknown.check().is_err());
+        let mut negative = system();
+        negative.supply_leakage = DuctLeakage::Area {
+            area: 100.,
+            pressure: -4.,
+        };
+        assert!(negative.check().is_err());
+        negative.supply_leakage = DuctLeakage::Area {
+            area: -100.,
+            pressure: 4.,
+        };
+        assert!(negative.check().is_err());
+    }
+}
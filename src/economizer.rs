@@ -0,0 +1,240 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Airside economizers, which bring in more outdoor air than the minimum
+//! when it can cool a Space for free.
+
+use crate::physics::{enthalpy, humidity_ratio};
+use crate::Float;
+
+/// How an economizer decides whether outdoor air can be used for cooling
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EconomizerControl {
+    /// Outdoor air is used when its temperature is below a limit (in C)
+    FixedDryBulb {
+        /// The high limit, in C
+        high_limit: Float,
+    },
+    /// Outdoor air is used when it is cooler than the return air
+    DifferentialDryBulb,
+    /// Outdoor air is used when its enthalpy is below a limit (in J/kg)
+    FixedEnthalpy {
+        /// The high limit, in J/kg
+        high_limit: Float,
+    },
+    /// Outdoor air is used when its enthalpy is lower than that of the return air
+    DifferentialEnthalpy,
+}
+
+/// The state of the air on both sides of an economizer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EconomizerConditions {
+    /// The outdoor temperature, in C
+    pub outdoor_temperature: Float,
+    /// The outdoor humidity ratio, in kg/kg
+    pub outdoor_humidity_ratio: Float,
+    /// The return air (i.e., Space) temperature, in C
+    pub return_temperature: Float,
+    /// The return air humidity ratio, in kg/kg
+    pub return_humidity_ratio: Float,
+}
+
+/// An outdoor air economizer serving a Space
+#[derive(Debug, Clone, PartialEq)]
+pub struct Economizer {
+    /// The name of the Space served by the economizer
+    pub space: String,
+
+    /// How the economizer decides whether outdoor air can be used
+    pub control: EconomizerControl,
+
+    /// The outdoor dry bulb temperature (in C) above which the economizer
+    /// is locked out, regardless of the `control`
+    pub high_limit_lockout: Option<Float>,
+
+    /// The outdoor dry bulb temperature (in C) below which the economizer
+    /// is locked out (e.g., to avoid freezing coils)
+    pub low_limit_lockout: Option<Float>,
+
+    /// The minimum outdoor airflow, in m3/s
+    pub minimum_flow: Float,
+
+    /// The maximum outdoor airflow, in m3/s
+    pub maximum_flow: Float,
+
+    /// The Space temperature (in C) above which the Space needs cooling
+    pub cooling_setpoint: Float,
+
+    /// The temperature range (in K) above the `cooling_setpoint` over which
+    /// the outdoor airflow goes from the minimum to the maximum
+    pub throttling_range: Float,
+
+    /// The dew point temperature (in C) assumed for the return air, which
+    /// is used by the enthalpy controls
+    pub return_dew_point: Float,
+}
+
+impl Economizer {
+    /// Checks whether outdoor air can be used for cooling
+    pub fn is_available(&self, conditions: &EconomizerConditions) -> bool {
+        let t_out = conditions.outdoor_temperature;
+        if let Some(limit) = self.high_limit_lockout {
+            if t_out > limit {
+                return false;
+            }
+        }
+        if let Some(limit) = self.low_limit_lockout {
+            if t_out < limit {
+                return false;
+            }
+        }
+        let h_out = enthalpy(t_out, conditions.outdoor_humidity_ratio);
+        let h_return = enthalpy(
+            conditions.return_temperature,
+            conditions.return_humidity_ratio,
+        );
+        match self.control {
+            EconomizerControl::FixedDryBulb { high_limit } => t_out < high_limit,
+            EconomizerControl::DifferentialDryBulb => t_out < conditions.return_temperature,
+            EconomizerControl::FixedEnthalpy { high_limit } => h_out < high_limit,
+            EconomizerControl::DifferentialEnthalpy => h_out < h_return,
+        }
+    }
+
+    /// Whether the `control` needs to know the humidity of the air
+    pub fn needs_humidity(&self) -> bool {
+        matches!(
+            self.control,
+            EconomizerControl::FixedEnthalpy { .. } | EconomizerControl::DifferentialEnthalpy
+        )
+    }
+
+    /// The humidity ratio assumed for the return air, in kg/kg
+    pub fn return_humidity_ratio(&self) -> Float {
+        humidity_ratio(self.return_dew_point)
+    }
+
+    /// Calculates the outdoor airflow (in m3/s), which grows from the minimum to the
+    /// maximum as the Space temperature rises above the cooling setpoint,
+    /// if outdoor air can be used for cooling.
+    pub fn outdoor_airflow(&self, conditions: &EconomizerConditions) -> Float {
+        if !self.is_available(conditions) {
+            return self.minimum_flow;
+        }
+        let excess = conditions.return_temperature - self.cooling_setpoint;
+        let fraction = if self.throttling_range > 0.0 {
+            (excess / self.throttling_range).max(0.0).min(1.0)
+        } else if excess > 0.0 {
+            1.0
+        } else {
+            0.0
+        };
+        self.minimum_flow + fraction * (self.maximum_flow - self.minimum_flow)
+    }
+
+    /// Checks whether the economizer makes sense
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if self.minimum_flow < 0.0 || self.maximum_flow < self.minimum_flow {
+            return Err(format!(
+                "The economizer of Space '{}' has a maximum flow lower than its minimum (or negative flows)",
+                self.space
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn economizer(control: EconomizerControl) -> Economizer {
+        Economizer {
+            space: "office".to_string(),
+            control,
+            high_limit_lockout: None,
+            low_limit_lockout: None,
+            minimum_flow: 0.1,
+            maximum_flow: 1.0,
+            cooling_setpoint: 24.,
+            throttling_range: 2.,
+            return_dew_point: 12.,
+        }
+    }
+
+    fn conditions(outdoor_temperature: Float, outdoor_dew_point: Float) -> EconomizerConditions {
+        EconomizerConditions {
+            outdoor_temperature,
+            outdoor_humidity_ratio: humidity_ratio(outdoor_dew_point),
+            return_temperature: 25.,
+            return_humidity_ratio: humidity_ratio(12.),
+        }
+    }
+
+    #[test]
+    fn test_dry_bulb() {
+        let e = economizer(EconomizerControl::FixedDryBulb { high_limit: 18. });
+        assert!(e.check().is_ok());
+        assert!(e.is_available(&conditions(15., 5.)));
+        assert!(!e.is_available(&conditions(20., 5.)));
+
+        let e = economizer(EconomizerControl::DifferentialDryBulb);
+        assert!(e.is_available(&conditions(20., 5.)));
+        assert!(!e.is_available(&conditions(26., 5.)));
+    }
+
+    #[test]
+    fn test_enthalpy() {
+        // Cooler but very humid outdoor air is not useful
+        let e = economizer(EconomizerControl::DifferentialEnthalpy);
+        assert!(e.needs_humidity());
+        assert!(e.is_available(&conditions(20., 5.)));
+        assert!(!e.is_available(&conditions(23., 22.)));
+
+        let e = economizer(EconomizerControl::FixedEnthalpy {
+            high_limit: 47_000.,
+        });
+        assert!(e.is_available(&conditions(20., 5.)));
+        assert!(!e.is_available(&conditions(23., 22.)));
+    }
+
+    #[test]
+    fn test_lockouts() {
+        let mut e = economizer(EconomizerControl::DifferentialDryBulb);
+        e.low_limit_lockout = Some(-5.);
+        e.high_limit_lockout = Some(21.);
+        assert!(!e.is_available(&conditions(-10., -15.)));
+        assert!(!e.is_available(&conditions(22., 5.)));
+        assert!(e.is_available(&conditions(10., 5.)));
+    }
+
+    #[test]
+    fn test_outdoor_airflow() {
+        let e = economizer(EconomizerControl::DifferentialDryBulb);
+        // Space at 25 C: half way through the throttling range
+        assert!((e.outdoor_airflow(&conditions(15., 5.)) - 0.55).abs() < 1e-12);
+        // Not available: the minimum
+        assert!((e.outdoor_airflow(&conditions(30., 5.)) - 0.1).abs() < 1e-12);
+        // No cooling needed: the minimum
+        let mut c = conditions(15., 5.);
+        c.return_temperature = 22.;
+        assert!((e.outdoor_airflow(&c) - 0.1).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,520 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! A library of airflow elements, each of them described by the relationship
+//! between the pressure difference across it and the mass flow through it.
+//!
+//! The pressure difference $`\Delta P = P_i - P_j`$ is positive when it pushes
+//! air from side $`i`$ to side $`j`$, which is also the direction of a positive
+//! flow. Every element returns both the mass flow and its derivative with
+//! respect to $`\Delta P`$, which is what network solvers need.
+
+use crate::physics::air_density;
+use crate::Float;
+
+/// The density of air at the conditions in which power-law
+/// coefficients are usually reported (20 C, 101325 Pa), in kg/m3
+pub const REFERENCE_DENSITY: Float = 1.2041;
+
+/// The viscosity of air at the conditions in which power-law
+/// coefficients are usually reported (20 C), in Pa s
+pub const REFERENCE_VISCOSITY: Float = 1.8134e-5;
+
+/// Below this pressure difference (in Pa), power-law elements are
+/// linearised to avoid an infinite derivative at zero.
+const LINEARISATION_PRESSURE: Float = 1e-3;
+
+/// Calculates the dynamic viscosity of air (in Pa s) at a certain temperature
+/// (in C), using Sutherland's law
+pub fn air_viscosity(temperature: Float) -> Float {
+    let t = temperature + 273.15;
+    1.458e-6 * t.powf(1.5) / (t + 110.4)
+}
+
+/// A leak (e.g., a crack) described by the power law $`Q = C \Delta P^n`$.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerLaw {
+    /// The flow coefficient $`C`$, in $`m^3/(s Pa^n)`$, at the reference
+    /// density and viscosity
+    pub coefficient: Float,
+
+    /// The flow exponent $`n`$, between 0.5 (fully turbulent) and 1 (laminar)
+    pub exponent: Float,
+}
+
+impl PowerLaw {
+    /// Returns the mass flow (kg/s) and its derivative for a certain pressure
+    /// difference (Pa) and temperature of the air flowing through it (C).
+    ///
+    /// The coefficient is corrected for density and viscosity as
+    /// $`C_T = C \left(\frac{\rho}{\rho_{ref}}\right)^{n-1}\left(\frac{\mu_{ref}}{\mu}\right)^{2n-1}`$.
+    pub fn mass_flow(&self, delta_p: Float, temperature: Float) -> (Float, Float) {
+        let rho = air_density(temperature);
+        let mu = air_viscosity(temperature);
+        let n = self.exponent;
+        let c = self.coefficient
+            * (rho / REFERENCE_DENSITY).powf(n - 1.)
+            * (REFERENCE_VISCOSITY / mu).powf(2. * n - 1.);
+        power_law(rho * c, n, delta_p)
+    }
+}
+
+/// Evaluates $`\dot{m} = k \Delta P^n`$ (keeping the sign of $`\Delta P`$) and
+/// its derivative, linearising it for very small pressure differences
+fn power_law(k: Float, n: Float, delta_p: Float) -> (Float, Float) {
+    let abs_p = delta_p.abs();
+    let sign = if delta_p < 0.0 { -1. } else { 1. };
+    if abs_p < LINEARISATION_PRESSURE {
+        let slope = k * LINEARISATION_PRESSURE.powf(n - 1.);
+        (slope * delta_p, slope)
+    } else {
+        let m = k * abs_p.powf(n);
+        (sign * m, n * m / abs_p)
+    }
+}
+
+/// A straight duct with friction and local losses, in which
+/// $`\Delta P = \left(f\frac{L}{D} + \sum K\right)\frac{\rho V^2}{2}`$
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Duct {
+    /// The length of the duct, in m
+    pub length: Float,
+
+    /// The hydraulic diameter of the duct, in m
+    pub diameter: Float,
+
+    /// The absolute roughness of the duct's surface, in m
+    pub roughness: Float,
+
+    /// The sum of the local loss coefficients (e.g., bends, entries, exits)
+    pub local_losses: Float,
+}
+
+/// Calculates the Darcy friction factor for a certain Reynolds number
+/// and relative roughness, using the Swamee-Jain approximation of the
+/// Colebrook equation (or $`64/Re`$ for laminar flow)
+pub fn friction_factor(reynolds: Float, relative_roughness: Float) -> Float {
+    if reynolds < 1e-9 {
+        return 0.0;
+    }
+    if reynolds < 2300. {
+        return 64. / reynolds;
+    }
+    let x = (relative_roughness / 3.7 + 5.74 / reynolds.powf(0.9)).log10();
+    0.25 / (x * x)
+}
+
+impl Duct {
+    /// The cross-sectional area of the duct, in m2
+    fn area(&self) -> Float {
+        std::f64::consts::PI as Float * self.diameter * self.diameter / 4.
+    }
+
+    /// Calculates the pressure drop (Pa) caused by a certain air velocity (m/s)
+    pub fn pressure_drop(&self, velocity: Float, temperature: Float) -> Float {
+        let rho = air_density(temperature);
+        let mu = air_viscosity(temperature);
+        let reynolds = rho * velocity.abs() * self.diameter / mu;
+        let f = friction_factor(reynolds, self.roughness / self.diameter);
+        (f * self.length / self.diameter + self.local_losses) * rho * velocity * velocity / 2.
+    }
+
+    /// Calculates the (positive) mass flow through the duct for a
+    /// certain (positive) pressure drop by iterating on the friction factor
+    fn solve_mass_flow(&self, delta_p: Float, temperature: Float) -> Float {
+        if delta_p <= 0.0 {
+            return 0.0;
+        }
+        let rho = air_density(temperature);
+        let mu = air_viscosity(temperature);
+        let mut f: Float = 0.02;
+        let mut velocity = 0.0;
+        for _ in 0..100 {
+            let resistance = f * self.length / self.diameter + self.local_losses;
+            let new_velocity = (2. * delta_p / (rho * resistance)).sqrt();
+            let reynolds = rho * new_velocity * self.diameter / mu;
+            if reynolds < 2300. {
+                // Laminar: dP = 32 mu L V / D^2 + K rho V^2 / 2, a quadratic in V
+                let a = self.local_losses * rho / 2.;
+                let b = 32. * mu * self.length / (self.diameter * self.diameter);
+                velocity = if a > 0.0 {
+                    (-b + (b * b + 4. * a * delta_p).sqrt()) / (2. * a)
+                } else {
+                    delta_p / b
+                };
+                break;
+            }
+            f = friction_factor(reynolds, self.roughness / self.diameter);
+            let converged = (new_velocity - velocity).abs() < 1e-9 * new_velocity;
+            velocity = new_velocity;
+            if converged {
+                break;
+            }
+        }
+        rho * velocity * self.area()
+    }
+
+    /// Returns the mass flow (kg/s) and its derivative for a certain pressure
+    /// difference (Pa) and temperature of the air flowing through it (C).
+    pub fn mass_flow(&self, delta_p: Float, temperature: Float) -> (Float, Float) {
+        let sign = if delta_p < 0.0 { -1. } else { 1. };
+        let abs_p = delta_p.abs().max(LINEARISATION_PRESSURE);
+        let m = self.solve_mass_flow(abs_p, temperature);
+        // The derivative is estimated numerically, as the friction
+        // factor changes with the flow
+        let h = 1e-4 * abs_p;
+        let derivative = (self.solve_mass_flow(abs_p + h, temperature)
+            - self.solve_mass_flow(abs_p - h, temperature))
+            / (2. * h);
+        if delta_p.abs() < LINEARISATION_PRESSURE {
+            let slope = m / LINEARISATION_PRESSURE;
+            (slope * delta_p, slope)
+        } else {
+            (sign * m, derivative)
+        }
+    }
+}
+
+/// A fan described by a curve of pressure rise as a function of the
+/// volumetric flow: $`\Delta P_{fan} = \sum_k c_k Q^k`$. The curve must
+/// decrease with the flow.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FanCurve {
+    /// The coefficients $`c_k`$ of the polynomial, starting from $`c_0`$ (the
+    /// shut-off pressure, in Pa)
+    pub coefficients: Vec<Float>,
+}
+
+impl FanCurve {
+    /// The pressure rise (Pa) at a certain flow (m3/s)
+    pub fn pressure_rise(&self, flow: Float) -> Float {
+        self.coefficients
+            .iter()
+            .rev()
+            .fold(0.0, |acc, c| acc * flow + c)
+    }
+
+    /// The derivative of the pressure rise with respect to the flow
+    fn slope(&self, flow: Float) -> Float {
+        self.coefficients
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(k, c)| k as Float * c * flow.powi(k as i32 - 1))
+            .sum()
+    }
+
+    /// The flow (m3/s) delivered against zero pressure rise
+    pub fn free_delivery(&self) -> Float {
+        let mut hi: Float = 1.;
+        while self.pressure_rise(hi) > 0.0 && hi < 1e6 {
+            hi *= 2.;
+        }
+        let mut lo = 0.0;
+        for _ in 0..200 {
+            let mid = (lo + hi) / 2.;
+            if self.pressure_rise(mid) > 0.0 {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        (lo + hi) / 2.
+    }
+
+    /// Returns the mass flow (kg/s) and its derivative for a certain
+    /// pressure difference across the fan (Pa), measured from its inlet
+    /// to its outlet (i.e., a negative value means the fan needs to
+    /// raise the pressure), and the temperature of the air (C).
+    ///
+    /// The fan does not flow backwards: if the pressure it needs to raise
+    /// exceeds its shut-off pressure, it delivers no flow. Beyond the free
+    /// delivery, the curve is extrapolated linearly.
+    pub fn mass_flow(&self, delta_p: Float, temperature: Float) -> (Float, Float) {
+        let rho = air_density(temperature);
+        let rise = -delta_p;
+        let shut_off = self.pressure_rise(0.0);
+        if rise >= shut_off {
+            return (0.0, 0.0);
+        }
+        let free = self.free_delivery();
+        if rise <= 0.0 {
+            let slope = self.slope(free);
+            let q = free + rise / slope;
+            return (rho * q, -rho / slope);
+        }
+        let mut lo = 0.0;
+        let mut hi = free;
+        for _ in 0..200 {
+            let mid = (lo + hi) / 2.;
+            if self.pressure_rise(mid) > rise {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        let q = (lo + hi) / 2.;
+        (rho * q, -rho / self.slope(q))
+    }
+}
+
+/// A self-regulating vent (e.g., a pressure-controlled trickle vent),
+/// which behaves as an orifice up to its regulation pressure and then
+/// keeps its flow constant.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelfRegulatingVent {
+    /// The flow above the regulation pressure, in m3/s
+    pub design_flow: Float,
+
+    /// The pressure difference at which the vent starts regulating, in Pa
+    pub regulation_pressure: Float,
+}
+
+impl SelfRegulatingVent {
+    /// Returns the mass flow (kg/s) and its derivative for a certain pressure
+    /// difference (Pa) and temperature of the air flowing through it (C).
+    pub fn mass_flow(&self, delta_p: Float, temperature: Float) -> (Float, Float) {
+        let rho = air_density(temperature);
+        if delta_p.abs() >= self.regulation_pressure {
+            let sign = if delta_p < 0.0 { -1. } else { 1. };
+            (sign * rho * self.design_flow, 0.0)
+        } else {
+            let k = rho * self.design_flow / self.regulation_pressure.sqrt();
+            power_law(k, 0.5, delta_p)
+        }
+    }
+}
+
+/// The elements through which air can flow
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlowElement {
+    /// A crack or leak described by a power law
+    PowerLaw(PowerLaw),
+
+    /// A sharp-edged orifice: $`\dot{m} = C_d A\sqrt{2\rho|\Delta P|}`$
+    Orifice {
+        /// The area of the orifice, in m2
+        area: Float,
+        /// The discharge coefficient (about 0.6 for sharp edges)
+        discharge_coefficient: Float,
+    },
+
+    /// A duct with friction and local losses
+    Duct(Duct),
+
+    /// A fan that delivers a constant flow (in m3/s), regardless of
+    /// the pressure difference
+    ConstantFlowFan {
+        /// The volumetric flow, in m3/s
+        flow: Float,
+    },
+
+    /// A fan described by a performance curve
+    CurveFan(FanCurve),
+
+    /// A damper that lets air flow freely in the positive direction and
+    /// only leaks in the other one
+    BackdraftDamper {
+        /// The behaviour when air flows in the positive direction
+        open: PowerLaw,
+        /// The leakage when air tries to flow in the negative direction
+        closed: PowerLaw,
+    },
+
+    /// A vent that keeps its flow constant above a certain pressure
+    SelfRegulatingVent(SelfRegulatingVent),
+}
+
+impl FlowElement {
+    /// Returns the mass flow (kg/s) through the element and its derivative
+    /// with respect to the pressure difference, for a certain pressure
+    /// difference (Pa) and temperature of the air flowing through it (C).
+    pub fn mass_flow(&self, delta_p: Float, temperature: Float) -> (Float, Float) {
+        match self {
+            FlowElement::PowerLaw(p) => p.mass_flow(delta_p, temperature),
+            FlowElement::Orifice {
+                area,
+                discharge_coefficient,
+            } => {
+                let rho = air_density(temperature);
+                let k = discharge_coefficient * area * (2. * rho).sqrt();
+                power_law(k, 0.5, delta_p)
+            }
+            FlowElement::Duct(d) => d.mass_flow(delta_p, temperature),
+            FlowElement::ConstantFlowFan { flow } => (air_density(temperature) * flow, 0.0),
+            FlowElement::CurveFan(c) => c.mass_flow(delta_p, temperature),
+            FlowElement::BackdraftDamper { open, closed } => {
+                if delta_p >= 0.0 {
+                    open.mass_flow(delta_p, temperature)
+                } else {
+                    closed.mass_flow(delta_p, temperature)
+                }
+            }
+            FlowElement::SelfRegulatingVent(v) => v.mass_flow(delta_p, temperature),
+        }
+    }
+
+    /// Returns the volumetric flow (m3/s) through the element for a certain
+    /// pressure difference (Pa) and temperature of the air flowing through it (C).
+    pub fn volumetric_flow(&self, delta_p: Float, temperature: Float) -> Float {
+        self.mass_flow(delta_p, temperature).0 / air_density(temperature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks the derivative returned by an element against finite differences
+    fn check_derivative(element: &FlowElement, delta_p: Float) {
+        let h = 1e-5 * delta_p.abs().max(1.);
+        let (_, d) = element.mass_flow(delta_p, 20.);
+        let (m1, _) = element.mass_flow(delta_p + h, 20.);
+        let (m0, _) = element.mass_flow(delta_p - h, 20.);
+        let exp = (m1 - m0) / (2. * h);
+        assert!(
+            (d - exp).abs() <= 1e-3 * exp.abs().max(1e-9),
+            "found {}, expected {}",
+            d,
+            exp
+        );
+    }
+
+    #[test]
+    fn test_air_viscosity() {
+        assert!((air_viscosity(20.) - REFERENCE_VISCOSITY).abs() < 1e-7);
+    }
+
+    #[test]
+    fn test_power_law() {
+        let crack = FlowElement::PowerLaw(PowerLaw {
+            coefficient: 0.001,
+            exponent: 0.65,
+        });
+        // At (about) reference conditions, Q = C dP^n
+        let q = crack.volumetric_flow(50., 20.);
+        assert!((q - 0.001 * (50. as Float).powf(0.65)).abs() < 1e-5);
+        // Antisymmetric
+        let q2 = crack.volumetric_flow(-50., 20.);
+        assert!((q + q2).abs() < 1e-12);
+        // Continuous around the linearisation
+        let (a, _) = crack.mass_flow(LINEARISATION_PRESSURE * 0.999999, 20.);
+        let (b, _) = crack.mass_flow(LINEARISATION_PRESSURE * 1.000001, 20.);
+        assert!((a - b).abs() < 1e-9);
+
+        check_derivative(&crack, 10.);
+        check_derivative(&crack, -3.);
+    }
+
+    #[test]
+    fn test_orifice() {
+        let orifice = FlowElement::Orifice {
+            area: 0.01,
+            discharge_coefficient: 0.6,
+        };
+        let (m, _) = orifice.mass_flow(10., 20.);
+        let rho = air_density(20.);
+        assert!((m - 0.6 * 0.01 * (2. * rho * 10.).sqrt()).abs() < 1e-9);
+        check_derivative(&orifice, 10.);
+    }
+
+    #[test]
+    fn test_friction_factor() {
+        // Moody chart: Re = 1e5, e/D = 0.0005 => f ~ 0.0204
+        assert!((friction_factor(1e5, 0.0005) - 0.0204).abs() < 0.0005);
+        // Laminar
+        assert!((friction_factor(1000., 0.001) - 0.064).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_duct() {
+        let duct = Duct {
+            length: 10.,
+            diameter: 0.2,
+            roughness: 0.0001,
+            local_losses: 1.5,
+        };
+        // Round trip: the flow found for the pressure drop caused by
+        // a velocity of 5 m/s must give that velocity
+        let dp = duct.pressure_drop(5., 20.);
+        let element = FlowElement::Duct(duct);
+        let q = element.volumetric_flow(dp, 20.);
+        let exp = 5. * std::f64::consts::PI as Float * 0.2 * 0.2 / 4.;
+        assert!((q - exp).abs() / exp < 1e-4);
+
+        check_derivative(&element, dp);
+        check_derivative(&element, -dp);
+    }
+
+    #[test]
+    fn test_fans() {
+        let fan = FlowElement::ConstantFlowFan { flow: 0.1 };
+        let (m, d) = fan.mass_flow(-100., 20.);
+        assert!((m - 0.1 * air_density(20.)).abs() < 1e-9);
+        assert!(d.abs() < 1e-12);
+
+        // dP_fan = 200 - 2000 Q^2
+        let curve = FanCurve {
+            coefficients: vec![200., 0., -2000.],
+        };
+        assert!((curve.free_delivery() - (0.1 as Float).sqrt()).abs() < 1e-6);
+        let fan = FlowElement::CurveFan(curve);
+        // Raising 100 Pa => Q = sqrt(100/2000)
+        let q = fan.volumetric_flow(-100., 20.);
+        assert!((q - (0.05 as Float).sqrt()).abs() < 1e-6);
+        check_derivative(&fan, -100.);
+        // Beyond shut-off
+        assert!(fan.volumetric_flow(-300., 20.) < 1e-12);
+        // Assisted by the pressure difference
+        assert!(fan.volumetric_flow(50., 20.) > (0.1 as Float).sqrt());
+    }
+
+    #[test]
+    fn test_backdraft_damper() {
+        let damper = FlowElement::BackdraftDamper {
+            open: PowerLaw {
+                coefficient: 0.05,
+                exponent: 0.5,
+            },
+            closed: PowerLaw {
+                coefficient: 0.0005,
+                exponent: 0.65,
+            },
+        };
+        let forward = damper.volumetric_flow(10., 20.);
+        let backward = damper.volumetric_flow(-10., 20.);
+        assert!(forward > 0.);
+        assert!(backward < 0.);
+        assert!(forward > 10. * backward.abs());
+    }
+
+    #[test]
+    fn test_self_regulating_vent() {
+        let vent = FlowElement::SelfRegulatingVent(SelfRegulatingVent {
+            design_flow: 0.01,
+            regulation_pressure: 10.,
+        });
+        assert!((vent.volumetric_flow(2.5, 20.) - 0.005).abs() < 1e-9);
+        assert!((vent.volumetric_flow(10., 20.) - 0.01).abs() < 1e-9);
+        assert!((vent.volumetric_flow(50., 20.) - 0.01).abs() < 1e-9);
+        assert!((vent.volumetric_flow(-50., 20.) + 0.01).abs() < 1e-9);
+        check_derivative(&vent, 5.);
+    }
+}
@@ -0,0 +1,523 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! The hourly calculation of airflow rates of EN 16798-7.
+//!
+//! The envelope of a ventilation zone is represented by a few leakage paths
+//! on its windward and leeward facades and on its roof. The internal pressure
+//! of the zones of a building is found by balancing the mass flowing through
+//! these paths, driven by wind and stack pressures, with the supply and
+//! exhaust of the mechanical ventilation. Open windows are treated
+//! separately, with the single-sided formula of de Gids and Phaff.
+
+use crate::elements::PowerLaw;
+use crate::options::Terrain;
+use crate::physics::{air_density, GRAVITY};
+use crate::profile::HourlyProfile;
+use crate::ventilation::de_gids_phaff_airflow;
+use crate::Float;
+
+/// The flow exponent of the leakage paths
+pub const LEAKAGE_EXPONENT: Float = 0.667;
+
+/// How exposed to the wind a building is, which sets its
+/// wind pressure coefficients
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindExposure {
+    /// Open terrain, or a building taller than its surroundings
+    Open,
+    /// A building as tall as its surroundings
+    Normal,
+    /// A building lower than its surroundings
+    Shielded,
+}
+
+impl WindExposure {
+    /// Returns the wind pressure coefficients of the windward facade, the
+    /// leeward facade and a flat roof of buildings lower than 15 m
+    pub fn pressure_coefficients(&self) -> (Float, Float, Float) {
+        match self {
+            WindExposure::Open => (0.50, -0.70, -0.70),
+            WindExposure::Normal => (0.25, -0.50, -0.60),
+            WindExposure::Shielded => (0.05, -0.30, -0.50),
+        }
+    }
+}
+
+/// A path through which air leaks into or out of a zone
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LeakagePath {
+    /// The leak
+    pub power_law: PowerLaw,
+
+    /// The height of the path above the floor of the zone, in m
+    pub height: Float,
+
+    /// The wind pressure coefficient at the path
+    pub pressure_coefficient: Float,
+}
+
+/// Distributes the leakage of a zone into the paths prescribed by the
+/// standard: a quarter of the facade leakage at 25% and at 75% of the height
+/// of each of the windward and leeward facades, and the rest on the roof.
+///
+/// * `q50`: The flow through the envelope at 50 Pa, in m3/s
+/// * `height`: The height of the zone, in m
+/// * `roof_fraction`: The fraction of the leakage that is in the roof
+pub fn leakage_paths(
+    q50: Float,
+    height: Float,
+    roof_fraction: Float,
+    exposure: WindExposure,
+) -> Vec<LeakagePath> {
+    let (windward, leeward, roof) = exposure.pressure_coefficients();
+    let coefficient = q50 / (50. as Float).powf(LEAKAGE_EXPONENT);
+    let path = |fraction: Float, height: Float, pressure_coefficient: Float| LeakagePath {
+        power_law: PowerLaw {
+            coefficient: fraction * coefficient,
+            exponent: LEAKAGE_EXPONENT,
+        },
+        height,
+        pressure_coefficient,
+    };
+    let facade = (1. - roof_fraction) / 4.;
+    let mut paths = vec![
+        path(facade, 0.25 * height, windward),
+        path(facade, 0.75 * height, windward),
+        path(facade, 0.25 * height, leeward),
+        path(facade, 0.75 * height, leeward),
+    ];
+    if roof_fraction > 0.0 {
+        paths.push(path(roof_fraction, height, roof));
+    }
+    paths
+}
+
+/// A window that can be opened for airing
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowOpening {
+    /// The area of the window when fully open, in m2
+    pub area: Float,
+
+    /// The height of the opening, in m
+    pub height: Float,
+
+    /// The fraction of the window that is open at each hour of the day.
+    /// If `None`, the window is always closed.
+    pub opening: Option<HourlyProfile>,
+}
+
+impl WindowOpening {
+    /// Returns the fraction of the window that is open at a certain hour of the day
+    pub fn open_fraction(&self, hour: Float) -> Float {
+        self.opening
+            .as_ref()
+            .map_or(0.0, |profile| profile.at(hour))
+    }
+
+    /// Calculates the outdoor air entering through the window (in m3/s),
+    /// with the same formula as
+    /// [`SingleSided::DeGidsPhaff`](crate::ventilation::SingleSided::DeGidsPhaff)
+    pub fn airflow(
+        &self,
+        hour: Float,
+        wind_speed: Float,
+        indoor_temperature: Float,
+        outdoor_temperature: Float,
+    ) -> Float {
+        de_gids_phaff_airflow(
+            self.area * self.open_fraction(hour),
+            self.height,
+            wind_speed,
+            indoor_temperature - outdoor_temperature,
+        )
+    }
+}
+
+/// A ventilation zone calculated with EN 16798-7
+#[derive(Debug, Clone, PartialEq)]
+pub struct En16798Zone {
+    /// The name of the Space that represents the zone
+    pub space: String,
+
+    /// The height of the floor of the zone above the ground, in m
+    pub floor_height: Float,
+
+    /// The leakage paths of the envelope
+    pub paths: Vec<LeakagePath>,
+
+    /// The windows that can be opened for airing
+    pub windows: Vec<WindowOpening>,
+
+    /// The outdoor air supplied by fans, in m3/s
+    pub supply_flow: Float,
+
+    /// The air extracted by fans, in m3/s
+    pub exhaust_flow: Float,
+
+    /// The fraction of each hour of the day during which the fans run.
+    /// If `None`, they always run.
+    pub schedule: Option<HourlyProfile>,
+}
+
+/// The airflows of a zone during a timestep
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct En16798Flows {
+    /// The internal pressure of the zone at floor level (relative to the
+    /// outdoor pressure at the same level, without wind), in Pa
+    pub internal_pressure: Float,
+
+    /// The outdoor air entering through the leakage paths, in m3/s
+    pub infiltration: Float,
+
+    /// The air leaving through the leakage paths, in m3/s
+    pub exfiltration: Float,
+
+    /// The outdoor air entering through open windows, in m3/s
+    pub windows: Float,
+
+    /// The outdoor air supplied by fans, in m3/s
+    pub supply: Float,
+
+    /// The air extracted by fans, in m3/s
+    pub exhaust: Float,
+}
+
+impl En16798Flows {
+    /// The total outdoor air entering the zone, in m3/s
+    pub fn outdoor_air(&self) -> Float {
+        self.infiltration + self.windows + self.supply
+    }
+}
+
+impl En16798Zone {
+    /// Returns the fraction of a certain hour of the day during
+    /// which the fans run
+    pub fn running_fraction(&self, hour: Float) -> Float {
+        match &self.schedule {
+            Some(profile) => profile.at(hour),
+            None => 1.0,
+        }
+    }
+
+    /// Checks whether the zone makes sense
+    pub(crate) fn check(&self) -> Result<(), String> {
+        for profile in self
+            .schedule
+            .iter()
+            .chain(self.windows.iter().filter_map(|w| w.opening.as_ref()))
+        {
+            profile.check(&format!(
+                "A profile of the EN 16798-7 zone '{}'",
+                self.space
+            ))?;
+        }
+        if self.paths.is_empty() && (self.supply_flow - self.exhaust_flow).abs() > 0.0 {
+            return Err(format!(
+                "The EN 16798-7 zone '{}' has unbalanced fans but no leakage paths",
+                self.space
+            ));
+        }
+        Ok(())
+    }
+
+    /// Finds the internal pressure that balances the mass entering and
+    /// leaving the zone on its own, and the resulting airflows. See
+    /// [`solve_building`].
+    pub fn solve(
+        &self,
+        hour: Float,
+        wind_speed: Float,
+        indoor_temperature: Float,
+        outdoor_temperature: Float,
+        building: &En16798Building,
+    ) -> En16798Flows {
+        solve_building(
+            &[(self, indoor_temperature)],
+            hour,
+            wind_speed,
+            outdoor_temperature,
+            building,
+        )[0]
+    }
+}
+
+/// The building that contains some EN 16798-7 zones
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct En16798Building {
+    /// The height of the building, in m, at which the wind pressure
+    /// is evaluated
+    pub height: Float,
+
+    /// The terrain surrounding the building
+    pub terrain: Terrain,
+}
+
+/// Finds the internal pressure that balances the mass entering and leaving
+/// all the zones of a building, which are assumed to be connected (i.e.,
+/// they share their internal pressure at ground level), and the resulting
+/// airflows of each zone.
+///
+/// * `zones`: Each zone and its temperature, in C
+/// * `hour`: The hour of the day
+/// * `wind_speed`: The wind speed at the meteorological station, in m/s
+/// * `outdoor_temperature`: The outdoor temperature, in C
+/// * `building`: The building that contains the zones
+pub fn solve_building(
+    zones: &[(&En16798Zone, Float)],
+    hour: Float,
+    wind_speed: Float,
+    outdoor_temperature: Float,
+    building: &En16798Building,
+) -> Vec<En16798Flows> {
+    let rho_out = air_density(outdoor_temperature);
+    let (alpha, gamma) = building.terrain.parameters();
+    let site_wind_speed = alpha * wind_speed;
+    let building_wind_speed = site_wind_speed * (building.height / 10.).powf(gamma);
+
+    // The outdoor pressure at each path, minus the hydrostatic pressure
+    // of the indoor air, both measured from the ground
+    let outdoor: Vec<Vec<Float>> = zones
+        .iter()
+        .map(|(zone, indoor_temperature)| {
+            let rho_in = air_density(*indoor_temperature);
+            zone.paths
+                .iter()
+                .map(|p| {
+                    0.5 * rho_out
+                        * p.pressure_coefficient
+                        * building_wind_speed
+                        * building_wind_speed
+                        - (rho_out - rho_in) * GRAVITY * (zone.floor_height + p.height)
+                })
+                .collect()
+        })
+        .collect();
+    let mass_flow = |path: &LeakagePath, delta_p: Float, indoor_temperature: Float| -> Float {
+        let temperature = if delta_p > 0.0 {
+            outdoor_temperature
+        } else {
+            indoor_temperature
+        };
+        path.power_law.mass_flow(delta_p, temperature).0
+    };
+    let fan_flows: Vec<(Float, Float)> = zones
+        .iter()
+        .map(|(zone, _)| {
+            let fraction = zone.running_fraction(hour);
+            (zone.supply_flow * fraction, zone.exhaust_flow * fraction)
+        })
+        .collect();
+    let balance = |p_in: Float| -> Float {
+        zones
+            .iter()
+            .zip(outdoor.iter())
+            .zip(fan_flows.iter())
+            .map(
+                |(((zone, indoor_temperature), outdoor), (supply, exhaust))| {
+                    zone.paths
+                        .iter()
+                        .zip(outdoor.iter())
+                        .map(|(p, p_out)| mass_flow(p, p_out - p_in, *indoor_temperature))
+                        .sum::<Float>()
+                        + rho_out * supply
+                        - air_density(*indoor_temperature) * exhaust
+                },
+            )
+            .sum()
+    };
+
+    // The balance decreases with the internal pressure. Widen the
+    // bracket until it changes sign, as fans can push the internal
+    // pressure beyond the range of the outdoor pressures.
+    let has_paths = zones.iter().any(|(zone, _)| !zone.paths.is_empty());
+    let internal_pressure = if has_paths {
+        let all = outdoor.iter().flatten().cloned();
+        let mut lo = all.clone().fold(Float::INFINITY, Float::min) - 1.;
+        let mut hi = all.fold(Float::NEG_INFINITY, Float::max) + 1.;
+        for _ in 0..60 {
+            if balance(lo) >= 0.0 {
+                break;
+            }
+            lo -= 2. * (hi - lo);
+        }
+        for _ in 0..60 {
+            if balance(hi) <= 0.0 {
+                break;
+            }
+            hi += 2. * (hi - lo);
+        }
+        for _ in 0..200 {
+            let mid = (lo + hi) / 2.;
+            if balance(mid) > 0.0 {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        (lo + hi) / 2.
+    } else {
+        0.0
+    };
+
+    zones
+        .iter()
+        .zip(outdoor.iter())
+        .zip(fan_flows.iter())
+        .map(
+            |(((zone, indoor_temperature), outdoor), (supply, exhaust))| {
+                let rho_in = air_density(*indoor_temperature);
+                let mut infiltration = 0.0;
+                let mut exfiltration = 0.0;
+                for (path, p_out) in zone.paths.iter().zip(outdoor.iter()) {
+                    let m = mass_flow(path, p_out - internal_pressure, *indoor_temperature);
+                    if m > 0.0 {
+                        infiltration += m / rho_out;
+                    } else {
+                        exfiltration -= m / rho_in;
+                    }
+                }
+                let windows = zone
+                    .windows
+                    .iter()
+                    .map(|w| {
+                        w.airflow(
+                            hour,
+                            site_wind_speed,
+                            *indoor_temperature,
+                            outdoor_temperature,
+                        )
+                    })
+                    .sum();
+                En16798Flows {
+                    internal_pressure: if has_paths {
+                        internal_pressure + (rho_out - rho_in) * GRAVITY * zone.floor_height
+                    } else {
+                        0.0
+                    },
+                    infiltration,
+                    exfiltration,
+                    windows,
+                    supply: *supply,
+                    exhaust: *exhaust,
+                }
+            },
+        )
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUILDING: En16798Building = En16798Building {
+        height: 6.,
+        terrain: Terrain::Urban,
+    };
+
+    fn zone(supply_flow: Float, exhaust_flow: Float) -> En16798Zone {
+        En16798Zone {
+            space: "flat".to_string(),
+            floor_height: 0.,
+            paths: leakage_paths(0.3, 3., 0.2, WindExposure::Normal),
+            windows: Vec::new(),
+            supply_flow,
+            exhaust_flow,
+            schedule: None,
+        }
+    }
+
+    #[test]
+    fn test_leakage_paths() {
+        let paths = leakage_paths(0.3, 3., 0.2, WindExposure::Open);
+        assert_eq!(paths.len(), 5);
+        let total: Float = paths.iter().map(|p| p.power_law.coefficient).sum();
+        assert!((total * (50. as Float).powf(LEAKAGE_EXPONENT) - 0.3).abs() < 1e-12);
+        assert!((paths[1].height - 2.25).abs() < 1e-12);
+        assert!((paths[4].pressure_coefficient + 0.7).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_mass_balance() {
+        // Wind and stack, no fans
+        let z = zone(0., 0.);
+        assert!(z.check().is_ok());
+        let res = z.solve(12., 5., 20., 0., &BUILDING);
+        let rho_in = air_density(20.);
+        let rho_out = air_density(0.);
+        assert!(res.infiltration > 0.0);
+        assert!((res.infiltration * rho_out - res.exfiltration * rho_in).abs() < 1e-9);
+
+        // No drivers, no flow
+        let res = z.solve(12., 0., 10., 10., &BUILDING);
+        assert!(res.infiltration.abs() < 1e-9);
+
+        // Exhaust dominated: the zone is depressurised, and the
+        // infiltration makes up for the exhaust
+        let z = zone(0., 0.1);
+        let res = z.solve(12., 0., 10., 10., &BUILDING);
+        assert!(res.internal_pressure < 0.0);
+        assert!((res.infiltration - 0.1).abs() < 1e-9);
+        assert!((res.outdoor_air() - 0.1).abs() < 1e-9);
+
+        // Balanced fans do not change the infiltration
+        let z = zone(0.1, 0.1);
+        let res = z.solve(12., 5., 10., 10., &BUILDING);
+        let res_no_fans = zone(0., 0.).solve(12., 5., 10., 10., &BUILDING);
+        assert!((res.infiltration - res_no_fans.infiltration).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_windows() {
+        let window = WindowOpening {
+            area: 2.,
+            height: 1.5,
+            opening: Some(HourlyProfile::constant(0.5)),
+        };
+        let q = window.airflow(10., 3., 20., 10.);
+        let exp = 0.5 * (0.001 * 9. + 0.0035 * 1.5 * 10. + 0.01 as Float).sqrt();
+        assert!((q - exp).abs() < 1e-12);
+
+        let mut z = zone(0., 0.);
+        z.windows.push(window);
+        let res = z.solve(10., 0., 20., 10., &BUILDING);
+        assert!(res.windows > 0.0);
+        z.windows[0].opening = Some(vec![1.; 3].into());
+        assert!(z.check().is_err());
+    }
+
+    #[test]
+    fn test_building_balance() {
+        // An exhaust-only flat above a flat with leaky paths but no fans:
+        // both share the infiltration needed by the exhaust
+        let mut upper = zone(0., 0.1);
+        upper.floor_height = 3.;
+        let lower = zone(0., 0.);
+        let res = solve_building(&[(&upper, 10.), (&lower, 10.)], 12., 0., 10., &BUILDING);
+        assert!((res[0].infiltration + res[1].infiltration - 0.1).abs() < 1e-9);
+        assert!(res[1].infiltration > 0.0);
+        assert!((res[0].internal_pressure - res[1].internal_pressure).abs() < 1e-9);
+
+        // On its own, the flat would take all of it
+        let alone = upper.solve(12., 0., 10., 10., &BUILDING);
+        assert!((alone.infiltration - 0.1).abs() < 1e-9);
+        assert!(alone.internal_pressure < res[0].internal_pressure);
+    }
+}
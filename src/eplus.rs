@@ -20,10 +20,20 @@ SOFTWARE.
 
 use std::sync::Arc;
 
+use crate::options::Terrain;
+use crate::physics::GRAVITY;
 use crate::Float;
-use simple_model::{SimulationState, Space};
+use simple_model::{ShelterClass, SimulationState, Space};
 use weather::CurrentWeather;
 
+/// The indoor temperature, in K, implied by the stack coefficients
+/// tabulated by ASHRAE
+const REFERENCE_TEMPERATURE: Float = 293.15;
+
+/// The storey height, in m, implied by the stack and wind coefficients
+/// tabulated by ASHRAE
+pub const DEFAULT_STOREY_HEIGHT: Float = 2.5;
+
 /// Calculates an infiltration rate equal to that estimated by
 /// EnergyPlus' `ZoneInfiltration:DesignFlowRate`.
 ///
@@ -49,9 +59,30 @@ pub fn design_flow_rate(
         .wind_speed
         .expect("Weather does not have Wind Speed");
 
-    design_rate * (a + b * (t_space - t_out).abs() + c * wind_speed + d * wind_speed * wind_speed)
+    design_flow_rate_at(design_rate, a, b, c, d, t_space - t_out, wind_speed)
 }
 
+/// Calculates the infiltration rate of [`design_flow_rate`] for a certain
+/// temperature difference (in K) and wind speed (in m/s), without
+/// looking at the weather or at the state of the simulation
+pub fn design_flow_rate_at(
+    design_rate: Float,
+    a: Float,
+    b: Float,
+    c: Float,
+    d: Float,
+    delta_t: Float,
+    wind_speed: Float,
+) -> Float {
+    design_rate * (a + b * delta_t.abs() + c * wind_speed + d * wind_speed * wind_speed)
+}
+
+/// The $`(A, B, C, D)`$ coefficients of the BLAST defaults
+pub const BLAST_COEFFICIENTS: (Float, Float, Float, Float) = (0.606, 0.03636, 0.1177, 0.);
+
+/// The $`(A, B, C, D)`$ coefficients of the DOE-2 defaults
+pub const DOE2_COEFFICIENTS: (Float, Float, Float, Float) = (0., 0., 0.224, 0.);
+
 /// Calculates the design flow rates using the BLAST defaults (reported in EnergyPlus' Input/Output reference)
 pub fn blast_design_flow_rate(
     weather: &CurrentWeather,
@@ -59,16 +90,8 @@ pub fn blast_design_flow_rate(
     state: &SimulationState,
     design_rate: Float,
 ) -> Float {
-    design_flow_rate(
-        weather,
-        space,
-        state,
-        design_rate,
-        0.606,
-        0.03636,
-        0.1177,
-        0.,
-    )
+    let (a, b, c, d) = BLAST_COEFFICIENTS;
+    design_flow_rate(weather, space, state, design_rate, a, b, c, d)
 }
 
 /// Calculates the design flow rates using the DOE-2 defaults (reported in EnergyPlus' Input/Output reference)
@@ -78,9 +101,16 @@ pub fn doe2_design_flow_rate(
     state: &SimulationState,
     design_rate: Float,
 ) -> Float {
-    design_flow_rate(weather, space, state, design_rate, 0., 0., 0.224, 0.)
+    let (a, b, c, d) = DOE2_COEFFICIENTS;
+    design_flow_rate(weather, space, state, design_rate, a, b, c, d)
 }
 
+/// Calculates the infiltration rate estimated by EnergyPlus'
+/// `ZoneInfiltration:EffectiveLeakageArea` (i.e., the Sherman-Grimsrud
+/// model)
+///
+/// The equation is $`\phi = \frac{A_L}{1000} \sqrt{C_s |T_{space} - T_{outside}| + C_w W^2_{speed}}`$,
+/// with $`A_L`$ in $`cm^2`$
 pub fn effective_leakage_area(
     weather: &CurrentWeather,
     space: &Arc<Space>,
@@ -95,10 +125,159 @@ pub fn effective_leakage_area(
     let space_temp = space
         .dry_bulb_temperature(state)
         .expect("Space has no Dry-bulb temperature");
-    let delta_t = (outdoor_temp - space_temp).abs();
     let ws = weather.wind_speed.unwrap_or(0.0);
 
-    (area / 1000.) * (cs * delta_t + cw * ws * ws).sqrt()
+    effective_leakage_area_at(area, cw, cs, outdoor_temp - space_temp, ws)
+}
+
+/// Calculates the infiltration rate of [`effective_leakage_area`] for a
+/// certain temperature difference (in K) and wind speed (in m/s), without
+/// looking at the weather or at the state of the simulation
+pub fn effective_leakage_area_at(
+    area: Float,
+    cw: Float,
+    cs: Float,
+    delta_t: Float,
+    wind_speed: Float,
+) -> Float {
+    (area / 1000.) * (cs * delta_t.abs() + cw * wind_speed * wind_speed).sqrt()
+}
+
+/// Returns the stack coefficient (in $`(L/s)^2/(cm^4 K)`$) tabulated
+/// by ASHRAE for a building of `n_storeys` storeys.
+///
+/// Returns `None` if the table does not cover the building.
+pub fn tabulated_stack_coefficient(n_storeys: usize) -> Option<Float> {
+    match n_storeys {
+        1 => Some(0.000145),
+        2 => Some(0.000290),
+        3 => Some(0.000435),
+        _ => None,
+    }
+}
+
+/// Returns the wind coefficient (in $`(L/s)^2/(cm^4 (m/s)^2)`$) tabulated
+/// by ASHRAE for a building of `n_storeys` storeys.
+///
+/// Returns `None` if the table does not cover the building.
+pub fn tabulated_wind_coefficient(shelter: &ShelterClass, n_storeys: usize) -> Option<Float> {
+    let row = match shelter {
+        ShelterClass::NoObstructions => [0.000319, 0.000420, 0.000494],
+        ShelterClass::IsolatedRural => [0.000246, 0.000325, 0.000382],
+        ShelterClass::Urban => [0.000172, 0.000231, 0.000271],
+        ShelterClass::LargeLotUrban => [0.000104, 0.000137, 0.000161],
+        ShelterClass::SmallLotUrban => [0.000032, 0.000042, 0.000049],
+    };
+    match n_storeys {
+        1 | 2 | 3 => Some(row[n_storeys - 1]),
+        _ => None,
+    }
+}
+
+/// Calculates the stack coefficient (in $`(L/s)^2/(cm^4 K)`$) of a building
+/// of a certain `height` (in m), following Sherman and Grimsrud.
+///
+/// The stack factor is $`f_s = \frac{1 + R/2}{3}\sqrt{\frac{g H}{T}}`$, assuming
+/// that half the leakage is in the floor and ceiling ($`R = 0.5`$) and evenly
+/// split between them. These are the assumptions behind the ASHRAE table, so
+/// this function reproduces it at $`H = 2.5, 5.0, 7.5`$.
+pub fn stack_coefficient_from_height(height: Float) -> Float {
+    lbl_stack_coefficient(height, 0.25, 0.25, REFERENCE_TEMPERATURE - 273.15)
+}
+
+/// Calculates the wind coefficient (in $`(L/s)^2/(cm^4 (m/s)^2)`$) of a building
+/// of a certain `height` (in m), following Sherman and Grimsrud.
+///
+/// The wind factor is proportional to the local wind speed at the top of the
+/// building (i.e., $`f_w \propto \alpha (H/10)^\gamma`$), so the coefficient
+/// tabulated by ASHRAE for a one-storey building with a certain shelter
+/// class is scaled by the square of the ratio between the local wind speeds
+/// at $`H`$ in the given `terrain` and at $`2.5`$ m in the default
+/// [`Terrain`], which is the one implied by the ASHRAE table.
+pub fn wind_coefficient_from_height(
+    height: Float,
+    shelter: &ShelterClass,
+    terrain: &Terrain,
+) -> Float {
+    let (alpha, gamma) = terrain.parameters();
+    let (reference_alpha, reference_gamma) = Terrain::default().parameters();
+    let one_storey = tabulated_wind_coefficient(shelter, 1).unwrap();
+    let speed_ratio = alpha * (height / 10.).powf(gamma)
+        / (reference_alpha * (DEFAULT_STOREY_HEIGHT / 10.).powf(reference_gamma));
+    one_storey * speed_ratio.powi(2)
+}
+
+/// Returns the generalised shielding coefficient $`C'`$ that Sherman and
+/// Grimsrud associate with each shelter class
+pub fn shielding_coefficient(shelter: &ShelterClass) -> Float {
+    match shelter {
+        ShelterClass::NoObstructions => 0.324,
+        ShelterClass::IsolatedRural => 0.285,
+        ShelterClass::Urban => 0.240,
+        ShelterClass::LargeLotUrban => 0.185,
+        ShelterClass::SmallLotUrban => 0.102,
+    }
+}
+
+/// Calculates the stack coefficient (in $`(L/s)^2/(cm^4 K)`$) of the
+/// complete Sherman-Grimsrud (LBL) model.
+///
+/// The stack factor is
+/// $`f_s = \frac{1 + R/2}{3}\left(1 - \frac{X^2}{(2-R)^2}\right)^{3/2}\sqrt{\frac{g H}{T_{in}}}`$,
+/// where $`R`$ is the fraction of the leakage area located in the ceiling and
+/// floor and $`X`$ is the difference between the ceiling and floor fractions.
+///
+/// * `height`: The height of the building, in m
+/// * `ceiling_fraction`: The fraction of the leakage area located in the ceiling
+/// * `floor_fraction`: The fraction of the leakage area located in the floor
+/// * `indoor_temperature`: The indoor temperature, in C
+pub fn lbl_stack_coefficient(
+    height: Float,
+    ceiling_fraction: Float,
+    floor_fraction: Float,
+    indoor_temperature: Float,
+) -> Float {
+    let r = ceiling_fraction + floor_fraction;
+    let x = ceiling_fraction - floor_fraction;
+    let distribution = (1. - x * x / ((2. - r) * (2. - r))).powf(1.5);
+    let t_in = indoor_temperature + 273.15;
+    let fs = (1. + r / 2.) / 3. * distribution * (GRAVITY * height / t_in).sqrt();
+    // From m3/s per m2 to L/s per cm2
+    fs * fs / 100.
+}
+
+/// Calculates the wind coefficient (in $`(L/s)^2/(cm^4 (m/s)^2)`$) of the
+/// complete Sherman-Grimsrud (LBL) model.
+///
+/// The wind factor is
+/// $`f_w = C' (1-R)^{1/3} \frac{\alpha (H/10)^\gamma}{\alpha' (H'/10)^{\gamma'}}`$,
+/// where $`C'`$ is the generalised shielding coefficient, $`R`$ is the fraction of
+/// the leakage area located in the ceiling and floor, and the last term transforms
+/// the wind speed measured at the meteorological station (at a height $`H'`$, in a
+/// terrain with parameters $`\alpha'`$ and $`\gamma'`$) into the wind speed at the
+/// top of the building.
+///
+/// * `height`: The height of the building, in m
+/// * `shielding`: The generalised shielding coefficient $`C'`$
+/// * `ceiling_and_floor_fraction`: The fraction of the leakage area located in the ceiling and floor
+/// * `site`: The terrain surrounding the building
+/// * `met_station`: The terrain surrounding the meteorological station
+/// * `met_station_height`: The height at which the wind speed was measured, in m
+pub fn lbl_wind_coefficient(
+    height: Float,
+    shielding: Float,
+    ceiling_and_floor_fraction: Float,
+    site: &Terrain,
+    met_station: &Terrain,
+    met_station_height: Float,
+) -> Float {
+    let (alpha, gamma) = site.parameters();
+    let (met_alpha, met_gamma) = met_station.parameters();
+    let terrain_factor = alpha * (height / 10.).powf(gamma)
+        / (met_alpha * (met_station_height / 10.).powf(met_gamma));
+    let fw = shielding * (1. - ceiling_and_floor_fraction).cbrt() * terrain_factor;
+    // From m3/s per m2 to L/s per cm2
+    fw * fw / 100.
 }
 
 #[cfg(test)]
@@ -248,4 +427,118 @@ mod tests {
         let flow = doe2_design_flow_rate(&current_weather, &space, &state, design_rate);
         assert!((1. - flow).abs() < 0.02);
     }
+
+    #[test]
+    fn test_stack_coefficient_from_height() {
+        // Should reproduce the ASHRAE tables
+        for n_storeys in 1..=3 {
+            let height = n_storeys as Float * DEFAULT_STOREY_HEIGHT;
+            let exp = tabulated_stack_coefficient(n_storeys).unwrap();
+            let found = stack_coefficient_from_height(height);
+            assert!(
+                (exp - found).abs() / exp < 0.01,
+                "n_storeys = {}",
+                n_storeys
+            );
+        }
+        assert!(tabulated_stack_coefficient(4).is_none());
+
+        // And keep growing after that
+        let three = stack_coefficient_from_height(3. * DEFAULT_STOREY_HEIGHT);
+        let eight = stack_coefficient_from_height(8. * DEFAULT_STOREY_HEIGHT);
+        assert!((eight / three - 8. / 3.).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_wind_coefficient_from_height() {
+        let terrain = Terrain::default();
+        for shelter in [
+            ShelterClass::NoObstructions,
+            ShelterClass::IsolatedRural,
+            ShelterClass::Urban,
+            ShelterClass::LargeLotUrban,
+            ShelterClass::SmallLotUrban,
+        ] {
+            for n_storeys in 1..=3 {
+                let height = n_storeys as Float * DEFAULT_STOREY_HEIGHT;
+                let exp = tabulated_wind_coefficient(&shelter, n_storeys).unwrap();
+                let found = wind_coefficient_from_height(height, &shelter, &terrain);
+                assert!(
+                    (exp - found).abs() / exp < 0.03,
+                    "n_storeys = {}",
+                    n_storeys
+                );
+            }
+            assert!(tabulated_wind_coefficient(&shelter, 4).is_none());
+
+            let three =
+                wind_coefficient_from_height(3. * DEFAULT_STOREY_HEIGHT, &shelter, &terrain);
+            let six = wind_coefficient_from_height(6. * DEFAULT_STOREY_HEIGHT, &shelter, &terrain);
+            assert!(six > three);
+        }
+
+        // Rougher terrain slows the wind down, even though its profile is steeper
+        let height = 20.;
+        let flat = wind_coefficient_from_height(height, &ShelterClass::Urban, &Terrain::Flat);
+        let city = wind_coefficient_from_height(height, &ShelterClass::Urban, &Terrain::CityCentre);
+        assert!(city < flat);
+    }
+
+    #[test]
+    fn test_lbl_stack_coefficient() {
+        // The ASHRAE assumptions
+        let height = 7.5;
+        let exp = stack_coefficient_from_height(height);
+        let found = lbl_stack_coefficient(height, 0.25, 0.25, 20.);
+        assert!((exp - found).abs() < 1e-9);
+
+        // Uneven distribution of ceiling and floor leakage reduces the stack effect
+        let uneven = lbl_stack_coefficient(height, 0.4, 0.1, 20.);
+        assert!(uneven < found);
+
+        // No leakage in ceiling or floor: f_s = 1/3 sqrt(gH/T)
+        let found = lbl_stack_coefficient(height, 0., 0., 20.);
+        let fs = (GRAVITY * height / 293.15 as Float).sqrt() / 3.;
+        assert!((fs * fs / 100. - found).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_lbl_wind_coefficient() {
+        // At 10m, same terrain as met station, no ceiling/floor leakage
+        let shielding = 0.324;
+        let found = lbl_wind_coefficient(10., shielding, 0., &Terrain::Flat, &Terrain::Flat, 10.);
+        assert!((shielding * shielding / 100. - found).abs() < 1e-9);
+
+        // Rougher site terrain reduces wind at the building
+        let city = lbl_wind_coefficient(
+            10.,
+            shielding,
+            0.,
+            &Terrain::CityCentre,
+            &Terrain::Flat,
+            10.,
+        );
+        assert!(city < found);
+
+        // Ratios between shelter classes match the ASHRAE table
+        let open = lbl_wind_coefficient(
+            2.5,
+            shielding_coefficient(&ShelterClass::NoObstructions),
+            0.5,
+            &Terrain::Rural,
+            &Terrain::Flat,
+            10.,
+        );
+        let urban = lbl_wind_coefficient(
+            2.5,
+            shielding_coefficient(&ShelterClass::Urban),
+            0.5,
+            &Terrain::Rural,
+            &Terrain::Flat,
+            10.,
+        );
+        let exp = tabulated_wind_coefficient(&ShelterClass::Urban, 1).unwrap()
+            / tabulated_wind_coefficient(&ShelterClass::NoObstructions, 1).unwrap();
+        assert!((exp - urban / open).abs() < 0.02);
+    }
 }
@@ -0,0 +1,142 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Airflow between Spaces through large internal openings (e.g., doorways)
+
+use crate::physics::GRAVITY;
+use crate::profile::HourlyProfile;
+use crate::Float;
+
+/// A large opening between two Spaces, through which warm air flows
+/// out at the top and cool air flows in at the bottom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InternalOpening {
+    /// The name of the Space on one side of the opening
+    pub space_a: String,
+
+    /// The name of the Space on the other side of the opening
+    pub space_b: String,
+
+    /// The width of the opening, in m
+    pub width: Float,
+
+    /// The height of the opening, in m
+    pub height: Float,
+
+    /// The discharge coefficient of the opening
+    pub discharge_coefficient: Float,
+
+    /// The fraction of the opening that is open at each hour of the day.
+    /// If `None`, the opening is always open.
+    pub schedule: Option<HourlyProfile>,
+}
+
+impl InternalOpening {
+    /// Returns the fraction of the opening that is open at a
+    /// certain hour of the day
+    pub fn opening_fraction(&self, hour: Float) -> Float {
+        match &self.schedule {
+            Some(profile) => profile.at(hour),
+            None => 1.0,
+        }
+    }
+
+    /// Checks whether the opening makes sense
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if let Some(profile) = &self.schedule {
+            profile.check(&format!(
+                "The schedule of the opening between Spaces '{}' and '{}'",
+                self.space_a, self.space_b
+            ))?;
+        }
+        if self.width < 0.0 || self.height < 0.0 {
+            return Err(format!(
+                "The opening between Spaces '{}' and '{}' has negative dimensions",
+                self.space_a, self.space_b
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Calculates the airflow (in m3/s) exchanged in each direction through a
+/// large vertical opening between two spaces at different temperatures
+/// (in C), following Brown and Solvason (1962):
+/// $`Q = \frac{C_d W}{3}\sqrt{g \frac{|\Delta T|}{\bar{T}} H^3}`$.
+///
+/// The same volume of air flows in both directions, with the neutral
+/// plane at mid-height of the opening.
+pub fn bidirectional_flow(
+    width: Float,
+    height: Float,
+    discharge_coefficient: Float,
+    temperature_a: Float,
+    temperature_b: Float,
+) -> Float {
+    let delta_t = (temperature_a - temperature_b).abs();
+    let t_mean = (temperature_a + temperature_b) / 2. + 273.15;
+    discharge_coefficient * width / 3. * (GRAVITY * delta_t / t_mean * height.powi(3)).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bidirectional_flow() {
+        // A standard door, 2 K of temperature difference
+        let q = bidirectional_flow(0.8, 2.0, 0.6, 22., 20.);
+        let exp = 0.6 * 0.8 / 3. * (GRAVITY * 2. / 294.15 * 8. as Float).sqrt();
+        assert!((q - exp).abs() < 1e-9);
+        // Roughly 0.1 m3/s, as usually reported for doorways
+        assert!(q > 0.05 && q < 0.2);
+
+        // Symmetric
+        let q2 = bidirectional_flow(0.8, 2.0, 0.6, 20., 22.);
+        assert!((q - q2).abs() < 1e-12);
+
+        // No temperature difference, no flow
+        assert!(bidirectional_flow(0.8, 2.0, 0.6, 20., 20.) < 1e-12);
+    }
+
+    #[test]
+    fn test_schedule() {
+        let mut opening = InternalOpening {
+            space_a: "a".to_string(),
+            space_b: "b".to_string(),
+            width: 0.8,
+            height: 2.0,
+            discharge_coefficient: 0.6,
+            schedule: None,
+        };
+        assert!(opening.check().is_ok());
+        assert!((opening.opening_fraction(3.5) - 1.).abs() < 1e-12);
+
+        let mut profile = vec![0.0; 24];
+        profile[8] = 0.5;
+        opening.schedule = Some(profile.into());
+        assert!(opening.check().is_ok());
+        assert!((opening.opening_fraction(8.5) - 0.5).abs() < 1e-12);
+        assert!(opening.opening_fraction(9.5) < 1e-12);
+
+        opening.schedule = Some(vec![1.0; 12].into());
+        assert!(opening.check().is_err());
+    }
+}
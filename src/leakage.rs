@@ -0,0 +1,464 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Distribution of the airtightness of a building over the components of
+//! its envelope, so that each leak sees the wind pressure of its own
+//! facade and the stack pressure at its own height.
+
+use crate::catalogue::{ComponentLeak, DEFAULT_LEAKAGE_EXPONENT};
+use crate::elements::PowerLaw;
+use crate::physics::{air_density, GRAVITY};
+use crate::ventilation::{angle_difference, normalised_pressure_coefficient};
+use crate::Float;
+
+/// The pressure coefficient of a wall when the wind is normal to it
+const WALL_PRESSURE_COEFFICIENT: Float = 0.6;
+
+/// The pressure coefficient of a (flat) roof, which is under suction
+/// regardless of the wind direction
+const ROOF_PRESSURE_COEFFICIENT: Float = -0.5;
+
+/// The kinds of envelope components
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentType {
+    /// An opaque exterior wall
+    Wall,
+    /// A window, including its frame
+    Window,
+    /// An exterior door
+    Door,
+    /// A roof or a ceiling below an unconditioned attic
+    Roof,
+    /// A floor above the ground or an unconditioned crawlspace
+    Floor,
+}
+
+impl ComponentType {
+    /// The leakage of a square metre of this kind of component, relative
+    /// to a square metre of opaque wall
+    pub fn relative_leakiness(&self) -> Float {
+        match self {
+            ComponentType::Wall => 1.0,
+            ComponentType::Window => 3.0,
+            ComponentType::Door => 5.0,
+            ComponentType::Roof => 1.0,
+            ComponentType::Floor => 0.5,
+        }
+    }
+}
+
+/// A component of the envelope of a Space
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvelopeComponent {
+    /// The kind of component
+    pub kind: ComponentType,
+
+    /// The area of the component, in m2
+    pub area: Float,
+
+    /// The azimuth of the outward normal of the component, in degrees
+    /// clockwise from North. It is ignored for roofs and floors.
+    pub azimuth: Float,
+
+    /// The height of the centre of the component above the ground, in m
+    pub height: Float,
+
+    /// Overrides the relative leakiness given by the `kind` of the component
+    pub leakiness: Option<Float>,
+
+    /// The entry of the leakage catalogue that describes this component.
+    /// When given, the component keeps the leakage of the catalogue
+    /// instead of sharing the airtightness of its Building.
+    pub catalogue: Option<ComponentLeak>,
+}
+
+impl EnvelopeComponent {
+    /// Creates a component whose leakage is given by an entry of the
+    /// [`catalogue`](crate::catalogue) (e.g., 12 `"recessed_light"` in a
+    /// `Roof`, or 25 m of `"window_casement_weatherstripped"` crack in a
+    /// `Window`). It has no `area`, as it does not share the airtightness
+    /// of its Building.
+    pub fn from_catalogue(
+        kind: ComponentType,
+        name: &str,
+        quantity: Float,
+        azimuth: Float,
+        height: Float,
+    ) -> Self {
+        Self {
+            kind,
+            area: 0.0,
+            azimuth,
+            height,
+            leakiness: None,
+            catalogue: Some(ComponentLeak {
+                name: name.to_string(),
+                quantity,
+            }),
+        }
+    }
+
+    /// The relative leakiness of a square metre of the component
+    fn leakiness(&self) -> Float {
+        self.leakiness
+            .unwrap_or_else(|| self.kind.relative_leakiness())
+    }
+
+    /// The pressure coefficient of the component for a certain wind
+    /// direction (in degrees clockwise from North)
+    pub fn pressure_coefficient(&self, wind_direction: Float) -> Float {
+        match self.kind {
+            ComponentType::Roof => ROOF_PRESSURE_COEFFICIENT,
+            ComponentType::Floor => 0.0,
+            _ => {
+                WALL_PRESSURE_COEFFICIENT
+                    * normalised_pressure_coefficient(angle_difference(
+                        self.azimuth,
+                        wind_direction,
+                    ))
+            }
+        }
+    }
+}
+
+/// The airtightness of a building, as measured by a blower-door test
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Airtightness {
+    /// The air permeability at 50 Pa, in m3/(h m2) of envelope area
+    pub q50: Float,
+
+    /// The flow exponent of the leaks
+    pub exponent: Float,
+}
+
+impl Airtightness {
+    /// Distributes the leakage of a building over the components of its
+    /// envelope in proportion to their area and relative leakiness, returning
+    /// the power law of each of them. Components taken from the catalogue
+    /// get no share.
+    pub fn distribute(&self, components: &[EnvelopeComponent]) -> Vec<PowerLaw> {
+        let shared = |c: &&EnvelopeComponent| c.catalogue.is_none();
+        let envelope_area: Float = components.iter().filter(shared).map(|c| c.area).sum();
+        let weighted_area: Float = components
+            .iter()
+            .filter(shared)
+            .map(|c| c.area * c.leakiness())
+            .sum();
+        // Total flow at 50 Pa, in m3/s
+        let q50 = self.q50 * envelope_area / 3600.;
+        components
+            .iter()
+            .map(|c| {
+                let share = if c.catalogue.is_none() && weighted_area > 0.0 {
+                    c.area * c.leakiness() / weighted_area
+                } else {
+                    0.0
+                };
+                PowerLaw {
+                    coefficient: q50 * share / (50. as Float).powf(self.exponent),
+                    exponent: self.exponent,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Calculates the power law of each component of the envelope of a
+/// building. Components taken from the catalogue keep their own leakage,
+/// while the `airtightness` (if any) is distributed over the rest.
+pub fn envelope_leakage(
+    airtightness: Option<&Airtightness>,
+    components: &[EnvelopeComponent],
+) -> Result<Vec<PowerLaw>, String> {
+    let exponent = airtightness.map_or(DEFAULT_LEAKAGE_EXPONENT, |a| a.exponent);
+    let shared = match airtightness {
+        Some(a) => a.distribute(components),
+        None => {
+            if components.iter().any(|c| c.catalogue.is_none()) {
+                return Err(
+                    "Envelope components that are not taken from the catalogue need an airtightness to share"
+                        .to_string(),
+                );
+            }
+            Vec::new()
+        }
+    };
+    components
+        .iter()
+        .enumerate()
+        .map(|(i, c)| match &c.catalogue {
+            Some(leak) => leak.power_law(exponent),
+            None => Ok(shared[i]),
+        })
+        .collect()
+}
+
+/// A leak through a component of the envelope
+#[derive(Debug, Clone, PartialEq)]
+pub struct Leak {
+    /// The component in which the leak is
+    pub component: EnvelopeComponent,
+    /// The flow through the leak
+    pub power_law: PowerLaw,
+}
+
+/// The result of solving the flows through the leaks of a Space
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeakFlows {
+    /// The indoor pressure at height zero relative to the outdoor
+    /// pressure at the same height, in Pa
+    pub indoor_pressure: Float,
+
+    /// The volumetric flow through each leak, in m3/s. Positive values
+    /// mean air entering the Space.
+    pub flows: Vec<Float>,
+}
+
+impl LeakFlows {
+    /// The total outdoor air entering the Space, in m3/s
+    pub fn infiltration(&self) -> Float {
+        self.flows.iter().filter(|q| **q > 0.0).sum()
+    }
+}
+
+/// Finds the indoor pressure that balances the mass flows through the leaks
+/// of a Space, and the resulting flows.
+///
+/// The outdoor-minus-indoor pressure difference at leak $`i`$ is
+/// $`\Delta P_i = \frac{1}{2}\rho_{out} C_{p,i} U^2 - P_{in} - (\rho_{out} - \rho_{in}) g z_i`$.
+pub fn solve_leaks(
+    leaks: &[Leak],
+    wind_speed: Float,
+    wind_direction: Float,
+    indoor_temperature: Float,
+    outdoor_temperature: Float,
+) -> LeakFlows {
+    let rho_out = air_density(outdoor_temperature);
+    let rho_in = air_density(indoor_temperature);
+    let outdoor: Vec<Float> = leaks
+        .iter()
+        .map(|l| {
+            0.5 * rho_out
+                * l.component.pressure_coefficient(wind_direction)
+                * wind_speed
+                * wind_speed
+                - (rho_out - rho_in) * GRAVITY * l.component.height
+        })
+        .collect();
+
+    let mass_flow = |leak: &Leak, delta_p: Float| -> Float {
+        let temperature = if delta_p > 0.0 {
+            outdoor_temperature
+        } else {
+            indoor_temperature
+        };
+        leak.power_law.mass_flow(delta_p, temperature).0
+    };
+    let balance = |p_in: Float| -> Float {
+        leaks
+            .iter()
+            .zip(outdoor.iter())
+            .map(|(l, p_out)| mass_flow(l, p_out - p_in))
+            .sum()
+    };
+
+    // The balance decreases with the indoor pressure, and changes sign
+    // between the lowest and highest outdoor pressures
+    if leaks.is_empty() {
+        return LeakFlows {
+            indoor_pressure: 0.0,
+            flows: Vec::new(),
+        };
+    }
+    let mut lo = outdoor.iter().cloned().fold(Float::INFINITY, Float::min);
+    let mut hi = outdoor
+        .iter()
+        .cloned()
+        .fold(Float::NEG_INFINITY, Float::max);
+    for _ in 0..200 {
+        let mid = (lo + hi) / 2.;
+        if balance(mid) > 0.0 {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    let p_in = (lo + hi) / 2.;
+
+    let flows = leaks
+        .iter()
+        .zip(outdoor.iter())
+        .map(|(l, p_out)| {
+            let delta_p = p_out - p_in;
+            let m = mass_flow(l, delta_p);
+            if m > 0.0 {
+                m / rho_out
+            } else {
+                m / rho_in
+            }
+        })
+        .collect();
+
+    LeakFlows {
+        indoor_pressure: p_in,
+        flows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn component(
+        kind: ComponentType,
+        area: Float,
+        azimuth: Float,
+        height: Float,
+    ) -> EnvelopeComponent {
+        EnvelopeComponent {
+            kind,
+            area,
+            azimuth,
+            height,
+            leakiness: None,
+            catalogue: None,
+        }
+    }
+
+    #[test]
+    fn test_distribute() {
+        let components = vec![
+            component(ComponentType::Wall, 30., 0., 1.5),
+            component(ComponentType::Window, 10., 180., 1.5),
+            component(ComponentType::Roof, 40., 0., 3.),
+        ];
+        let airtightness = Airtightness {
+            q50: 3.,
+            exponent: DEFAULT_LEAKAGE_EXPONENT,
+        };
+        let leaks = airtightness.distribute(&components);
+        // The total flow at 50 Pa is q50 times the envelope area
+        let total: Float = leaks
+            .iter()
+            .map(|p| p.coefficient * (50. as Float).powf(DEFAULT_LEAKAGE_EXPONENT))
+            .sum();
+        assert!((total - 3. * 80. / 3600.).abs() < 1e-9);
+        // Windows leak three times more per square metre than walls
+        let wall = leaks[0].coefficient / 30.;
+        let window = leaks[1].coefficient / 10.;
+        assert!((window / wall - 3.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_wind_driven() {
+        // Two equal facades, facing North and South
+        let airtightness = Airtightness {
+            q50: 3.,
+            exponent: DEFAULT_LEAKAGE_EXPONENT,
+        };
+        let components = vec![
+            component(ComponentType::Wall, 30., 0., 1.5),
+            component(ComponentType::Wall, 30., 180., 1.5),
+        ];
+        let leaks: Vec<Leak> = components
+            .iter()
+            .zip(airtightness.distribute(&components).iter())
+            .map(|(c, p)| Leak {
+                component: c.clone(),
+                power_law: *p,
+            })
+            .collect();
+
+        // Wind from the North, no temperature difference: air enters
+        // through the northern facade and leaves through the southern one
+        let res = solve_leaks(&leaks, 5., 0., 20., 20.);
+        assert!(res.flows[0] > 0.);
+        assert!(res.flows[1] < 0.);
+        assert!((res.flows[0] + res.flows[1]).abs() < 1e-9);
+        assert!((res.infiltration() - res.flows[0]).abs() < 1e-12);
+
+        // No wind, no temperature difference: no flow
+        let res = solve_leaks(&leaks, 0., 0., 20., 20.);
+        assert!(res.infiltration() < 1e-9);
+    }
+
+    #[test]
+    fn test_stack_driven() {
+        // Warm inside, no wind: air enters at the bottom and leaves at the top
+        let airtightness = Airtightness {
+            q50: 3.,
+            exponent: DEFAULT_LEAKAGE_EXPONENT,
+        };
+        let components = vec![
+            component(ComponentType::Floor, 40., 0., 0.),
+            component(ComponentType::Wall, 30., 0., 1.5),
+            component(ComponentType::Roof, 40., 0., 3.),
+        ];
+        let leaks: Vec<Leak> = components
+            .iter()
+            .zip(airtightness.distribute(&components).iter())
+            .map(|(c, p)| Leak {
+                component: c.clone(),
+                power_law: *p,
+            })
+            .collect();
+        let res = solve_leaks(&leaks, 0., 0., 20., 0.);
+        assert!(res.flows[0] > 0.);
+        assert!(res.flows[2] < 0.);
+        let rho_in = air_density(20.);
+        let rho_out = air_density(0.);
+        let mass: Float = res
+            .flows
+            .iter()
+            .map(|q| if *q > 0. { q * rho_out } else { q * rho_in })
+            .sum();
+        assert!(mass.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_envelope_leakage() {
+        let light =
+            EnvelopeComponent::from_catalogue(ComponentType::Roof, "recessed_light", 6., 0., 3.);
+        let components = vec![component(ComponentType::Wall, 30., 0., 1.5), light];
+        let airtightness = Airtightness {
+            q50: 3.,
+            exponent: DEFAULT_LEAKAGE_EXPONENT,
+        };
+        let laws = envelope_leakage(Some(&airtightness), &components).unwrap();
+        // The wall takes all of the airtightness...
+        let wall = laws[0].coefficient * (50. as Float).powf(DEFAULT_LEAKAGE_EXPONENT);
+        assert!((wall - 3. * 30. / 3600.).abs() < 1e-9);
+        // ... and the lights keep their catalogue leakage
+        let exp = ComponentLeak {
+            name: "recessed_light".to_string(),
+            quantity: 6.,
+        }
+        .power_law(DEFAULT_LEAKAGE_EXPONENT)
+        .unwrap();
+        assert!((laws[1].coefficient - exp.coefficient).abs() < 1e-12);
+
+        // Without an airtightness, every component needs a catalogue entry
+        assert!(envelope_leakage(None, &components).is_err());
+        assert!(envelope_leakage(None, &components[1..]).is_ok());
+
+        let unknown = EnvelopeComponent::from_catalogue(ComponentType::Wall, "nope", 1., 0., 1.);
+        assert!(envelope_leakage(None, &[unknown]).is_err());
+    }
+}
@@ -21,8 +21,9 @@ SOFTWARE.
 //! A simple AirFlow model for SIMPLE Building Simulation
 //!
 //! It uses the equations available in EnergyPlus' Engineering Reference
-//! for calculating the infiltration rates. No ventiation is calculated
-//! yet.
+//! for calculating the infiltration rates. Natural ventilation through
+//! operable openings can be added to each Space through the
+//! [`AirFlowModelOptions`](options::AirFlowModelOptions).
 
 /// The kind of Floating point number used in the
 /// library... the `"float"` feature means it becomes `f32`
@@ -33,6 +34,29 @@ type Float = f32;
 #[cfg(not(feature = "float"))]
 type Float = f64;
 
+pub mod catalogue;
+pub mod comfort;
+pub mod design_load;
+pub mod doas;
+pub mod ducts;
+pub mod economizer;
+pub mod elements;
+pub mod en16798;
 mod eplus;
+pub mod interzone;
+pub mod leakage;
+pub mod mechanical;
 pub mod model;
+pub mod options;
+pub mod output;
+pub mod physics;
+pub mod profile;
+mod random;
+pub mod residential_ventilation;
 mod resolvers;
+pub mod stack;
+pub mod statistics;
+pub mod trickle_vents;
+pub mod ventilation;
+pub mod ventilation_rate;
+pub mod window_control;
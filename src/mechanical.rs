@@ -0,0 +1,435 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Combination of natural infiltration with mechanical ventilation.
+//!
+//! Unbalanced fans pressurise or depressurise the Space, so their flow does
+//! not simply add to the infiltration. Following the ASHRAE Handbook of
+//! Fundamentals, the balanced part adds linearly and the unbalanced part
+//! adds in quadrature:
+//! $`Q = Q_{bal} + \sqrt{Q_{inf}^2 + Q_{unbal}^2}`$.
+
+use crate::physics::{air_density, AIR_SPECIFIC_HEAT};
+use crate::profile::HourlyProfile;
+use crate::Float;
+
+/// How the electric power of a fan is described
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FanPower {
+    /// The specific fan power, in W/(m3/s)
+    SpecificFanPower(Float),
+    /// The pressure rise of the fan and its total efficiency
+    PressureRise {
+        /// The total pressure rise, in Pa
+        pressure_rise: Float,
+        /// The total (i.e., fan, motor and drive) efficiency, between 0 and 1
+        total_efficiency: Float,
+    },
+}
+
+impl FanPower {
+    /// The specific fan power, in W/(m3/s)
+    pub fn specific_fan_power(&self) -> Float {
+        match self {
+            FanPower::SpecificFanPower(sfp) => *sfp,
+            FanPower::PressureRise {
+                pressure_rise,
+                total_efficiency,
+            } => pressure_rise / total_efficiency,
+        }
+    }
+}
+
+/// A supply or exhaust fan of a mechanical ventilation system
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fan {
+    /// The electric power of the fan
+    pub power: FanPower,
+
+    /// The fraction of the fan's power that ends up as heat in the
+    /// airstream (1 when the motor is in the airstream)
+    pub heat_to_air: Float,
+}
+
+impl Fan {
+    /// The electric power of the fan (in W) when moving a certain flow (in m3/s)
+    pub fn power(&self, flow: Float) -> Float {
+        self.power.specific_fan_power() * flow
+    }
+
+    /// The temperature rise (in K) of the air moved by the fan at a certain
+    /// temperature (in C), $`\Delta T = f \frac{SFP}{\rho c_p}`$
+    pub fn temperature_rise(&self, temperature: Float) -> Float {
+        self.heat_to_air * self.power.specific_fan_power()
+            / (air_density(temperature) * AIR_SPECIFIC_HEAT)
+    }
+
+    /// Checks whether the fan makes sense. The `owner` describes what the
+    /// fan belongs to in the error messages (e.g., "Space 'kitchen'").
+    pub(crate) fn check(&self, owner: &str) -> Result<(), String> {
+        if let FanPower::PressureRise {
+            total_efficiency, ..
+        } = self.power
+        {
+            if total_efficiency <= 0.0 || total_efficiency > 1.0 {
+                return Err(format!(
+                    "A fan of {} has a total efficiency of {}... it must be between 0 and 1",
+                    owner, total_efficiency
+                ));
+            }
+        }
+        if self.power.specific_fan_power() < 0.0 {
+            return Err(format!("A fan of {} has a negative power", owner));
+        }
+        if !(0.0..=1.0).contains(&self.heat_to_air) {
+            return Err(format!(
+                "A fan of {} has a fraction of heat to the airstream out of the range [0, 1]",
+                owner
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The mechanical ventilation of a Space
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MechanicalVentilation {
+    /// The outdoor air supplied by fans, in m3/s
+    pub supply_flow: Float,
+
+    /// The air extracted by fans, in m3/s
+    pub exhaust_flow: Float,
+
+    /// The outdoor air (in m3/s) supplied whatever the `schedule` (e.g.,
+    /// the minimum required by ASHRAE 62.1). Raising the supply up to it
+    /// raises the exhaust by the same amount, so the imbalance is kept.
+    pub minimum_supply_flow: Float,
+
+    /// The temperature of the supplied air, in C. If `None`, it is
+    /// supplied at the outdoor temperature.
+    pub supply_temperature: Option<Float>,
+
+    /// The fraction of each hour of the day during which the fans run.
+    /// If `None`, they always run.
+    pub schedule: Option<HourlyProfile>,
+
+    /// The fan supplying air, if its power is to be accounted for
+    pub supply_fan: Option<Fan>,
+
+    /// The fan extracting air, if its power is to be accounted for
+    pub exhaust_fan: Option<Fan>,
+}
+
+impl MechanicalVentilation {
+    /// Returns the fraction of a certain hour of the day during
+    /// which the fans run
+    pub fn running_fraction(&self, hour: Float) -> Float {
+        match &self.schedule {
+            Some(profile) => profile.at(hour),
+            None => 1.0,
+        }
+    }
+
+    /// Returns the supply and exhaust flows (in m3/s) when the fans
+    /// run a certain fraction of the time
+    pub fn flows(&self, running_fraction: Float) -> (Float, Float) {
+        let supply = self.supply_flow * running_fraction;
+        let increase = (self.minimum_supply_flow - supply).max(0.0);
+        (
+            supply + increase,
+            self.exhaust_flow * running_fraction + increase,
+        )
+    }
+
+    /// Checks whether the mechanical ventilation makes sense
+    pub(crate) fn check(&self, space_name: &str) -> Result<(), String> {
+        if let Some(profile) = &self.schedule {
+            profile.check(&format!(
+                "The mechanical ventilation schedule of Space '{}'",
+                space_name
+            ))?;
+        }
+        for fan in self.supply_fan.iter().chain(self.exhaust_fan.iter()) {
+            fan.check(&format!("Space '{}'", space_name))?;
+        }
+        if self.supply_flow < 0.0 || self.exhaust_flow < 0.0 || self.minimum_supply_flow < 0.0 {
+            return Err(format!(
+                "The mechanical ventilation of Space '{}' has negative flows",
+                space_name
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// When a local exhaust fan runs
+#[derive(Debug, Clone, PartialEq)]
+pub enum FanOperation {
+    /// The fan always runs
+    Always,
+    /// The fraction of each hour of the day during which the fan runs
+    Schedule(HourlyProfile),
+    /// The fan runs whenever the Space is occupied
+    Occupancy,
+}
+
+/// Where the air extracted by a local exhaust fan is replaced from
+#[derive(Debug, Clone, PartialEq)]
+pub enum MakeUpAir {
+    /// From outdoors, through the envelope of the Space
+    Infiltration,
+    /// From an adjacent Space, given by its name
+    Space(String),
+}
+
+/// An intermittent local exhaust fan (e.g., in a kitchen or a bathroom)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExhaustFan {
+    /// The flow extracted by the fan when running, in m3/s
+    pub flow: Float,
+
+    /// When the fan runs
+    pub operation: FanOperation,
+
+    /// The fan, if its power is to be accounted for. Its heat leaves
+    /// with the extracted air.
+    pub fan: Option<Fan>,
+
+    /// Where the extracted air is replaced from
+    pub make_up_air: MakeUpAir,
+}
+
+impl ExhaustFan {
+    /// Returns the fraction of a certain hour of the day during which
+    /// the fan runs, given whether the Space is occupied
+    pub fn running_fraction(&self, hour: Float, occupied: bool) -> Float {
+        match &self.operation {
+            FanOperation::Always => 1.0,
+            FanOperation::Schedule(profile) => profile.at(hour),
+            FanOperation::Occupancy => {
+                if occupied {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// The electric power of the fan (in W) when it runs a certain
+    /// fraction of the time
+    pub fn power(&self, running_fraction: Float) -> Float {
+        self.fan
+            .map_or(0.0, |fan| fan.power(self.flow * running_fraction))
+    }
+
+    /// Checks whether the fan makes sense
+    pub(crate) fn check(&self, space_name: &str) -> Result<(), String> {
+        if let FanOperation::Schedule(profile) = &self.operation {
+            profile.check(&format!(
+                "The schedule of an exhaust fan of Space '{}'",
+                space_name
+            ))?;
+        }
+        if let Some(fan) = &self.fan {
+            fan.check(&format!("Space '{}'", space_name))?;
+        }
+        if self.flow < 0.0 {
+            return Err(format!(
+                "An exhaust fan of Space '{}' has a negative flow",
+                space_name
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The outdoor air entering a Space when infiltration and mechanical
+/// ventilation are combined
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Superposition {
+    /// The air entering through the envelope, in m3/s
+    pub envelope: Float,
+
+    /// The air entering through supply fans, in m3/s
+    pub fans: Float,
+}
+
+impl Superposition {
+    /// The total outdoor air entering the Space, in m3/s
+    pub fn total(&self) -> Float {
+        self.envelope + self.fans
+    }
+}
+
+/// Combines the natural infiltration of a Space (in m3/s) with the flows
+/// of its supply and exhaust fans (in m3/s).
+///
+/// When the exhaust dominates, the unbalanced part enters through the
+/// envelope. When the supply dominates, it enters through the fans, and the
+/// envelope only lets in what the superposition adds on top of it.
+pub fn superpose(infiltration: Float, supply: Float, exhaust: Float) -> Superposition {
+    let balanced = supply.min(exhaust);
+    let unbalanced = (supply - exhaust).abs();
+    let combined = (infiltration * infiltration + unbalanced * unbalanced).sqrt();
+    if exhaust >= supply {
+        Superposition {
+            envelope: combined,
+            fans: balanced,
+        }
+    } else {
+        Superposition {
+            envelope: (combined - unbalanced).max(0.0),
+            fans: supply,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_superpose() {
+        // No fans: only infiltration
+        let s = superpose(0.03, 0., 0.);
+        assert!((s.envelope - 0.03).abs() < 1e-12);
+        assert!(s.fans.abs() < 1e-12);
+
+        // Balanced: linear addition
+        let s = superpose(0.03, 0.05, 0.05);
+        assert!((s.total() - 0.08).abs() < 1e-12);
+        assert!((s.fans - 0.05).abs() < 1e-12);
+
+        // Exhaust only: quadrature, everything through the envelope
+        let s = superpose(0.03, 0., 0.04);
+        assert!((s.total() - 0.05).abs() < 1e-12);
+        assert!(s.fans.abs() < 1e-12);
+
+        // Supply only: quadrature, mostly through the fans
+        let s = superpose(0.03, 0.04, 0.);
+        assert!((s.total() - 0.05).abs() < 1e-12);
+        assert!((s.fans - 0.04).abs() < 1e-12);
+        assert!((s.envelope - 0.01).abs() < 1e-12);
+
+        // Mixed: Q_bal + sqrt(Q_inf^2 + Q_unbal^2)
+        let s = superpose(0.03, 0.02, 0.06);
+        assert!((s.total() - 0.07).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_fan() {
+        let fan = Fan {
+            power: FanPower::PressureRise {
+                pressure_rise: 600.,
+                total_efficiency: 0.5,
+            },
+            heat_to_air: 1.0,
+        };
+        assert!(fan.check("some space").is_ok());
+        assert!((fan.power.specific_fan_power() - 1200.).abs() < 1e-9);
+        assert!((fan.power(0.1) - 120.).abs() < 1e-9);
+        // About 1 K per 1200 W/(m3/s)
+        let exp = 1200. / (air_density(20.) * AIR_SPECIFIC_HEAT);
+        assert!((fan.temperature_rise(20.) - exp).abs() < 1e-9);
+        assert!((fan.temperature_rise(20.) - 1.).abs() < 0.02);
+
+        let fan = Fan {
+            power: FanPower::SpecificFanPower(1200.),
+            heat_to_air: 0.0,
+        };
+        assert!(fan.temperature_rise(20.).abs() < 1e-12);
+
+        let fan = Fan {
+            power: FanPower::PressureRise {
+                pressure_rise: 600.,
+                total_efficiency: 0.0,
+            },
+            heat_to_air: 1.0,
+        };
+        assert!(fan.check("some space").is_err());
+    }
+
+    #[test]
+    fn test_exhaust_fan() {
+        let mut profile = vec![0.0; 24];
+        profile[7] = 0.25;
+        let mut fan = ExhaustFan {
+            flow: 0.015,
+            operation: FanOperation::Schedule(profile.into()),
+            fan: Some(Fan {
+                power: FanPower::SpecificFanPower(300.),
+                heat_to_air: 0.0,
+            }),
+            make_up_air: MakeUpAir::Infiltration,
+        };
+        assert!(fan.check("bathroom").is_ok());
+        assert!((fan.running_fraction(7.5, false) - 0.25).abs() < 1e-12);
+        assert!(fan.running_fraction(12., true).abs() < 1e-12);
+        assert!((fan.power(0.25) - 300. * 0.015 * 0.25).abs() < 1e-12);
+
+        fan.operation = FanOperation::Occupancy;
+        assert!((fan.running_fraction(3., true) - 1.).abs() < 1e-12);
+        assert!(fan.running_fraction(3., false).abs() < 1e-12);
+
+        fan.operation = FanOperation::Schedule(vec![1.; 2].into());
+        assert!(fan.check("bathroom").is_err());
+
+        fan.operation = FanOperation::Always;
+        fan.fan = None;
+        assert!(fan.check("bathroom").is_ok());
+        assert!(fan.power(1.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_check() {
+        let mut ventilation = MechanicalVentilation {
+            supply_flow: 0.05,
+            exhaust_flow: 0.05,
+            ..MechanicalVentilation::default()
+        };
+        assert!(ventilation.check("some space").is_ok());
+        ventilation.schedule = Some(vec![1.; 23].into());
+        assert!(ventilation.check("some space").is_err());
+        ventilation.schedule = None;
+        ventilation.exhaust_flow = -1.;
+        assert!(ventilation.check("some space").is_err());
+    }
+
+    #[test]
+    fn test_minimum_supply_flow() {
+        let ventilation = MechanicalVentilation {
+            supply_flow: 0.05,
+            exhaust_flow: 0.07,
+            minimum_supply_flow: 0.02,
+            schedule: Some(HourlyProfile::constant(0.)),
+            ..MechanicalVentilation::default()
+        };
+        // Off: the minimum is still supplied, and extracted
+        let (supply, exhaust) = ventilation.flows(0.0);
+        assert!((supply - 0.02).abs() < 1e-12 && (exhaust - 0.02).abs() < 1e-12);
+        // Half the time: above the minimum
+        let (supply, exhaust) = ventilation.flows(0.5);
+        assert!((supply - 0.025).abs() < 1e-12 && (exhaust - 0.035).abs() < 1e-12);
+        // A fifth of the time: the imbalance is kept
+        let (supply, exhaust) = ventilation.flows(0.2);
+        assert!((supply - 0.02).abs() < 1e-12 && (exhaust - 0.024).abs() < 1e-12);
+    }
+}
@@ -18,20 +18,433 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
 
-// use crate::Float;
+use crate::comfort::RunningMeanTemperature;
+use crate::doas::{condition, CoilLoads, Doas, DoasEnergy};
+use crate::ducts::{DuctLeakageFlows, DuctLocation, DuctSystem};
+use crate::economizer::{Economizer, EconomizerConditions};
+use crate::en16798::{solve_building, En16798Building, En16798Flows, En16798Zone};
+use crate::interzone::{bidirectional_flow, InternalOpening};
+use crate::leakage::{envelope_leakage, solve_leaks, Leak};
+use crate::mechanical::{superpose, MakeUpAir, Superposition};
+use crate::options::{AirFlowModelOptions, SpaceAirFlowOptions, Terrain};
+use crate::physics::humidity_ratio;
+use crate::residential_ventilation::{Dwelling, DwellingLeakage};
 use crate::resolvers::*;
+use crate::stack::{solve_stack, StackVentilation};
+use crate::statistics::{SpaceStatistics, SpaceSummary, TimestepConditions};
+use crate::trickle_vents::{trickle_vents_airflow, TrickleVentConditions, TrickleVentControl};
+use crate::ventilation::NaturalVentilation;
+use crate::ventilation_rate::{apply_minimum_ventilation, VentilationReport};
+use crate::window_control::{ControlConditions, WindowState};
+use crate::Float;
 use calendar::Date;
 use communication_protocols::{ErrorHandling, MetaOptions, SimulationModel};
 use simple_model::{
     Infiltration, SimpleModel, SimulationState, SimulationStateElement, SimulationStateHeader,
+    Space,
 };
 use std::borrow::Borrow;
+use std::sync::Arc;
 use weather::{CurrentWeather, Weather};
 
 pub type Resolver = Box<dyn Fn(&CurrentWeather, &mut SimulationState)>;
 
+/// Describes where a stack or wind coefficient came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoefficientSource {
+    /// It was given explicitly in the `Building`
+    Input,
+    /// It was taken from the ASHRAE tables, which cover buildings
+    /// of 1 to 3 storeys
+    Table,
+    /// It was derived from the height of the `Building`
+    Height,
+    /// It was calculated with the complete Sherman-Grimsrud model, based
+    /// on the [`LblParameters`](crate::options::LblParameters) of the `Building`
+    Lbl,
+}
+
+/// The stack and wind coefficients used for calculating the infiltration of
+/// a Space with an `Infiltration::EffectiveAirLeakageArea`
+#[derive(Debug, Clone, Copy)]
+pub struct LeakageCoefficients {
+    /// The index of the Space in the `SimpleModel`
+    pub space_index: usize,
+    /// The stack coefficient, in $`(L/s)^2/(cm^4 K)`$
+    pub stack: Float,
+    /// Where the stack coefficient came from
+    pub stack_source: CoefficientSource,
+    /// The wind coefficient, in $`(L/s)^2/(cm^4 (m/s)^2)`$
+    pub wind: Float,
+    /// Where the wind coefficient came from
+    pub wind_source: CoefficientSource,
+}
+
+/// A Space with operable openings
+struct VentilatedSpace {
+    space: Arc<Space>,
+    ventilation: NaturalVentilation,
+    options: SpaceAirFlowOptions,
+    /// The terrain surrounding the Building of the Space
+    terrain: Terrain,
+}
+
+/// A Space whose infiltration is calculated through the leaks of its envelope
+pub(crate) struct LeakySpace {
+    pub(crate) space_index: usize,
+    space: Arc<Space>,
+    pub(crate) leaks: Vec<Leak>,
+    /// The height at which the wind speed is evaluated, in m
+    reference_height: Float,
+    terrain: Terrain,
+}
+
+impl LeakySpace {
+    /// The wind speed (in m/s) at the `reference_height`, for a certain
+    /// wind speed at the meteorological station
+    pub(crate) fn local_wind_speed(&self, wind_speed: Float) -> Float {
+        let (alpha, gamma) = self.terrain.parameters();
+        wind_speed * alpha * (self.reference_height / 10.).powf(gamma)
+    }
+}
+
+/// Distributes the airtightness of each Building over the envelope of its
+/// Spaces. Components taken from the catalogue keep their own leakage, so
+/// Buildings whose envelope is entirely made of them need no airtightness.
+pub(crate) fn distribute_leakage(
+    model: &SimpleModel,
+    options: &AirFlowModelOptions,
+) -> Result<Vec<LeakySpace>, String> {
+    let mut building_names: Vec<&String> = Vec::new();
+    for space in model.spaces.iter() {
+        let has_envelope = options
+            .spaces
+            .get(&space.name)
+            .map_or(false, |o| !o.envelope.is_empty());
+        if let Ok(building_name) = space.building() {
+            if has_envelope && !building_names.contains(&building_name) {
+                building_names.push(building_name);
+            }
+        }
+    }
+
+    for (building_name, building_options) in options.buildings.iter() {
+        if building_options.airtightness.is_some() && !building_names.contains(&building_name) {
+            return Err(format!(
+                "Building '{}' has an airtightness but none of its Spaces has an envelope",
+                building_name
+            ));
+        }
+    }
+
+    let mut leaky_spaces = Vec::new();
+    for building_name in building_names {
+        let building_options = options
+            .buildings
+            .get(building_name)
+            .cloned()
+            .unwrap_or_default();
+        let mut spaces = Vec::new();
+        let mut components = Vec::new();
+        for (i, space) in model.spaces.iter().enumerate() {
+            let in_building = space
+                .building()
+                .map_or(false, |b| b.as_str() == building_name.as_str());
+            if !in_building {
+                continue;
+            }
+            if let Some(space_options) = options.spaces.get(&space.name) {
+                if !space_options.envelope.is_empty() {
+                    spaces.push((i, space_options.envelope.len()));
+                    components.extend_from_slice(&space_options.envelope);
+                }
+            }
+        }
+        let airtightness = building_options.airtightness.as_ref();
+        let from_catalogue = components.iter().any(|c| c.catalogue.is_some());
+        if airtightness.is_none() && !from_catalogue {
+            continue;
+        }
+        let power_laws = envelope_leakage(airtightness, &components)
+            .map_err(|e| format!("Building '{}': {}", building_name, e))?;
+        let reference_height = building_options.height.unwrap_or_else(|| {
+            components
+                .iter()
+                .map(|c| c.height)
+                .fold(Float::NEG_INFINITY, Float::max)
+        });
+        let mut leaks =
+            components
+                .into_iter()
+                .zip(power_laws.into_iter())
+                .map(|(component, power_law)| Leak {
+                    component,
+                    power_law,
+                });
+        for (space_index, n) in spaces {
+            leaky_spaces.push(LeakySpace {
+                space_index,
+                space: Arc::clone(&model.spaces[space_index]),
+                leaks: leaks.by_ref().take(n).collect(),
+                reference_height,
+                terrain: building_options.terrain,
+            });
+        }
+    }
+    leaky_spaces.sort_by_key(|l| l.space_index);
+    Ok(leaky_spaces)
+}
+
+/// A Space whose airflows are calculated with EN 16798-7
+struct StandardZone {
+    space_index: usize,
+    space: Arc<Space>,
+    zone: En16798Zone,
+}
+
+/// A Building whose EN 16798-7 zones are balanced together
+pub(crate) struct StandardBuilding {
+    pub(crate) building: En16798Building,
+    /// The index of each of its zones within the `en16798_zones`
+    pub(crate) zones: Vec<usize>,
+}
+
+/// Groups the EN 16798-7 zones by the Building of their Space, whose
+/// height and terrain are taken from its options
+pub(crate) fn group_standard_zones(
+    model: &SimpleModel,
+    options: &AirFlowModelOptions,
+) -> Result<Vec<StandardBuilding>, String> {
+    let mut standard_buildings: Vec<(String, StandardBuilding)> = Vec::new();
+    for (j, zone) in options.en16798_zones.iter().enumerate() {
+        let space = find_space(model, &zone.space)?;
+        let building_name = space.building().map_err(|_| {
+            format!(
+                "Space '{}' is an EN 16798-7 zone but has no building... Assign a Building to it.",
+                space.name
+            )
+        })?;
+        if let Some((_, standard)) = standard_buildings
+            .iter_mut()
+            .find(|(name, _)| name.as_str() == building_name.as_str())
+        {
+            standard.zones.push(j);
+            continue;
+        }
+        let building = model.get_building(building_name)?;
+        let building_options = options
+            .buildings
+            .get(building_name)
+            .cloned()
+            .unwrap_or_default();
+        let height = required_building_height(&building, &building_options, "EN 16798-7")?;
+        standard_buildings.push((
+            building_name.clone(),
+            StandardBuilding {
+                building: En16798Building {
+                    height,
+                    terrain: building_options.terrain,
+                },
+                zones: vec![j],
+            },
+        ));
+    }
+    Ok(standard_buildings.into_iter().map(|(_, b)| b).collect())
+}
+
+/// A Space with supply, exhaust or whole-house fans, or served by a DOAS
+/// or leaky ducts
+struct MechanicallyVentilatedSpace {
+    space_index: usize,
+    space: Arc<Space>,
+    options: SpaceAirFlowOptions,
+    /// The Space (and its index in the model) from which the air extracted
+    /// by each exhaust fan is replaced, if not from outdoors
+    make_up_spaces: Vec<Option<(usize, Arc<Space>)>>,
+    /// The index of each duct system serving the Space within the `leaky_ducts`
+    duct_systems: Vec<usize>,
+    /// The indices of each DOAS serving the Space within the `doas_systems`
+    /// and of its outlet
+    doas_outlets: Vec<(usize, usize)>,
+    /// The index of the dwelling represented by the Space within the
+    /// `dwellings`, if any
+    dwelling: Option<usize>,
+}
+
+impl MechanicallyVentilatedSpace {
+    /// The flows (in m3/s) drawn at a certain hour of the day by the exhaust
+    /// fans whose make-up air comes from a neighbour, along with the index
+    /// of the neighbour in the model and the neighbour itself
+    fn make_up_draws(&self, hour: Float) -> impl Iterator<Item = (usize, &Arc<Space>, Float)> + '_ {
+        let occupied = self.options.is_occupied(hour);
+        self.options
+            .exhaust_fans
+            .iter()
+            .zip(self.make_up_spaces.iter())
+            .filter_map(move |(fan, make_up_space)| {
+                make_up_space.as_ref().map(|(j, neighbour)| {
+                    (
+                        *j,
+                        neighbour,
+                        fan.flow * fan.running_fraction(hour, occupied),
+                    )
+                })
+            })
+    }
+}
+
+/// A Space with trickle vents
+struct TrickleVentedSpace {
+    space: Arc<Space>,
+    options: SpaceAirFlowOptions,
+}
+
+/// A tall volume ventilated by the stack effect
+struct StackVolume {
+    /// The Space associated with each opening
+    opening_spaces: Vec<Arc<Space>>,
+    /// The Spaces that make up the volume (without repetitions)
+    spaces: Vec<Arc<Space>>,
+    ventilation: StackVentilation,
+}
+
+/// A large opening between two Spaces
+struct InternalLink {
+    space_a: Arc<Space>,
+    space_b: Arc<Space>,
+    opening: InternalOpening,
+}
+
+/// A dedicated outdoor air system
+struct DoasSystem {
+    /// The Space served by each outlet
+    spaces: Vec<Arc<Space>>,
+    doas: Doas,
+}
+
+/// A dwelling with a whole-house ventilation fan
+struct VentilatedDwelling {
+    space_index: usize,
+    dwelling: Dwelling,
+}
+
+/// A Space whose statistics are accumulated
+struct MonitoredSpace {
+    space: Arc<Space>,
+    options: SpaceAirFlowOptions,
+    /// The index of the Space within the `ventilated_spaces`, if it
+    /// has operable openings
+    window: Option<usize>,
+}
+
+/// An outdoor air economizer
+struct EconomizedSpace {
+    space_index: usize,
+    space: Arc<Space>,
+    economizer: Economizer,
+}
+
+/// An air distribution system whose ducts leak
+struct LeakyDucts {
+    space: Arc<Space>,
+    /// The Space in which the supply ducts run, if any
+    supply_space: Option<Arc<Space>>,
+    /// The Space in which the return ducts run, if any
+    return_space: Option<Arc<Space>>,
+    system: DuctSystem,
+}
+
+/// Finds a Space by name
+fn find_space(model: &SimpleModel, name: &str) -> Result<Arc<Space>, String> {
+    find_space_index(model, name).map(|i| Arc::clone(&model.spaces[i]))
+}
+
+/// Finds the index of a Space in the model, by name
+fn find_space_index(model: &SimpleModel, name: &str) -> Result<usize, String> {
+    model
+        .spaces
+        .iter()
+        .position(|s| s.name == name)
+        .ok_or_else(|| format!("Could not find Space '{}'", name))
+}
+
 pub struct AirFlowModel {
     infiltration_calcs: Vec<Resolver>,
+
+    /// The tall volumes ventilated by the stack effect
+    stack_volumes: Vec<StackVolume>,
+
+    /// The large openings between Spaces
+    internal_links: Vec<InternalLink>,
+
+    /// The Spaces that have operable openings
+    ventilated_spaces: Vec<VentilatedSpace>,
+
+    /// The Spaces that have trickle vents
+    trickle_vented_spaces: Vec<TrickleVentedSpace>,
+
+    /// The Spaces whose infiltration is calculated through
+    /// the leaks of their envelope
+    leaky_spaces: Vec<LeakySpace>,
+
+    /// The Spaces whose airflows are calculated with EN 16798-7
+    standard_zones: Vec<StandardZone>,
+
+    /// The Buildings containing the `standard_zones`
+    standard_buildings: Vec<StandardBuilding>,
+
+    /// The Spaces that have supply or exhaust fans, or are served by
+    /// leaky ducts
+    mechanically_ventilated_spaces: Vec<MechanicallyVentilatedSpace>,
+
+    /// The air distribution systems whose ducts leak
+    leaky_ducts: Vec<LeakyDucts>,
+
+    /// The dedicated outdoor air systems
+    doas_systems: Vec<DoasSystem>,
+
+    /// The outdoor air economizers
+    economized_spaces: Vec<EconomizedSpace>,
+
+    /// The dwellings ventilated according to ASHRAE 62.2
+    dwellings: Vec<VentilatedDwelling>,
+
+    /// Every Space in the model, whose statistics are accumulated
+    monitored_spaces: Vec<MonitoredSpace>,
+
+    /// The timestep, in seconds
+    dt: Float,
+
+    /// The seed of the random numbers used by stochastic models
+    random_seed: u64,
+
+    /// The coefficients used by the Spaces with an
+    /// `Infiltration::EffectiveAirLeakageArea`
+    leakage_coefficients: Vec<LeakageCoefficients>,
+
+    /// The results of the Ventilation Rate Procedure for each
+    /// of the `ventilation_systems`
+    ventilation_reports: Vec<VentilationReport>,
+}
+
+impl AirFlowModel {
+    /// Reports the stack and wind coefficients used by each Space with an
+    /// `Infiltration::EffectiveAirLeakageArea`, and how they were obtained.
+    pub fn leakage_coefficients(&self) -> &[LeakageCoefficients] {
+        &self.leakage_coefficients
+    }
+
+    /// Reports the minimum outdoor airflows given by the Ventilation Rate
+    /// Procedure to the `ventilation_systems`, which are used when marching.
+    pub fn ventilation_reports(&self) -> &[VentilationReport] {
+        &self.ventilation_reports
+    }
+
+    /// Reports the dwellings, whose `leakage` (when not given) is taken from
+    /// the infiltration or the envelope of their Space
+    pub fn dwellings(&self) -> Vec<&Dwelling> {
+        self.dwellings.iter().map(|d| &d.dwelling).collect()
+    }
 }
 
 impl ErrorHandling for AirFlowModel {
@@ -41,27 +454,182 @@ impl ErrorHandling for AirFlowModel {
 }
 
 /// The memory needed to run this simulation
-pub type AirFlowModelMemory = ();
+#[derive(Debug, Clone, Default)]
+pub struct AirFlowModelMemory {
+    /// The state of the openings of each naturally ventilated Space
+    pub windows: Vec<WindowState>,
+
+    /// The running mean of the daily outdoor temperature, which
+    /// builds up across calls to `march`
+    pub outdoor_running_mean: RunningMeanTemperature,
+
+    /// The neutral pressure level (in m) of each volume ventilated by
+    /// the stack effect, during the last timestep
+    pub neutral_pressure_levels: Vec<Option<Float>>,
+
+    /// The flow (in m3/s) through each leak of the envelope of the Spaces
+    /// whose Building has an airtightness, during the last timestep.
+    /// Positive values mean air entering the Space.
+    pub leak_flows: Vec<Vec<Float>>,
+
+    /// The flows and energy lost through the leaks of each duct
+    /// system, during the last timestep
+    pub duct_leakage: Vec<DuctLeakageFlows>,
+
+    /// The airflows of each Space calculated with EN 16798-7,
+    /// during the last timestep
+    pub en16798_flows: Vec<En16798Flows>,
+
+    /// The outdoor air entering each Space in the model with supply or
+    /// exhaust fans (or leaky ducts) through its envelope and through its
+    /// fans, during the last timestep
+    pub mechanical_ventilation: Vec<Option<Superposition>>,
+
+    /// The air (in m3/s) drawn from each Space in the model by the exhaust
+    /// fans of its neighbours, during the last timestep. It leaves the Space
+    /// like through an exhaust fan.
+    pub drawn_air: Vec<Float>,
+
+    /// The electric power (in W) of all the fans serving each Space in
+    /// the model (i.e., supply, exhaust, local exhaust, duct system and
+    /// whole-house fans), averaged over the last timestep
+    pub fan_power: Vec<Float>,
+
+    /// The temperature rise (in K) of the air supplied to each Space in
+    /// the model because of the heat of its supply fan, during the last
+    /// timestep
+    pub supply_temperature_rise: Vec<Float>,
+
+    /// The coil loads and supply conditions of each dedicated outdoor
+    /// air system, during the last timestep
+    pub doas_loads: Vec<CoilLoads>,
+
+    /// The energy used by each dedicated outdoor air system, accumulated
+    /// over all the timesteps simulated so far
+    pub doas_energy: Vec<DoasEnergy>,
+
+    /// The outdoor airflow (in m3/s) supplied to the Space of each
+    /// economizer (i.e., including the mechanical supply it takes over),
+    /// during the last timestep
+    pub economizer_flows: Vec<Float>,
+
+    /// The flow (in m3/s) moved by the whole-house fan of each dwelling,
+    /// during the last timestep. It is combined with the infiltration and
+    /// the other fans of the Space in the `mechanical_ventilation`.
+    pub whole_house_fan_flows: Vec<Float>,
+
+    /// The statistics of each Space in the model, accumulated
+    /// over all the timesteps simulated so far
+    pub statistics: Vec<SpaceStatistics>,
+
+    /// The indoor pressure (in Pa) of each Space in the model whose
+    /// airflows come from a pressure balance, during the last timestep
+    pub indoor_pressures: Vec<Option<Float>>,
+}
+
+impl AirFlowModelMemory {
+    /// Summarises the statistics of each Space (e.g., at the end of a
+    /// simulation)
+    pub fn summary(&self) -> Vec<SpaceSummary> {
+        self.statistics.iter().map(|s| s.summary()).collect()
+    }
+}
+
+/// Adds an airflow (in m3/s) at a certain temperature (in C) to the air
+/// entering a Space, mixing it with the air that is already entering it.
+fn add_airflow(
+    space: &Space,
+    state: &mut SimulationState,
+    flow: Float,
+    temperature: Float,
+) -> Result<(), String> {
+    if flow <= 0.0 {
+        return Ok(());
+    }
+    let current_flow = space.infiltration_volume(state)?;
+    let current_temperature = space.infiltration_temperature(state)?;
+    let total_flow = current_flow + flow;
+    let mixed_temperature = (current_flow * current_temperature + flow * temperature) / total_flow;
+    space.set_infiltration_volume(state, total_flow)?;
+    space.set_infiltration_temperature(state, mixed_temperature)?;
+    Ok(())
+}
 
 impl SimulationModel for AirFlowModel {
     type OutputType = Self;
-    type OptionType = ();
+    type OptionType = AirFlowModelOptions;
     type AllocType = AirFlowModelMemory;
 
-    fn allocate_memory(&self)->Result<Self::AllocType, String>{
-        Ok(())
+    fn allocate_memory(&self) -> Result<Self::AllocType, String> {
+        let n_spaces = self.monitored_spaces.len();
+        Ok(AirFlowModelMemory {
+            windows: (0..self.ventilated_spaces.len())
+                .map(|i| WindowState::new(self.random_seed.wrapping_add(i as u64)))
+                .collect(),
+            outdoor_running_mean: RunningMeanTemperature::default(),
+            neutral_pressure_levels: vec![None; self.stack_volumes.len()],
+            leak_flows: self
+                .leaky_spaces
+                .iter()
+                .map(|l| vec![0.0; l.leaks.len()])
+                .collect(),
+            duct_leakage: vec![DuctLeakageFlows::default(); self.leaky_ducts.len()],
+            mechanical_ventilation: vec![None; n_spaces],
+            drawn_air: vec![0.0; n_spaces],
+            fan_power: vec![0.0; n_spaces],
+            supply_temperature_rise: vec![0.0; n_spaces],
+            doas_loads: vec![CoilLoads::default(); self.doas_systems.len()],
+            doas_energy: vec![DoasEnergy::default(); self.doas_systems.len()],
+            economizer_flows: vec![0.0; self.economized_spaces.len()],
+            en16798_flows: vec![En16798Flows::default(); self.standard_zones.len()],
+            whole_house_fan_flows: vec![0.0; self.dwellings.len()],
+            statistics: self
+                .monitored_spaces
+                .iter()
+                .map(|m| {
+                    SpaceStatistics::new(
+                        m.space.name.clone(),
+                        m.options.volume,
+                        m.options.minimum_airflow,
+                    )
+                })
+                .collect(),
+            indoor_pressures: vec![None; n_spaces],
+        })
     }
-    
 
     /// Creates a new AirFlowModel from a SimpleModel.    
     fn new<M: Borrow<SimpleModel>>(
         _meta_options: &MetaOptions,
-        _options: (),
+        options: AirFlowModelOptions,
         model: M,
         state: &mut SimulationStateHeader,
-        _n: usize,
+        n: usize,
     ) -> Result<Self, String> {
+        let mut options = options;
+        // Otherwise, the minimum would go to a Space that does not exist
+        for system in options.ventilation_systems.iter() {
+            for zone in system.zones.iter() {
+                find_space(model.borrow(), &zone.space)?;
+            }
+        }
+        let ventilation_reports = apply_minimum_ventilation(&mut options)?;
         let mut infiltration_calcs = Vec::with_capacity(model.borrow().spaces.len());
+        let mut leakage_coefficients = Vec::new();
+        let mut ventilated_spaces = Vec::new();
+        let mut trickle_vented_spaces = Vec::new();
+        let leaky_spaces = distribute_leakage(model.borrow(), &options)?;
+        let mut standard_zones = Vec::with_capacity(options.en16798_zones.len());
+        for zone in options.en16798_zones.iter() {
+            zone.check()?;
+            let space_index = find_space_index(model.borrow(), &zone.space)?;
+            standard_zones.push(StandardZone {
+                space_index,
+                space: Arc::clone(&model.borrow().spaces[space_index]),
+                zone: zone.clone(),
+            });
+        }
+        let standard_buildings = group_standard_zones(model.borrow(), &options)?;
 
         for (i, space) in model.borrow().spaces.iter().enumerate() {
             // Should these initial values be different?
@@ -79,7 +647,14 @@ impl SimulationModel for AirFlowModel {
             space.set_infiltration_temperature_index(inf_temp_index)?;
 
             // Pre-process infiltration calculations
-            if let Ok(infiltration) = space.infiltration() {
+            if leaky_spaces.iter().any(|l| l.space_index == i)
+                || standard_zones.iter().any(|z| z.space.name == space.name)
+            {
+                // Calculated through the leaks of the envelope (or with
+                // EN 16798-7)... just clear whatever was added during the
+                // previous timestep
+                infiltration_calcs.push(constant_resolver(space, 0.0)?);
+            } else if let Ok(infiltration) = space.infiltration() {
                 let infiltration_fn = match infiltration {
                     Infiltration::Constant { flow } => constant_resolver(space, *flow)?,
                     Infiltration::Blast { flow } => blast_resolver(space, *flow)?,
@@ -88,19 +663,265 @@ impl SimulationModel for AirFlowModel {
                         design_flow_rate_resolver(space, *a, *b, *c, *d, *phi)?
                     }
                     Infiltration::EffectiveAirLeakageArea { area } => {
-                        effective_air_leakage_resolver(space, model.borrow(), *area)?
+                        let coefficients =
+                            resolve_leakage_coefficients(i, space, model.borrow(), &options)?;
+                        leakage_coefficients.push(coefficients);
+                        effective_air_leakage_resolver(space, *area, &coefficients)?
                     }
                 };
                 infiltration_calcs.push(infiltration_fn);
             } else {
-                // Does nothing
-                infiltration_calcs.push(Box::new(
-                    move |_current_weather: &CurrentWeather, _state: &mut SimulationState| {},
+                // No infiltration... just clear whatever was added
+                // during the previous timestep
+                infiltration_calcs.push(constant_resolver(space, 0.0)?);
+            }
+
+            // Natural ventilation
+            if let Some(space_options) = options.spaces.get(&space.name) {
+                space_options.check(&space.name)?;
+                if let Some(ventilation) = &space_options.natural_ventilation {
+                    let terrain = space
+                        .building()
+                        .ok()
+                        .and_then(|name| options.buildings.get(name))
+                        .map_or_else(Terrain::default, |b| b.terrain);
+                    ventilated_spaces.push(VentilatedSpace {
+                        space: Arc::clone(space),
+                        ventilation: ventilation.clone(),
+                        options: space_options.clone(),
+                        terrain,
+                    });
+                }
+                if !space_options.trickle_vents.is_empty() {
+                    trickle_vented_spaces.push(TrickleVentedSpace {
+                        space: Arc::clone(space),
+                        options: space_options.clone(),
+                    });
+                }
+            }
+        }
+
+        // Statistics
+        let monitored_spaces = model
+            .borrow()
+            .spaces
+            .iter()
+            .map(|space| MonitoredSpace {
+                space: Arc::clone(space),
+                options: options.spaces.get(&space.name).cloned().unwrap_or_default(),
+                window: ventilated_spaces
+                    .iter()
+                    .position(|v| v.space.name == space.name),
+            })
+            .collect();
+
+        // Stack ventilation
+        let mut stack_volumes = Vec::with_capacity(options.stack_ventilation.len());
+        for ventilation in options.stack_ventilation.iter() {
+            let mut opening_spaces = Vec::with_capacity(ventilation.openings.len());
+            let mut spaces: Vec<Arc<Space>> = Vec::new();
+            for opening in ventilation.openings.iter() {
+                let space = find_space(model.borrow(), &opening.space)?;
+                if !spaces.iter().any(|s| Arc::ptr_eq(s, &space)) {
+                    spaces.push(Arc::clone(&space));
+                }
+                opening_spaces.push(space);
+            }
+            stack_volumes.push(StackVolume {
+                opening_spaces,
+                spaces,
+                ventilation: ventilation.clone(),
+            });
+        }
+
+        // Internal openings
+        let mut internal_links = Vec::with_capacity(options.internal_openings.len());
+        for opening in options.internal_openings.iter() {
+            opening.check()?;
+            internal_links.push(InternalLink {
+                space_a: find_space(model.borrow(), &opening.space_a)?,
+                space_b: find_space(model.borrow(), &opening.space_b)?,
+                opening: opening.clone(),
+            });
+        }
+
+        // Duct leakage
+        let location_space = |location: &DuctLocation| -> Result<Option<Arc<Space>>, String> {
+            match location {
+                DuctLocation::Space(name) => Ok(Some(find_space(model.borrow(), name)?)),
+                _ => Ok(None),
+            }
+        };
+        let mut leaky_ducts = Vec::with_capacity(options.duct_systems.len());
+        for system in options.duct_systems.iter() {
+            system.check()?;
+            leaky_ducts.push(LeakyDucts {
+                space: find_space(model.borrow(), &system.space)?,
+                supply_space: location_space(&system.supply_location)?,
+                return_space: location_space(&system.return_location)?,
+                system: system.clone(),
+            });
+        }
+
+        // Dedicated outdoor air systems
+        let mut doas_systems = Vec::with_capacity(options.doas.len());
+        for doas in options.doas.iter() {
+            doas.check()?;
+            let mut spaces = Vec::with_capacity(doas.outlets.len());
+            for outlet in doas.outlets.iter() {
+                spaces.push(find_space(model.borrow(), &outlet.space)?);
+            }
+            doas_systems.push(DoasSystem {
+                spaces,
+                doas: doas.clone(),
+            });
+        }
+
+        // Dwellings
+        let mut dwellings = Vec::with_capacity(options.dwellings.len());
+        for dwelling in options.dwellings.iter() {
+            dwelling.check()?;
+            let space_index = find_space_index(model.borrow(), &dwelling.space)?;
+            if dwellings
+                .iter()
+                .any(|d: &VentilatedDwelling| d.space_index == space_index)
+            {
+                return Err(format!(
+                    "Space '{}' represents more than one dwelling",
+                    dwelling.space
+                ));
+            }
+            let space = Arc::clone(&model.borrow().spaces[space_index]);
+            let mut dwelling = dwelling.clone();
+            if dwelling.leakage.is_none() {
+                dwelling.leakage = match space.infiltration() {
+                    Ok(Infiltration::EffectiveAirLeakageArea { area }) => {
+                        Some(DwellingLeakage::EffectiveLeakageArea(*area))
+                    }
+                    _ => leaky_spaces
+                        .iter()
+                        .find(|l| l.space_index == space_index)
+                        .map(|l| {
+                            DwellingLeakage::BlowerDoor(
+                                l.leaks
+                                    .iter()
+                                    .map(|leak| {
+                                        let p = &leak.power_law;
+                                        p.coefficient * (50. as Float).powf(p.exponent)
+                                    })
+                                    .sum(),
+                            )
+                        }),
+                };
+            }
+            dwellings.push(VentilatedDwelling {
+                space_index,
+                dwelling,
+            });
+        }
+
+        // The Spaces from which exhaust fans draw their make-up air
+        let mut make_up_sources = vec![false; model.borrow().spaces.len()];
+        for space_options in options.spaces.values() {
+            for fan in space_options.exhaust_fans.iter() {
+                if let MakeUpAir::Space(name) = &fan.make_up_air {
+                    make_up_sources[find_space_index(model.borrow(), name)?] = true;
+                }
+            }
+        }
+
+        // Supply and exhaust fans, whole-house fans, DOAS outlets and the imbalance of leaky ducts
+        let mut mechanically_ventilated_spaces = Vec::new();
+        for (i, space) in model.borrow().spaces.iter().enumerate() {
+            let space_options = options.spaces.get(&space.name).cloned().unwrap_or_default();
+            let duct_systems: Vec<usize> = leaky_ducts
+                .iter()
+                .enumerate()
+                .filter(|(_, ducts)| Arc::ptr_eq(&ducts.space, space))
+                .map(|(j, _)| j)
+                .collect();
+            let mut doas_outlets = Vec::new();
+            for (j, system) in doas_systems.iter().enumerate() {
+                for (k, outlet_space) in system.spaces.iter().enumerate() {
+                    if Arc::ptr_eq(outlet_space, space) {
+                        doas_outlets.push((j, k));
+                    }
+                }
+            }
+            let dwelling = dwellings.iter().position(|d| d.space_index == i);
+            if space_options.mechanical_ventilation.is_none()
+                && space_options.exhaust_fans.is_empty()
+                && dwelling.is_none()
+                && !make_up_sources[i]
+                && duct_systems.is_empty()
+                && doas_outlets.is_empty()
+            {
+                continue;
+            }
+            // The DOAS would not condition the air of the mechanical supply
+            let has_supply = space_options
+                .mechanical_ventilation
+                .as_ref()
+                .map_or(false, |v| v.flows(1.0).0 > 0.0);
+            if has_supply && !doas_outlets.is_empty() {
+                return Err(format!(
+                    "Space '{}' is served by a DOAS, so its mechanical ventilation cannot also supply air",
+                    space.name
                 ));
             }
+            let mut make_up_spaces = Vec::with_capacity(space_options.exhaust_fans.len());
+            for fan in space_options.exhaust_fans.iter() {
+                make_up_spaces.push(match &fan.make_up_air {
+                    MakeUpAir::Infiltration => None,
+                    MakeUpAir::Space(name) => {
+                        let j = find_space_index(model.borrow(), name)?;
+                        Some((j, Arc::clone(&model.borrow().spaces[j])))
+                    }
+                });
+            }
+            mechanically_ventilated_spaces.push(MechanicallyVentilatedSpace {
+                space_index: i,
+                space: Arc::clone(space),
+                options: space_options,
+                make_up_spaces,
+                duct_systems,
+                doas_outlets,
+                dwelling,
+            });
         }
 
-        Ok(AirFlowModel { infiltration_calcs })
+        // Economizers
+        let mut economized_spaces = Vec::with_capacity(options.economizers.len());
+        for economizer in options.economizers.iter() {
+            economizer.check()?;
+            let space_index = find_space_index(model.borrow(), &economizer.space)?;
+            economized_spaces.push(EconomizedSpace {
+                space_index,
+                space: Arc::clone(&model.borrow().spaces[space_index]),
+                economizer: economizer.clone(),
+            });
+        }
+
+        Ok(AirFlowModel {
+            infiltration_calcs,
+            stack_volumes,
+            internal_links,
+            ventilated_spaces,
+            trickle_vented_spaces,
+            leaky_spaces,
+            standard_zones,
+            standard_buildings,
+            mechanically_ventilated_spaces,
+            leaky_ducts,
+            doas_systems,
+            economized_spaces,
+            dwellings,
+            monitored_spaces,
+            dt: 3600. / n as Float,
+            random_seed: options.random_seed,
+            leakage_coefficients,
+            ventilation_reports,
+        })
     }
 
     /// Advances one main_timestep through time. That is,
@@ -112,14 +933,406 @@ impl SimulationModel for AirFlowModel {
         weather: &W,
         _model: M,
         state: &mut SimulationState,
-        _alloc: &mut AirFlowModelMemory,
+        alloc: &mut AirFlowModelMemory,
     ) -> Result<(), String> {
+        // Fans serving the same Space add up
+        for ((power, rise), drawn) in alloc
+            .fan_power
+            .iter_mut()
+            .zip(alloc.supply_temperature_rise.iter_mut())
+            .zip(alloc.drawn_air.iter_mut())
+        {
+            *power = 0.0;
+            *rise = 0.0;
+            *drawn = 0.0;
+        }
+
         // Process infiltration
         let current_weather = weather.get_weather_data(date);
         for func in self.infiltration_calcs.iter() {
             func(&current_weather, state)
         }
 
+        let outdoor_temperature = current_weather
+            .dry_bulb_temperature
+            .ok_or_else(|| "Weather does not have dry bulb temperature".to_string())?;
+
+        // Process infiltration through the leaks of the envelope
+        let wind_speed = current_weather.wind_speed.unwrap_or(0.0);
+        let wind_direction = current_weather.wind_direction.unwrap_or(0.0);
+        for (leaky, flows) in self.leaky_spaces.iter().zip(alloc.leak_flows.iter_mut()) {
+            let res = solve_leaks(
+                &leaky.leaks,
+                leaky.local_wind_speed(wind_speed),
+                wind_direction,
+                leaky.space.dry_bulb_temperature(state)?,
+                outdoor_temperature,
+            );
+            add_airflow(&leaky.space, state, res.infiltration(), outdoor_temperature)?;
+            alloc.indoor_pressures[leaky.space_index] = Some(res.indoor_pressure);
+            *flows = res.flows;
+        }
+
+        // Process the Spaces calculated with EN 16798-7, balancing the
+        // zones of each Building together
+        for standard_building in self.standard_buildings.iter() {
+            let mut zones = Vec::with_capacity(standard_building.zones.len());
+            for j in standard_building.zones.iter() {
+                let standard = &self.standard_zones[*j];
+                zones.push((&standard.zone, standard.space.dry_bulb_temperature(state)?));
+            }
+            let results = solve_building(
+                &zones,
+                date.hour,
+                wind_speed,
+                outdoor_temperature,
+                &standard_building.building,
+            );
+            for (j, res) in standard_building.zones.iter().zip(results.into_iter()) {
+                let standard = &self.standard_zones[*j];
+                add_airflow(
+                    &standard.space,
+                    state,
+                    res.infiltration + res.windows + res.supply,
+                    outdoor_temperature,
+                )?;
+                alloc.indoor_pressures[standard.space_index] = Some(res.internal_pressure);
+                alloc.en16798_flows[*j] = res;
+            }
+        }
+
+        // Process duct leakage. The imbalance between the supply and return
+        // leaks acts on the served Space like an exhaust (or supply) fan.
+        for (ducts, leakage) in self.leaky_ducts.iter().zip(alloc.duct_leakage.iter_mut()) {
+            let system = &ducts.system;
+            let space_temperature = ducts.space.dry_bulb_temperature(state)?;
+            let return_surroundings_temperature =
+                match (&system.return_location, &ducts.return_space) {
+                    (DuctLocation::Outdoors, _) => outdoor_temperature,
+                    (_, Some(space)) => space.dry_bulb_temperature(state)?,
+                    _ => space_temperature,
+                };
+            *leakage = system.leakage_flows(
+                system.running_fraction(date.hour),
+                space_temperature,
+                return_surroundings_temperature,
+            );
+        }
+
+        // Condition the outdoor air of dedicated outdoor air systems. It
+        // reaches the Spaces (with a balanced exhaust) with their mechanical
+        // ventilation.
+        if !self.doas_systems.is_empty() {
+            let dew_point = current_weather
+                .dew_point_temperature
+                .ok_or_else(|| "Weather does not have dew point temperature".to_string())?;
+            let outdoor_humidity_ratio = humidity_ratio(dew_point);
+            for ((system, loads), energy) in self
+                .doas_systems
+                .iter()
+                .zip(alloc.doas_loads.iter_mut())
+                .zip(alloc.doas_energy.iter_mut())
+            {
+                let fraction = system.doas.running_fraction(date.hour);
+                *loads = condition(
+                    system.doas.flow(fraction),
+                    outdoor_temperature,
+                    outdoor_humidity_ratio,
+                    &system.doas.setpoint,
+                );
+                energy.add(loads, system.doas.power(fraction), self.dt);
+            }
+        }
+
+        // Exhaust fans whose make-up air comes from a neighbour draw it from there
+        for ventilated in self.mechanically_ventilated_spaces.iter() {
+            for (j, _, flow) in ventilated.make_up_draws(date.hour) {
+                alloc.drawn_air[j] += flow;
+            }
+        }
+
+        // Combine infiltration with supply and exhaust fans (including the
+        // air drawn by the exhaust fans of neighbours)
+        for ventilated in self.mechanically_ventilated_spaces.iter() {
+            let options = &ventilated.options;
+            let occupied = options.is_occupied(date.hour);
+            let (mut supply, mut exhaust) = options
+                .mechanical_ventilation
+                .as_ref()
+                .map_or((0.0, 0.0), |v| v.flows(v.running_fraction(date.hour)));
+            let mut supply_temperature = outdoor_temperature;
+            let mut temperature_rise = 0.0;
+            let mut ventilation_power = 0.0;
+            if let Some(ventilation) = &options.mechanical_ventilation {
+                supply_temperature = ventilation
+                    .supply_temperature
+                    .unwrap_or(outdoor_temperature);
+                if let Some(fan) = &ventilation.supply_fan {
+                    ventilation_power += fan.power(supply);
+                    if supply > 0.0 {
+                        temperature_rise = fan.temperature_rise(supply_temperature);
+                    }
+                }
+                if let Some(fan) = &ventilation.exhaust_fan {
+                    ventilation_power += fan.power(exhaust);
+                }
+            }
+            let mut power = 0.0;
+            for (fan, make_up_space) in options
+                .exhaust_fans
+                .iter()
+                .zip(ventilated.make_up_spaces.iter())
+            {
+                let fraction = fan.running_fraction(date.hour, occupied);
+                power += fan.power(fraction);
+                if make_up_space.is_none() {
+                    exhaust += fan.flow * fraction;
+                }
+            }
+            exhaust += alloc.drawn_air[ventilated.space_index];
+
+            let mut supply_heat = supply * (supply_temperature + temperature_rise);
+            for (j, k) in ventilated.doas_outlets.iter() {
+                let doas = &self.doas_systems[*j].doas;
+                let flow = doas.outlets[*k].flow_at(doas.running_fraction(date.hour));
+                if flow > 0.0 {
+                    let supply_temperature = alloc.doas_loads[*j].supply_temperature;
+                    temperature_rise = doas.temperature_rise(supply_temperature);
+                    supply += flow;
+                    exhaust += flow;
+                    supply_heat += flow * (supply_temperature + temperature_rise);
+                    if let Some(fan) = &doas.fan {
+                        ventilation_power += fan.power(flow);
+                    }
+                }
+            }
+
+            // Whole-house fans deliver whatever the infiltration does not
+            let infiltration = ventilated.space.infiltration_volume(state)?;
+            if let Some(j) = ventilated.dwelling {
+                let dwelling = &self.dwellings[j].dwelling;
+                let flow = dwelling.fan_flow(infiltration);
+                let (fan_supply, fan_exhaust) = dwelling.fan.flows(flow);
+                if fan_supply > 0.0 {
+                    temperature_rise = dwelling.fan.temperature_rise(outdoor_temperature);
+                    supply += fan_supply;
+                    supply_heat += fan_supply * (outdoor_temperature + temperature_rise);
+                }
+                exhaust += fan_exhaust;
+                ventilation_power += dwelling.fan.power(flow);
+                alloc.whole_house_fan_flows[j] = flow;
+            }
+
+            for j in ventilated.duct_systems.iter() {
+                let system = &self.leaky_ducts[*j].system;
+                ventilation_power += system.power(system.running_fraction(date.hour));
+                let imbalance = alloc.duct_leakage[*j].imbalance;
+                if imbalance > 0.0 {
+                    exhaust += imbalance;
+                } else {
+                    // The air drawn into leaky return ducts reaches the Space at the
+                    // temperature delivered by the system
+                    supply -= imbalance;
+                    supply_heat -= imbalance * system.delivered_temperature();
+                }
+            }
+
+            let flows = superpose(infiltration, supply, exhaust);
+            ventilated
+                .space
+                .set_infiltration_volume(state, flows.envelope)?;
+            ventilated
+                .space
+                .set_infiltration_temperature(state, outdoor_temperature)?;
+            if flows.fans > 0.0 {
+                add_airflow(
+                    &ventilated.space,
+                    state,
+                    flows.fans,
+                    supply_heat / flows.fans,
+                )?;
+            }
+            let i = ventilated.space_index;
+            alloc.mechanical_ventilation[i] = Some(flows);
+            alloc.fan_power[i] += power + ventilation_power;
+            if supply > 0.0 {
+                alloc.supply_temperature_rise[i] = temperature_rise;
+            }
+        }
+
+        // Supply air lost to an unconditioned Space ends up there
+        for (ducts, leakage) in self.leaky_ducts.iter().zip(alloc.duct_leakage.iter()) {
+            if let Some(space) = &ducts.supply_space {
+                add_airflow(
+                    space,
+                    state,
+                    leakage.supply_leak,
+                    ducts.system.delivered_temperature(),
+                )?;
+            }
+        }
+
+        // Process economizers, which use the Space temperature as
+        // the return air temperature. The economizer raises the outdoor
+        // air of the fans serving the Space rather than adding to it.
+        for (economized, flow) in self
+            .economized_spaces
+            .iter()
+            .zip(alloc.economizer_flows.iter_mut())
+        {
+            let economizer = &economized.economizer;
+            let outdoor_humidity_ratio = if economizer.needs_humidity() {
+                let dew_point = current_weather
+                    .dew_point_temperature
+                    .ok_or_else(|| "Weather does not have dew point temperature".to_string())?;
+                humidity_ratio(dew_point)
+            } else {
+                0.0
+            };
+            let conditions = EconomizerConditions {
+                outdoor_temperature,
+                outdoor_humidity_ratio,
+                return_temperature: economized.space.dry_bulb_temperature(state)?,
+                return_humidity_ratio: economizer.return_humidity_ratio(),
+            };
+            let supply = alloc.mechanical_ventilation[economized.space_index]
+                .map_or(0.0, |flows| flows.fans);
+            let economizer_flow = economizer.outdoor_airflow(&conditions);
+            add_airflow(
+                &economized.space,
+                state,
+                economizer_flow - supply,
+                outdoor_temperature,
+            )?;
+            *flow = economizer_flow.max(supply);
+        }
+
+        // Process natural ventilation
+        alloc.outdoor_running_mean.update(
+            date.month as usize,
+            date.day as usize,
+            outdoor_temperature,
+        );
+        let outdoor_running_mean = alloc
+            .outdoor_running_mean
+            .value()
+            .unwrap_or(outdoor_temperature);
+        for (ventilated, window) in self.ventilated_spaces.iter().zip(alloc.windows.iter_mut()) {
+            let space = &ventilated.space;
+            let space_temperature = space.dry_bulb_temperature(state)?;
+            let conditions = ControlConditions {
+                space_temperature,
+                weather: &current_weather,
+                outdoor_running_mean,
+                occupied: ventilated.options.is_occupied(date.hour),
+                dt: self.dt,
+            };
+            let opening_fraction = ventilated
+                .ventilation
+                .control
+                .opening_fraction(window, &conditions);
+            let flow = ventilated.ventilation.airflow(
+                &current_weather,
+                &ventilated.terrain,
+                space_temperature,
+                opening_fraction,
+            );
+            add_airflow(space, state, flow, outdoor_temperature)?;
+        }
+
+        // Process stack ventilation
+        for (volume, npl) in self
+            .stack_volumes
+            .iter()
+            .zip(alloc.neutral_pressure_levels.iter_mut())
+        {
+            let mut indoor_temperature = 0.0;
+            for space in volume.spaces.iter() {
+                indoor_temperature += space.dry_bulb_temperature(state)?;
+            }
+            indoor_temperature /= volume.spaces.len() as Float;
+
+            let res = solve_stack(
+                &volume.ventilation.openings,
+                indoor_temperature,
+                outdoor_temperature,
+            );
+            for (space, flow) in volume.opening_spaces.iter().zip(res.flows.iter()) {
+                add_airflow(space, state, *flow, outdoor_temperature)?;
+            }
+            *npl = res.neutral_pressure_level;
+        }
+
+        // Process trickle vents. Humidity controlled ones follow the
+        // relative humidity of their Space.
+        for vented in self.trickle_vented_spaces.iter() {
+            let vents = &vented.options.trickle_vents;
+            let relative_humidity = if vents
+                .iter()
+                .any(|v| matches!(v.control, TrickleVentControl::Humidity { .. }))
+            {
+                vented.space.relative_humidity(state)?
+            } else {
+                0.0
+            };
+            let conditions = TrickleVentConditions {
+                wind_speed: current_weather.wind_speed.unwrap_or(0.0),
+                indoor_temperature: vented.space.dry_bulb_temperature(state)?,
+                outdoor_temperature,
+                relative_humidity,
+            };
+            let flow = trickle_vents_airflow(vents, &conditions);
+            add_airflow(&vented.space, state, flow, outdoor_temperature)?;
+        }
+
+        // Accumulate statistics, which only account for outdoor air. Spaces
+        // without a temperature (e.g., not simulated thermally) are skipped.
+        for (monitored, stats) in self
+            .monitored_spaces
+            .iter()
+            .zip(alloc.statistics.iter_mut())
+        {
+            let space = &monitored.space;
+            let space_temperature = match space.dry_bulb_temperature(state) {
+                Ok(temperature) => temperature,
+                Err(_) => continue,
+            };
+            stats.record(&TimestepConditions {
+                airflow: space.infiltration_volume(state)?,
+                airflow_temperature: space.infiltration_temperature(state)?,
+                space_temperature,
+                occupants: monitored.options.occupants_at(date.hour),
+                windows_open: monitored.window.map_or(false, |i| alloc.windows[i].open),
+                dt: self.dt,
+            });
+        }
+
+        // Process flows between Spaces, which are not outdoor air. Each
+        // Space receives air at the temperature of its neighbour.
+        for ventilated in self.mechanically_ventilated_spaces.iter() {
+            for (_, neighbour, flow) in ventilated.make_up_draws(date.hour) {
+                let neighbour_temperature = neighbour.dry_bulb_temperature(state)?;
+                add_airflow(&ventilated.space, state, flow, neighbour_temperature)?;
+            }
+        }
+        for link in self.internal_links.iter() {
+            let fraction = link.opening.opening_fraction(date.hour);
+            if fraction <= 0.0 {
+                continue;
+            }
+            let temperature_a = link.space_a.dry_bulb_temperature(state)?;
+            let temperature_b = link.space_b.dry_bulb_temperature(state)?;
+            let flow = bidirectional_flow(
+                link.opening.width * fraction,
+                link.opening.height,
+                link.opening.discharge_coefficient,
+                temperature_a,
+                temperature_b,
+            );
+            add_airflow(&link.space_a, state, flow, temperature_b)?;
+            add_airflow(&link.space_b, state, flow, temperature_a)?;
+        }
+
         Ok(())
     }
 }
@@ -127,6 +1340,18 @@ impl SimulationModel for AirFlowModel {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::doas::{DoasOutlet, DoasSetpoint};
+    use crate::ducts::DuctLeakage;
+    use crate::economizer::EconomizerControl;
+    use crate::elements::SelfRegulatingVent;
+    use crate::mechanical::{ExhaustFan, Fan, FanOperation, FanPower, MechanicalVentilation};
+    use crate::trickle_vents::TrickleVent;
+    use crate::ventilation::{wind_and_stack_open_area, Opening, VentilationMethod};
+    use crate::ventilation_rate::{
+        AirDistribution, OccupancyCategory, VentilationSystem, VentilationSystemKind,
+        VentilationZone,
+    };
+    use crate::window_control::{TemperatureControl, WindowControl};
     use schedule::ScheduleConstant;
     use simple_model::Space;
     use weather::SyntheticWeather;
@@ -151,11 +1376,18 @@ mod tests {
         space.set_dry_bulb_temperature_index(i).unwrap();
         let space = simple_model.add_space(space);
 
-        let model = AirFlowModel::new(&META_OPTIONS, (), &simple_model, &mut state_header, 1)
-            .expect("Could not build AirFlow model");
+        let model = AirFlowModel::new(
+            &META_OPTIONS,
+            AirFlowModelOptions::default(),
+            &simple_model,
+            &mut state_header,
+            1,
+        )
+        .expect("Could not build AirFlow model");
         let mut state = state_header
             .take_values()
             .expect("Could not take values form SimualationStateHeader");
+        let mut memory = model.allocate_memory().unwrap();
 
         /*
         This test is essentially the same as in test_design_blast_flow_rate().
@@ -182,7 +1414,7 @@ mod tests {
         assert!(inf < 1e-9);
 
         model
-            .march(date, &weather, &simple_model, &mut state, &mut ())
+            .march(date, &weather, &simple_model, &mut state, &mut memory)
             .unwrap();
 
         // Check values.
@@ -194,11 +1426,632 @@ mod tests {
         weather.dry_bulb_temperature = Box::new(ScheduleConstant::new(space_temp - 40.));
         weather.wind_speed = Box::new(ScheduleConstant::new(4.47));
         model
-            .march(date, &weather, &simple_model, &mut state, &mut ())
+            .march(date, &weather, &simple_model, &mut state, &mut memory)
             .unwrap();
 
         // Check values.
         let inf = space.infiltration_volume(&state).unwrap();
         assert!((1. - inf).abs() < 0.02);
+
+        // Statistics of both timesteps
+        let summary = memory.summary();
+        assert_eq!(summary.len(), 1);
+        let (min, max, mean) = summary[0].airflow;
+        assert!((1. - min).abs() < 0.02);
+        assert!((1.34 - max).abs() < 0.02);
+        assert!(((min + max) / 2. - mean).abs() < 1e-9);
+        assert!(summary[0].air_changes.is_none());
+        assert!(summary[0].infiltration_energy > 0.0);
+    }
+
+    #[test]
+    fn test_statistics_without_temperature() {
+        // A Space that is not simulated thermally has no temperature
+        let mut simple_model = SimpleModel::default();
+        let mut state_header = SimulationStateHeader::new();
+        let mut space = Space::new("some space".to_string());
+        space.set_infiltration(Infiltration::Constant { flow: 0.1 });
+        let space = simple_model.add_space(space);
+
+        let model = AirFlowModel::new(
+            &META_OPTIONS,
+            AirFlowModelOptions::default(),
+            &simple_model,
+            &mut state_header,
+            1,
+        )
+        .expect("Could not build AirFlow model");
+        let mut state = state_header
+            .take_values()
+            .expect("Could not take values form SimualationStateHeader");
+        let mut memory = model.allocate_memory().unwrap();
+
+        let mut weather = SyntheticWeather::default();
+        weather.dry_bulb_temperature = Box::new(ScheduleConstant::new(5.));
+        weather.wind_speed = Box::new(ScheduleConstant::new(0.));
+        let date = Date {
+            month: 1,
+            day: 1,
+            hour: 10.,
+        };
+        model
+            .march(date, &weather, &simple_model, &mut state, &mut memory)
+            .unwrap();
+        let inf = space.infiltration_volume(&state).unwrap();
+        assert!((inf - 0.1).abs() < 1e-9);
+        // ... so it is not monitored
+        assert!(memory.statistics[0].time.abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_natural_ventilation() {
+        let mut simple_model = SimpleModel::default();
+        let mut state_header = SimulationStateHeader::new();
+
+        let space = Space::new("some space".to_string());
+        let i = state_header
+            .push(SimulationStateElement::SpaceDryBulbTemperature(0), 28.)
+            .unwrap();
+        space.set_dry_bulb_temperature_index(i).unwrap();
+        let space = simple_model.add_space(space);
+
+        let opening = Opening {
+            area: 1.,
+            azimuth: 0.,
+            height_from_npl: 0.5,
+            wind_coefficient: Some(0.5),
+            discharge_coefficient: Some(0.6),
+        };
+        let mut options = AirFlowModelOptions::default();
+        options.spaces.insert(
+            "some space".to_string(),
+            SpaceAirFlowOptions {
+                natural_ventilation: Some(NaturalVentilation {
+                    method: VentilationMethod::WindAndStackOpenArea(vec![opening]),
+                    control: WindowControl::Temperature(TemperatureControl::default()),
+                    height: 10.,
+                }),
+                ..SpaceAirFlowOptions::default()
+            },
+        );
+
+        let model = AirFlowModel::new(&META_OPTIONS, options, &simple_model, &mut state_header, 4)
+            .expect("Could not build AirFlow model");
+        let mut state = state_header
+            .take_values()
+            .expect("Could not take values form SimualationStateHeader");
+        let mut memory = model.allocate_memory().unwrap();
+
+        let date = Date {
+            month: 7,
+            day: 1,
+            hour: 14.,
+        };
+
+        // Warmer outside... closed
+        let mut weather = SyntheticWeather::default();
+        weather.dry_bulb_temperature = Box::new(ScheduleConstant::new(30.));
+        weather.wind_speed = Box::new(ScheduleConstant::new(2.));
+        model
+            .march(date, &weather, &simple_model, &mut state, &mut memory)
+            .unwrap();
+        assert!(!memory.windows[0].open);
+        assert!(space.infiltration_volume(&state).unwrap() < 1e-9);
+
+        // Cooler outside... open
+        let mut weather = SyntheticWeather::default();
+        weather.dry_bulb_temperature = Box::new(ScheduleConstant::new(18.));
+        weather.wind_speed = Box::new(ScheduleConstant::new(2.));
+        model
+            .march(date, &weather, &simple_model, &mut state, &mut memory)
+            .unwrap();
+        assert!(memory.windows[0].open);
+        let current_weather = weather.get_weather_data(date);
+        let wind_speed = Terrain::default().local_wind_speed(2., 10.);
+        let exp = wind_and_stack_open_area(&current_weather, wind_speed, 28., &opening, 1.);
+        let found = space.infiltration_volume(&state).unwrap();
+        assert!((exp - found).abs() < 1e-9);
+        let t = space.infiltration_temperature(&state).unwrap();
+        assert!((t - 18.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_internal_openings() {
+        let mut simple_model = SimpleModel::default();
+        let mut state_header = SimulationStateHeader::new();
+
+        let mut spaces = Vec::new();
+        for (i, (name, temperature)) in [("warm", 24.), ("cool", 18.)].iter().enumerate() {
+            let space = Space::new(name.to_string());
+            let index = state_header
+                .push(
+                    SimulationStateElement::SpaceDryBulbTemperature(i),
+                    *temperature,
+                )
+                .unwrap();
+            space.set_dry_bulb_temperature_index(index).unwrap();
+            spaces.push(simple_model.add_space(space));
+        }
+
+        let mut options = AirFlowModelOptions::default();
+        options.internal_openings.push(InternalOpening {
+            space_a: "warm".to_string(),
+            space_b: "cool".to_string(),
+            width: 0.8,
+            height: 2.0,
+            discharge_coefficient: 0.6,
+            schedule: None,
+        });
+
+        let model = AirFlowModel::new(&META_OPTIONS, options, &simple_model, &mut state_header, 1)
+            .expect("Could not build AirFlow model");
+        let mut state = state_header
+            .take_values()
+            .expect("Could not take values form SimualationStateHeader");
+        let mut memory = model.allocate_memory().unwrap();
+
+        let mut weather = SyntheticWeather::default();
+        weather.dry_bulb_temperature = Box::new(ScheduleConstant::new(10.));
+        weather.wind_speed = Box::new(ScheduleConstant::new(0.));
+        let date = Date {
+            month: 1,
+            day: 1,
+            hour: 10.,
+        };
+        model
+            .march(date, &weather, &simple_model, &mut state, &mut memory)
+            .unwrap();
+
+        let exp = bidirectional_flow(0.8, 2.0, 0.6, 24., 18.);
+        let warm = &spaces[0];
+        let cool = &spaces[1];
+        assert!((warm.infiltration_volume(&state).unwrap() - exp).abs() < 1e-9);
+        assert!((cool.infiltration_volume(&state).unwrap() - exp).abs() < 1e-9);
+        // Air comes from the neighbour, not from outside
+        assert!((warm.infiltration_temperature(&state).unwrap() - 18.).abs() < 1e-9);
+        assert!((cool.infiltration_temperature(&state).unwrap() - 24.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_trickle_vents() {
+        let mut simple_model = SimpleModel::default();
+        let mut state_header = SimulationStateHeader::new();
+
+        let mut space = Space::new("some space".to_string());
+        space.set_infiltration(Infiltration::Constant { flow: 0.01 });
+        let i = state_header
+            .push(SimulationStateElement::SpaceDryBulbTemperature(0), 20.)
+            .unwrap();
+        space.set_dry_bulb_temperature_index(i).unwrap();
+        let space = simple_model.add_space(space);
+
+        let vent = TrickleVent {
+            control: TrickleVentControl::Pressure(SelfRegulatingVent {
+                design_flow: 0.008,
+                regulation_pressure: 10.,
+            }),
+            pressure_coefficient: 0.5,
+            height_from_npl: -1.,
+        };
+        let mut options = AirFlowModelOptions::default();
+        options.spaces.insert(
+            "some space".to_string(),
+            SpaceAirFlowOptions {
+                trickle_vents: vec![vent; 2],
+                ..SpaceAirFlowOptions::default()
+            },
+        );
+
+        let model = AirFlowModel::new(&META_OPTIONS, options, &simple_model, &mut state_header, 1)
+            .expect("Could not build AirFlow model");
+        let mut state = state_header
+            .take_values()
+            .expect("Could not take values form SimualationStateHeader");
+        let mut memory = model.allocate_memory().unwrap();
+
+        // Windy enough for the vents to regulate their flow
+        let mut weather = SyntheticWeather::default();
+        weather.dry_bulb_temperature = Box::new(ScheduleConstant::new(0.));
+        weather.wind_speed = Box::new(ScheduleConstant::new(10.));
+        let date = Date {
+            month: 1,
+            day: 1,
+            hour: 10.,
+        };
+        model
+            .march(date, &weather, &simple_model, &mut state, &mut memory)
+            .unwrap();
+        let found = space.infiltration_volume(&state).unwrap();
+        assert!((found - 0.026).abs() < 1e-6);
+        assert!(space.infiltration_temperature(&state).unwrap().abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_duct_leakage() {
+        let mut simple_model = SimpleModel::default();
+        let mut state_header = SimulationStateHeader::new();
+
+        let mut spaces = Vec::new();
+        for (i, (name, temperature)) in [("house", 20.), ("attic", 0.)].iter().enumerate() {
+            let space = Space::new(name.to_string());
+            let index = state_header
+                .push(
+                    SimulationStateElement::SpaceDryBulbTemperature(i),
+                    *temperature,
+                )
+                .unwrap();
+            space.set_dry_bulb_temperature_index(index).unwrap();
+            spaces.push(simple_model.add_space(space));
+        }
+
+        let mut options = AirFlowModelOptions::default();
+        options.duct_systems.push(DuctSystem {
+            space: "house".to_string(),
+            supply_flow: 0.3,
+            supply_temperature: 40.,
+            fan: None,
+            supply_leakage: DuctLeakage::Fraction(0.1),
+            supply_location: DuctLocation::Space("attic".to_string()),
+            return_leakage: DuctLeakage::Fraction(0.05),
+            return_location: DuctLocation::Space("attic".to_string()),
+            schedule: None,
+        });
+
+        let model = AirFlowModel::new(&META_OPTIONS, options, &simple_model, &mut state_header, 1)
+            .expect("Could not build AirFlow model");
+        let mut state = state_header
+            .take_values()
+            .expect("Could not take values form SimualationStateHeader");
+        let mut memory = model.allocate_memory().unwrap();
+
+        let mut weather = SyntheticWeather::default();
+        weather.dry_bulb_temperature = Box::new(ScheduleConstant::new(-5.));
+        weather.wind_speed = Box::new(ScheduleConstant::new(0.));
+        let date = Date {
+            month: 1,
+            day: 1,
+            hour: 10.,
+        };
+        model
+            .march(date, &weather, &simple_model, &mut state, &mut memory)
+            .unwrap();
+
+        // The house is depressurised by the imbalance
+        let house = &spaces[0];
+        assert!((house.infiltration_volume(&state).unwrap() - 0.015).abs() < 1e-9);
+        assert!((house.infiltration_temperature(&state).unwrap() + 5.).abs() < 1e-9);
+        // ... and the lost supply air ends up in the attic
+        let attic = &spaces[1];
+        assert!((attic.infiltration_volume(&state).unwrap() - 0.03).abs() < 1e-9);
+        assert!((attic.infiltration_temperature(&state).unwrap() - 40.).abs() < 1e-9);
+        assert!(memory.duct_leakage[0].energy_loss > 0.);
+        // The memory is indexed by the Spaces of the model
+        let flows = memory.mechanical_ventilation[0].unwrap();
+        assert!((flows.envelope - 0.015).abs() < 1e-9);
+        assert!(memory.mechanical_ventilation[1].is_none());
+    }
+
+    #[test]
+    fn test_mechanical_ventilation() {
+        let mut simple_model = SimpleModel::default();
+        let mut state_header = SimulationStateHeader::new();
+
+        let mut space = Space::new("some space".to_string());
+        space.set_infiltration(Infiltration::Constant { flow: 0.03 });
+        let i = state_header
+            .push(SimulationStateElement::SpaceDryBulbTemperature(0), 20.)
+            .unwrap();
+        space.set_dry_bulb_temperature_index(i).unwrap();
+        let space = simple_model.add_space(space);
+
+        let mut options = AirFlowModelOptions::default();
+        options.spaces.insert(
+            "some space".to_string(),
+            SpaceAirFlowOptions {
+                mechanical_ventilation: Some(MechanicalVentilation {
+                    exhaust_flow: 0.025,
+                    ..MechanicalVentilation::default()
+                }),
+                exhaust_fans: vec![ExhaustFan {
+                    flow: 0.015,
+                    operation: FanOperation::Always,
+                    fan: Some(Fan {
+                        power: FanPower::SpecificFanPower(300.),
+                        heat_to_air: 0.0,
+                    }),
+                    make_up_air: MakeUpAir::Infiltration,
+                }],
+                ..SpaceAirFlowOptions::default()
+            },
+        );
+
+        let model = AirFlowModel::new(&META_OPTIONS, options, &simple_model, &mut state_header, 1)
+            .expect("Could not build AirFlow model");
+        let mut state = state_header
+            .take_values()
+            .expect("Could not take values form SimualationStateHeader");
+        let mut memory = model.allocate_memory().unwrap();
+
+        let mut weather = SyntheticWeather::default();
+        weather.dry_bulb_temperature = Box::new(ScheduleConstant::new(5.));
+        weather.wind_speed = Box::new(ScheduleConstant::new(0.));
+        let date = Date {
+            month: 1,
+            day: 1,
+            hour: 10.,
+        };
+        // Marching twice must not accumulate the fans
+        for _ in 0..2 {
+            model
+                .march(date, &weather, &simple_model, &mut state, &mut memory)
+                .unwrap();
+            // Exhaust only: sqrt(0.03^2 + (0.025 + 0.015)^2)
+            let found = space.infiltration_volume(&state).unwrap();
+            assert!((found - 0.05).abs() < 1e-9);
+            let power = memory.fan_power[0];
+            assert!((power - 300. * 0.015).abs() < 1e-9);
+            let flows = memory.mechanical_ventilation[0].unwrap();
+            assert!(flows.fans.abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_make_up_air() {
+        let mut simple_model = SimpleModel::default();
+        let mut state_header = SimulationStateHeader::new();
+
+        let mut kitchen = Space::new("kitchen".to_string());
+        kitchen.set_infiltration(Infiltration::Constant { flow: 0.01 });
+        let i = state_header
+            .push(SimulationStateElement::SpaceDryBulbTemperature(0), 20.)
+            .unwrap();
+        kitchen.set_dry_bulb_temperature_index(i).unwrap();
+        let kitchen = simple_model.add_space(kitchen);
+
+        let mut living = Space::new("living".to_string());
+        living.set_infiltration(Infiltration::Constant { flow: 0.015 });
+        let i = state_header
+            .push(SimulationStateElement::SpaceDryBulbTemperature(1), 22.)
+            .unwrap();
+        living.set_dry_bulb_temperature_index(i).unwrap();
+        let living = simple_model.add_space(living);
+
+        // The kitchen hood draws its make-up air from the living room
+        let mut options = AirFlowModelOptions::default();
+        options.spaces.insert(
+            "kitchen".to_string(),
+            SpaceAirFlowOptions {
+                exhaust_fans: vec![ExhaustFan {
+                    flow: 0.02,
+                    operation: FanOperation::Always,
+                    fan: None,
+                    make_up_air: MakeUpAir::Space("living".to_string()),
+                }],
+                ..SpaceAirFlowOptions::default()
+            },
+        );
+
+        let model = AirFlowModel::new(&META_OPTIONS, options, &simple_model, &mut state_header, 1)
+            .expect("Could not build AirFlow model");
+        let mut state = state_header
+            .take_values()
+            .expect("Could not take values form SimualationStateHeader");
+        let mut memory = model.allocate_memory().unwrap();
+
+        let mut weather = SyntheticWeather::default();
+        weather.dry_bulb_temperature = Box::new(ScheduleConstant::new(5.));
+        weather.wind_speed = Box::new(ScheduleConstant::new(0.));
+        let date = Date {
+            month: 1,
+            day: 1,
+            hour: 10.,
+        };
+        model
+            .march(date, &weather, &simple_model, &mut state, &mut memory)
+            .unwrap();
+
+        // The kitchen receives the air of the living room...
+        let found = kitchen.infiltration_volume(&state).unwrap();
+        assert!((found - 0.03).abs() < 1e-9);
+        let exp = (0.01 * 5. + 0.02 * 22.) / 0.03;
+        assert!((kitchen.infiltration_temperature(&state).unwrap() - exp).abs() < 1e-9);
+
+        // ... which makes it up through its envelope
+        assert!((memory.drawn_air[1] - 0.02).abs() < 1e-12);
+        let found = living.infiltration_volume(&state).unwrap();
+        assert!((found - 0.025).abs() < 1e-9);
+        assert!((living.infiltration_temperature(&state).unwrap() - 5.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_doas() {
+        let mut simple_model = SimpleModel::default();
+        let mut state_header = SimulationStateHeader::new();
+
+        let mut space = Space::new("some space".to_string());
+        space.set_infiltration(Infiltration::Constant { flow: 0.03 });
+        let i = state_header
+            .push(SimulationStateElement::SpaceDryBulbTemperature(0), 20.)
+            .unwrap();
+        space.set_dry_bulb_temperature_index(i).unwrap();
+        let space = simple_model.add_space(space);
+
+        let mut options = AirFlowModelOptions::default();
+        options.spaces.insert(
+            "some space".to_string(),
+            SpaceAirFlowOptions {
+                exhaust_fans: vec![ExhaustFan {
+                    flow: 0.02,
+                    operation: FanOperation::Always,
+                    fan: None,
+                    make_up_air: MakeUpAir::Infiltration,
+                }],
+                ..SpaceAirFlowOptions::default()
+            },
+        );
+        options.doas.push(Doas {
+            setpoint: DoasSetpoint::neutral(18., 22.),
+            outlets: vec![DoasOutlet {
+                space: "some space".to_string(),
+                flow: 0.1,
+                minimum_flow: 0.0,
+            }],
+            fan: Some(Fan {
+                power: FanPower::SpecificFanPower(1000.),
+                heat_to_air: 1.0,
+            }),
+            schedule: None,
+        });
+
+        // A mechanical supply would bypass the DOAS
+        let mut overlapping = options.clone();
+        overlapping
+            .spaces
+            .get_mut("some space")
+            .unwrap()
+            .mechanical_ventilation = Some(MechanicalVentilation {
+            supply_flow: 0.05,
+            ..MechanicalVentilation::default()
+        });
+        assert!(AirFlowModel::new(
+            &META_OPTIONS,
+            overlapping,
+            &simple_model,
+            &mut SimulationStateHeader::new(),
+            1
+        )
+        .is_err());
+
+        let model = AirFlowModel::new(&META_OPTIONS, options, &simple_model, &mut state_header, 1)
+            .expect("Could not build AirFlow model");
+        let mut state = state_header
+            .take_values()
+            .expect("Could not take values form SimualationStateHeader");
+        let mut memory = model.allocate_memory().unwrap();
+
+        let mut weather = SyntheticWeather::default();
+        weather.dry_bulb_temperature = Box::new(ScheduleConstant::new(5.));
+        weather.dew_point_temperature = Box::new(ScheduleConstant::new(0.));
+        weather.wind_speed = Box::new(ScheduleConstant::new(0.));
+        let date = Date {
+            month: 1,
+            day: 1,
+            hour: 10.,
+        };
+        for n in 1..=2 {
+            model
+                .march(date, &weather, &simple_model, &mut state, &mut memory)
+                .unwrap();
+            // The DOAS is balanced, so only the exhaust fan unbalances the Space
+            let found = space.infiltration_volume(&state).unwrap();
+            let envelope = (0.03 as Float).hypot(0.02);
+            assert!((found - envelope - 0.1).abs() < 1e-9);
+            let flows = memory.mechanical_ventilation[0].unwrap();
+            assert!((flows.fans - 0.1).abs() < 1e-12);
+
+            // Heated to 18 C, and then by the fan
+            let rise = memory.supply_temperature_rise[0];
+            assert!(rise > 0.0);
+            assert!((memory.fan_power[0] - 100.).abs() < 1e-9);
+            let energy = memory.doas_energy[0];
+            let loads = memory.doas_loads[0];
+            assert!((loads.supply_temperature - 18.).abs() < 1e-12);
+            assert!((energy.heating - n as Float * 3600. * loads.heating).abs() < 1e-6);
+            assert!((energy.fan - n as Float * 3600. * 100.).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_economizer() {
+        let mut simple_model = SimpleModel::default();
+        let mut state_header = SimulationStateHeader::new();
+
+        let mut space = Space::new("some space".to_string());
+        space.set_infiltration(Infiltration::Constant { flow: 0.0 });
+        let i = state_header
+            .push(SimulationStateElement::SpaceDryBulbTemperature(0), 20.)
+            .unwrap();
+        space.set_dry_bulb_temperature_index(i).unwrap();
+        let space = simple_model.add_space(space);
+
+        let mut options = AirFlowModelOptions::default();
+        options.spaces.insert(
+            "some space".to_string(),
+            SpaceAirFlowOptions {
+                mechanical_ventilation: Some(MechanicalVentilation {
+                    supply_flow: 0.2,
+                    exhaust_flow: 0.2,
+                    ..MechanicalVentilation::default()
+                }),
+                ..SpaceAirFlowOptions::default()
+            },
+        );
+        options.economizers.push(Economizer {
+            space: "some space".to_string(),
+            control: EconomizerControl::FixedDryBulb { high_limit: 25. },
+            high_limit_lockout: None,
+            low_limit_lockout: None,
+            minimum_flow: 0.1,
+            maximum_flow: 0.5,
+            cooling_setpoint: 24.,
+            throttling_range: 2.,
+            return_dew_point: 10.,
+        });
+
+        let model = AirFlowModel::new(&META_OPTIONS, options, &simple_model, &mut state_header, 1)
+            .expect("Could not build AirFlow model");
+        let mut state = state_header
+            .take_values()
+            .expect("Could not take values form SimualationStateHeader");
+        let mut memory = model.allocate_memory().unwrap();
+
+        let mut weather = SyntheticWeather::default();
+        weather.dry_bulb_temperature = Box::new(ScheduleConstant::new(15.));
+        weather.wind_speed = Box::new(ScheduleConstant::new(0.));
+        let date = Date {
+            month: 1,
+            day: 1,
+            hour: 10.,
+        };
+
+        // The minimum outdoor air is already supplied by the fans
+        model
+            .march(date, &weather, &simple_model, &mut state, &mut memory)
+            .unwrap();
+        let found = space.infiltration_volume(&state).unwrap();
+        assert!((found - 0.2).abs() < 1e-9);
+        assert!((memory.economizer_flows[0] - 0.2).abs() < 1e-9);
+
+        // Free cooling raises the outdoor air up to the maximum
+        space.set_dry_bulb_temperature(&mut state, 30.).unwrap();
+        model
+            .march(date, &weather, &simple_model, &mut state, &mut memory)
+            .unwrap();
+        let found = space.infiltration_volume(&state).unwrap();
+        assert!((found - 0.5).abs() < 1e-9);
+        assert!((memory.economizer_flows[0] - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_ventilation_system_spaces() {
+        let mut simple_model = SimpleModel::default();
+        simple_model.add_space(Space::new("office".to_string()));
+
+        let mut options = AirFlowModelOptions::default();
+        options.ventilation_systems.push(VentilationSystem {
+            kind: VentilationSystemKind::SingleZone,
+            zones: vec![VentilationZone {
+                space: "offices".to_string(),
+                category: OccupancyCategory::Office,
+                floor_area: 100.,
+                occupants: None,
+                distribution: AirDistribution::CeilingSupplyCoolAir,
+                primary_airflow: None,
+            }],
+            system_population: None,
+        });
+        let mut state_header = SimulationStateHeader::new();
+        let res = AirFlowModel::new(&META_OPTIONS, options, &simple_model, &mut state_header, 1);
+        assert!(res.is_err());
     }
 }
@@ -0,0 +1,271 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use crate::design_load::En12831Infiltration;
+use crate::doas::Doas;
+use crate::ducts::DuctSystem;
+use crate::economizer::Economizer;
+use crate::en16798::En16798Zone;
+use crate::interzone::InternalOpening;
+use crate::leakage::{Airtightness, EnvelopeComponent};
+use crate::mechanical::{ExhaustFan, MechanicalVentilation};
+use crate::profile::HourlyProfile;
+use crate::residential_ventilation::Dwelling;
+use crate::stack::StackVentilation;
+use crate::trickle_vents::TrickleVent;
+use crate::ventilation::NaturalVentilation;
+use crate::ventilation_rate::VentilationSystem;
+use crate::Float;
+use std::collections::HashMap;
+
+/// The terrain classes used by Sherman and Grimsrud for describing
+/// the wind speed profile at a site.
+///
+/// The local wind speed at height $`H`$ is $`U = \alpha (H/10)^\gamma U_{10}`$.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Terrain {
+    /// Ocean or other body of water with at least 5 km of unrestricted expanse
+    Ocean,
+    /// Flat terrain with some isolated obstacles (e.g., buildings or trees well
+    /// separated from each other)
+    Flat,
+    /// Rural areas with low buildings, trees, etc.
+    Rural,
+    /// Urban, industrial or forest areas
+    Urban,
+    /// Centre of a large city
+    CityCentre,
+    /// Custom parameters
+    Custom {
+        /// The $`\alpha`$ parameter
+        alpha: Float,
+        /// The $`\gamma`$ exponent
+        gamma: Float,
+    },
+}
+
+impl Default for Terrain {
+    /// The terrain assumed by the ASHRAE tabulated wind coefficients
+    fn default() -> Self {
+        Terrain::Rural
+    }
+}
+
+impl Terrain {
+    /// Returns the $`(\alpha, \gamma)`$ pair of this terrain
+    pub fn parameters(&self) -> (Float, Float) {
+        match self {
+            Terrain::Ocean => (1.30, 0.10),
+            Terrain::Flat => (1.00, 0.15),
+            Terrain::Rural => (0.85, 0.20),
+            Terrain::Urban => (0.67, 0.25),
+            Terrain::CityCentre => (0.47, 0.35),
+            Terrain::Custom { alpha, gamma } => (*alpha, *gamma),
+        }
+    }
+
+    /// Calculates the local wind speed (in m/s) at a certain height (in m)
+    /// above this terrain, for a certain wind speed (in m/s) measured by the
+    /// meteorological station
+    pub fn local_wind_speed(&self, wind_speed: Float, height: Float) -> Float {
+        let (alpha, gamma) = self.parameters();
+        wind_speed * alpha * (height / 10.).powf(gamma)
+    }
+}
+
+/// The parameters of the complete Sherman-Grimsrud (LBL) infiltration
+/// model, which are used instead of the tabulated coefficients when
+/// given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LblParameters {
+    /// The generalised shielding coefficient $`C'`$. If `None`, it is
+    /// inferred from the `Building`'s `shelter_class`.
+    pub shielding_coefficient: Option<Float>,
+
+    /// The fraction of the leakage area located in the ceiling
+    pub ceiling_leakage_fraction: Float,
+
+    /// The fraction of the leakage area located in the floor
+    pub floor_leakage_fraction: Float,
+
+    /// The terrain surrounding the meteorological station
+    pub met_station_terrain: Terrain,
+
+    /// The height at which the wind speed is measured at the
+    /// meteorological station, in m
+    pub met_station_height: Float,
+
+    /// The indoor temperature used for calculating the stack
+    /// coefficient, in C
+    pub indoor_temperature: Float,
+}
+
+impl Default for LblParameters {
+    /// A met station in open terrain, and half of the leakage area evenly
+    /// split between ceiling and floor
+    fn default() -> Self {
+        Self {
+            shielding_coefficient: None,
+            ceiling_leakage_fraction: 0.25,
+            floor_leakage_fraction: 0.25,
+            met_station_terrain: Terrain::Flat,
+            met_station_height: 10.,
+            indoor_temperature: 20.,
+        }
+    }
+}
+
+/// Additional information about a `Building` that is needed by the
+/// [`AirFlowModel`](crate::model::AirFlowModel) but is not part of
+/// the `SimpleModel`
+#[derive(Debug, Clone, Default)]
+pub struct BuildingAirFlowOptions {
+    /// The height of the building, in m
+    pub height: Option<Float>,
+
+    /// The height of each storey, in m. It is multiplied by the `Building`'s
+    /// `n_storeys` when no `height` is given.
+    pub storey_height: Option<Float>,
+
+    /// The terrain surrounding the building
+    pub terrain: Terrain,
+
+    /// The parameters of the complete Sherman-Grimsrud model. If `None`,
+    /// the tabulated coefficients are used (or extrapolated based on the
+    /// height of the building)
+    pub lbl: Option<LblParameters>,
+
+    /// The airtightness of the building. When given, it is distributed over
+    /// the `envelope` of its Spaces, which then ignore their `Infiltration`.
+    pub airtightness: Option<Airtightness>,
+}
+
+/// Additional information about a `Space` that is needed by the
+/// [`AirFlowModel`](crate::model::AirFlowModel) but is not part of
+/// the `SimpleModel`
+#[derive(Debug, Clone, Default)]
+pub struct SpaceAirFlowOptions {
+    /// The operable openings of the Space and how they are controlled
+    pub natural_ventilation: Option<NaturalVentilation>,
+
+    /// The number of occupants of the Space at each hour of the day. If
+    /// `None`, the Space is considered to be always occupied.
+    pub occupants: Option<HourlyProfile>,
+
+    /// The components of the envelope of the Space, over which the
+    /// `airtightness` of its Building is distributed
+    pub envelope: Vec<EnvelopeComponent>,
+
+    /// The trickle vents in the facades of the Space
+    pub trickle_vents: Vec<TrickleVent>,
+
+    /// The supply and exhaust fans of the Space, whose flows are
+    /// combined with its infiltration
+    pub mechanical_ventilation: Option<MechanicalVentilation>,
+
+    /// The intermittent local exhaust fans of the Space
+    pub exhaust_fans: Vec<ExhaustFan>,
+
+    /// The parameters for calculating the design infiltration of the Space
+    /// with EN 12831. If `None`, its `Infiltration` is used instead.
+    pub en12831: Option<En12831Infiltration>,
+
+    /// The internal volume of the Space, in m3. It is used for reporting
+    /// air changes per hour and CO2 concentrations.
+    pub volume: Option<Float>,
+
+    /// The minimum airflow the Space should receive, in m3/s. The time
+    /// during which it does not is reported.
+    pub minimum_airflow: Option<Float>,
+}
+
+impl SpaceAirFlowOptions {
+    /// Returns the number of occupants at a certain hour of the day, if
+    /// an occupancy profile was given
+    pub fn occupants_at(&self, hour: Float) -> Option<Float> {
+        self.occupants.as_ref().map(|profile| profile.at(hour))
+    }
+
+    /// Checks whether the Space is occupied at a certain hour of the day,
+    /// which it always is without an occupancy profile
+    pub fn is_occupied(&self, hour: Float) -> bool {
+        self.occupants_at(hour).map_or(true, |n| n > 0.0)
+    }
+
+    /// Checks whether the options make sense
+    pub(crate) fn check(&self, space_name: &str) -> Result<(), String> {
+        if let Some(profile) = &self.occupants {
+            profile.check(&format!("The occupancy profile of Space '{}'", space_name))?;
+        }
+        if let Some(ventilation) = &self.mechanical_ventilation {
+            ventilation.check(space_name)?;
+        }
+        for fan in self.exhaust_fans.iter() {
+            fan.check(space_name)?;
+        }
+        Ok(())
+    }
+}
+
+/// The options for building an [`AirFlowModel`](crate::model::AirFlowModel)
+#[derive(Debug, Clone, Default)]
+pub struct AirFlowModelOptions {
+    /// Additional information about the Buildings, indexed by the name
+    /// of the `Building`
+    pub buildings: HashMap<String, BuildingAirFlowOptions>,
+
+    /// Additional information about the Spaces, indexed by the name
+    /// of the `Space`
+    pub spaces: HashMap<String, SpaceAirFlowOptions>,
+
+    /// The seed of the random numbers used by stochastic models, which
+    /// makes simulations reproducible
+    pub random_seed: u64,
+
+    /// Tall volumes made of connected Spaces (e.g., atria or stairwells)
+    /// that are ventilated by the stack effect
+    pub stack_ventilation: Vec<StackVentilation>,
+
+    /// Large openings between Spaces (e.g., internal doorways)
+    pub internal_openings: Vec<InternalOpening>,
+
+    /// The air distribution systems whose ducts leak
+    pub duct_systems: Vec<DuctSystem>,
+
+    /// The dedicated outdoor air systems that condition the outdoor
+    /// air supplied to the Spaces
+    pub doas: Vec<Doas>,
+
+    /// The outdoor air economizers serving the Spaces
+    pub economizers: Vec<Economizer>,
+
+    /// The ventilation systems whose minimum outdoor airflows are given by
+    /// the Ventilation Rate Procedure of ASHRAE 62.1. The Spaces they serve
+    /// are mechanically ventilated with at least that airflow.
+    pub ventilation_systems: Vec<VentilationSystem>,
+
+    /// The dwellings whose whole-house fans deliver the ventilation
+    /// required by ASHRAE 62.2
+    pub dwellings: Vec<Dwelling>,
+
+    /// The Spaces whose airflows are calculated with the method of
+    /// EN 16798-7 instead of their `Infiltration`
+    pub en16798_zones: Vec<En16798Zone>,
+}
@@ -0,0 +1,400 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Writes time series of the airflows of chosen Spaces as CSV or as
+//! JSON lines, either every timestep or averaged over hours, days or months.
+//!
+//! A [`TimeSeriesWriter`] is fed after each call to `march`, and must be
+//! `finish`ed at the end of the simulation so that the last period is written.
+
+use crate::model::AirFlowModelMemory;
+use crate::physics::{air_density, AIR_SPECIFIC_HEAT};
+use crate::Float;
+use calendar::Date;
+use simple_model::{SimpleModel, SimulationState, Space};
+use std::io::Write;
+use std::sync::Arc;
+
+/// The quantities that can be reported for each Space
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputVariable {
+    /// The air entering the Space, in m3/s
+    InfiltrationVolume,
+    /// The temperature of the air entering the Space, in C
+    InfiltrationTemperature,
+    /// The mass of air entering the Space, in kg/s
+    MassFlow,
+    /// The heat gained by the Space because of the air entering it, in W
+    HeatGain,
+    /// The indoor pressure of the Space, in Pa (only for Spaces whose
+    /// airflows come from a pressure balance)
+    IndoorPressure,
+    /// The CO2 concentration of the Space, in ppm
+    Co2Concentration,
+}
+
+impl OutputVariable {
+    /// The name of the variable, as written in headers and keys
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutputVariable::InfiltrationVolume => "infiltration_volume",
+            OutputVariable::InfiltrationTemperature => "infiltration_temperature",
+            OutputVariable::MassFlow => "mass_flow",
+            OutputVariable::HeatGain => "heat_gain",
+            OutputVariable::IndoorPressure => "indoor_pressure",
+            OutputVariable::Co2Concentration => "co2_concentration",
+        }
+    }
+}
+
+/// How often rows are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportingFrequency {
+    /// Every timestep, without aggregating
+    Timestep,
+    /// The mean of each hour
+    Hourly,
+    /// The mean of each day
+    Daily,
+    /// The mean of each month
+    Monthly,
+}
+
+/// The format of the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Comma separated values, with a header
+    Csv,
+    /// One JSON object per line
+    JsonLines,
+}
+
+/// A variable of a Space to be reported
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputRequest {
+    /// The name of the Space
+    pub space: String,
+    /// The variable to report
+    pub variable: OutputVariable,
+}
+
+/// Writes the requested variables as time series
+pub struct TimeSeriesWriter<W: Write> {
+    writer: W,
+    format: OutputFormat,
+    frequency: ReportingFrequency,
+    requests: Vec<OutputRequest>,
+    /// The Space of each request, and its index in the model
+    spaces: Vec<(usize, Arc<Space>)>,
+    header_written: bool,
+    /// The month, day and hour at which the current period started
+    period_start: Option<(usize, usize, Float)>,
+    sums: Vec<Float>,
+    count: usize,
+}
+
+impl<W: Write> TimeSeriesWriter<W> {
+    /// Creates a new writer, checking that the requested Spaces exist
+    pub fn new(
+        writer: W,
+        format: OutputFormat,
+        frequency: ReportingFrequency,
+        requests: Vec<OutputRequest>,
+        model: &SimpleModel,
+    ) -> Result<Self, String> {
+        let mut spaces = Vec::with_capacity(requests.len());
+        for request in requests.iter() {
+            let index = model
+                .spaces
+                .iter()
+                .position(|s| s.name == request.space)
+                .ok_or_else(|| format!("Could not find Space '{}' to report", request.space))?;
+            spaces.push((index, Arc::clone(&model.spaces[index])));
+        }
+        Ok(Self {
+            writer,
+            format,
+            frequency,
+            sums: vec![0.0; requests.len()],
+            requests,
+            spaces,
+            header_written: false,
+            period_start: None,
+            count: 0,
+        })
+    }
+
+    /// Returns the key identifying the reporting period of a date
+    fn period(&self, date: (usize, usize, Float)) -> (usize, usize, i64) {
+        let (month, day, hour) = date;
+        match self.frequency {
+            // Every timestep is different
+            ReportingFrequency::Timestep => (month, day, (hour * 1e6).round() as i64),
+            ReportingFrequency::Hourly => (month, day, hour.floor() as i64),
+            ReportingFrequency::Daily => (month, day, 0),
+            ReportingFrequency::Monthly => (month, 0, 0),
+        }
+    }
+
+    /// Reads the value of a request from the state of the simulation
+    fn value(
+        &self,
+        i: usize,
+        state: &SimulationState,
+        memory: &AirFlowModelMemory,
+    ) -> Result<Float, String> {
+        let (index, space) = &self.spaces[i];
+        let volume = space.infiltration_volume(state)?;
+        let temperature = space.infiltration_temperature(state)?;
+        Ok(match self.requests[i].variable {
+            OutputVariable::InfiltrationVolume => volume,
+            OutputVariable::InfiltrationTemperature => temperature,
+            OutputVariable::MassFlow => air_density(temperature) * volume,
+            OutputVariable::HeatGain => {
+                let space_temperature = space.dry_bulb_temperature(state)?;
+                air_density(temperature)
+                    * AIR_SPECIFIC_HEAT
+                    * volume
+                    * (temperature - space_temperature)
+            }
+            OutputVariable::IndoorPressure => memory
+                .indoor_pressures
+                .get(*index)
+                .cloned()
+                .flatten()
+                .unwrap_or(Float::NAN),
+            OutputVariable::Co2Concentration => {
+                memory.statistics.get(*index).map_or(Float::NAN, |s| s.co2)
+            }
+        })
+    }
+
+    /// Records the state of the simulation after a call to `march`
+    pub fn record(
+        &mut self,
+        date: Date,
+        state: &SimulationState,
+        memory: &AirFlowModelMemory,
+    ) -> Result<(), String> {
+        let date = (date.month as usize, date.day as usize, date.hour as Float);
+        if let Some(start) = self.period_start {
+            if self.period(start) != self.period(date) {
+                self.flush()?;
+            }
+        }
+        if self.period_start.is_none() {
+            self.period_start = Some(date);
+        }
+        for i in 0..self.requests.len() {
+            self.sums[i] += self.value(i, state, memory)?;
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Writes the period being accumulated, if any
+    fn flush(&mut self) -> Result<(), String> {
+        let (month, day, hour) = match self.period_start {
+            Some(start) if self.count > 0 => start,
+            _ => return Ok(()),
+        };
+        let n = self.count as Float;
+        let values: Vec<Float> = self.sums.iter().map(|s| s / n).collect();
+        let mut line = String::new();
+        match self.format {
+            OutputFormat::Csv => {
+                if !self.header_written {
+                    let mut header = "month,day,hour".to_string();
+                    for request in self.requests.iter() {
+                        header += &format!(
+                            ",{}:{}",
+                            csv_escape(&request.space),
+                            request.variable.name()
+                        );
+                    }
+                    writeln!(self.writer, "{}", header).map_err(|e| e.to_string())?;
+                    self.header_written = true;
+                }
+                line += &format!("{},{},{}", month, day, hour);
+                for v in values.iter() {
+                    if v.is_finite() {
+                        line += &format!(",{}", v);
+                    } else {
+                        line += ",";
+                    }
+                }
+            }
+            OutputFormat::JsonLines => {
+                line += &format!("{{\"month\":{},\"day\":{},\"hour\":{}", month, day, hour);
+                for (request, v) in self.requests.iter().zip(values.iter()) {
+                    let key =
+                        json_escape(&format!("{}:{}", request.space, request.variable.name()));
+                    if v.is_finite() {
+                        line += &format!(",\"{}\":{}", key, v);
+                    } else {
+                        line += &format!(",\"{}\":null", key);
+                    }
+                }
+                line += "}";
+            }
+        }
+        writeln!(self.writer, "{}", line).map_err(|e| e.to_string())?;
+
+        self.period_start = None;
+        self.count = 0;
+        for s in self.sums.iter_mut() {
+            *s = 0.0;
+        }
+        Ok(())
+    }
+
+    /// Writes the last period and returns the underlying writer
+    pub fn finish(mut self) -> Result<W, String> {
+        self.flush()?;
+        self.writer.flush().map_err(|e| e.to_string())?;
+        Ok(self.writer)
+    }
+}
+
+/// Quotes a CSV field if it needs to
+fn csv_escape(field: &str) -> String {
+    if field.contains(',') || field.contains('"') || field.contains('\n') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Escapes a string to be written within quotes in JSON
+fn json_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped += "\\\"",
+            '\\' => escaped += "\\\\",
+            '\n' => escaped += "\\n",
+            c if (c as u32) < 0x20 => escaped += &format!("\\u{:04x}", c as u32),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::AirFlowModel;
+    use crate::options::AirFlowModelOptions;
+    use communication_protocols::{MetaOptions, SimulationModel};
+    use schedule::ScheduleConstant;
+    use simple_model::{Infiltration, SimulationStateElement, SimulationStateHeader};
+    use weather::SyntheticWeather;
+
+    fn run(format: OutputFormat, frequency: ReportingFrequency) -> String {
+        let mut simple_model = SimpleModel::default();
+        let mut state_header = SimulationStateHeader::new();
+        let mut space = Space::new("a, room".to_string());
+        space.set_infiltration(Infiltration::Constant { flow: 0.1 });
+        let i = state_header
+            .push(SimulationStateElement::SpaceDryBulbTemperature(0), 20.)
+            .unwrap();
+        space.set_dry_bulb_temperature_index(i).unwrap();
+        simple_model.add_space(space);
+
+        let meta_options = MetaOptions {
+            latitude: 0.,
+            longitude: 0.,
+            standard_meridian: 0.,
+            elevation: 0.0,
+        };
+        let model = AirFlowModel::new(
+            &meta_options,
+            AirFlowModelOptions::default(),
+            &simple_model,
+            &mut state_header,
+            2,
+        )
+        .unwrap();
+        let mut state = state_header.take_values().unwrap();
+        let mut memory = model.allocate_memory().unwrap();
+        let mut weather = SyntheticWeather::default();
+        weather.dry_bulb_temperature = Box::new(ScheduleConstant::new(10.));
+
+        let requests = vec![
+            OutputRequest {
+                space: "a, room".to_string(),
+                variable: OutputVariable::InfiltrationVolume,
+            },
+            OutputRequest {
+                space: "a, room".to_string(),
+                variable: OutputVariable::IndoorPressure,
+            },
+        ];
+        let mut writer =
+            TimeSeriesWriter::new(Vec::new(), format, frequency, requests, &simple_model).unwrap();
+        for hour in [0.0, 0.5, 1.0, 1.5].iter() {
+            let date = Date {
+                month: 1,
+                day: 1,
+                hour: *hour,
+            };
+            model
+                .march(date, &weather, &simple_model, &mut state, &mut memory)
+                .unwrap();
+            writer.record(date, &state, &memory).unwrap();
+        }
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_csv() {
+        let out = run(OutputFormat::Csv, ReportingFrequency::Timestep);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines[0],
+            "month,day,hour,\"a, room\":infiltration_volume,\"a, room\":indoor_pressure"
+        );
+        assert_eq!(lines[2], "1,1,0.5,0.1,");
+
+        let out = run(OutputFormat::Csv, ReportingFrequency::Hourly);
+        assert_eq!(out.lines().count(), 3);
+        let out = run(OutputFormat::Csv, ReportingFrequency::Daily);
+        assert_eq!(out.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let out = run(OutputFormat::JsonLines, ReportingFrequency::Hourly);
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[1],
+            "{\"month\":1,\"day\":1,\"hour\":1,\"a, room:infiltration_volume\":0.1,\"a, room:indoor_pressure\":null}"
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(csv_escape("plain"), "plain");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(json_escape("a\"b\\c"), "a\\\"b\\\\c");
+    }
+}
@@ -0,0 +1,131 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! The physical constants and psychrometric relations shared by the
+//! models of this crate.
+
+use crate::Float;
+
+/// The acceleration of gravity, in m/s2
+pub const GRAVITY: Float = 9.81;
+
+/// The gas constant of dry air, in J/(kg K)
+pub const AIR_GAS_CONSTANT: Float = 287.055;
+
+/// The specific heat of dry air, in J/(kg K)
+pub const AIR_SPECIFIC_HEAT: Float = 1005.;
+
+/// The specific heat of water vapour, in J/(kg K)
+pub const VAPOUR_SPECIFIC_HEAT: Float = 1860.;
+
+/// The latent heat of vaporisation of water at 0 C, in J/kg
+pub const LATENT_HEAT: Float = 2_501_000.;
+
+/// The ratio between the molar masses of water and dry air
+const MOLAR_MASS_RATIO: Float = 0.622;
+
+/// The standard atmospheric pressure, in Pa
+pub const ATMOSPHERIC_PRESSURE: Float = 101325.;
+
+/// Calculates the density of dry air (in kg/m3) at a certain
+/// temperature (in C) and standard atmospheric pressure
+pub fn air_density(temperature: Float) -> Float {
+    ATMOSPHERIC_PRESSURE / (AIR_GAS_CONSTANT * (temperature + 273.15))
+}
+
+/// Calculates the saturation pressure of water vapour (in Pa) at a
+/// certain temperature (in C), using the Magnus formula
+pub fn saturation_pressure(temperature: Float) -> Float {
+    610.94 * (17.625 * temperature / (temperature + 243.04)).exp()
+}
+
+/// Calculates the partial pressure of water vapour (in Pa) in air with
+/// a certain humidity ratio (in kg/kg)
+fn vapour_pressure(humidity_ratio: Float) -> Float {
+    humidity_ratio * ATMOSPHERIC_PRESSURE / (MOLAR_MASS_RATIO + humidity_ratio)
+}
+
+/// Calculates the humidity ratio (kg of water per kg of dry air) of air
+/// with a certain dew point temperature (in C)
+pub fn humidity_ratio(dew_point_temperature: Float) -> Float {
+    let pw = saturation_pressure(dew_point_temperature);
+    MOLAR_MASS_RATIO * pw / (ATMOSPHERIC_PRESSURE - pw)
+}
+
+/// Calculates the relative humidity (between 0 and 1) of air at a certain
+/// temperature (in C) and humidity ratio (kg/kg)
+pub fn relative_humidity(temperature: Float, humidity_ratio: Float) -> Float {
+    (vapour_pressure(humidity_ratio) / saturation_pressure(temperature)).min(1.0)
+}
+
+/// Calculates the dew point temperature (in C) of air with a certain
+/// humidity ratio (in kg/kg), inverting the Magnus formula
+pub fn dew_point(humidity_ratio: Float) -> Float {
+    let x = (vapour_pressure(humidity_ratio) / 610.94).ln();
+    243.04 * x / (17.625 - x)
+}
+
+/// Calculates the specific heat of moist air (in J/(kg K) of dry air)
+/// with a certain humidity ratio (in kg/kg)
+pub fn moist_air_specific_heat(humidity_ratio: Float) -> Float {
+    AIR_SPECIFIC_HEAT + VAPOUR_SPECIFIC_HEAT * humidity_ratio
+}
+
+/// Calculates the specific enthalpy of moist air (in J/kg of dry air) at
+/// a certain temperature (in C) and humidity ratio (in kg/kg)
+pub fn enthalpy(temperature: Float, humidity_ratio: Float) -> Float {
+    AIR_SPECIFIC_HEAT * temperature
+        + humidity_ratio * (LATENT_HEAT + VAPOUR_SPECIFIC_HEAT * temperature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_air_density() {
+        assert!((air_density(20.) - 1.2041).abs() < 1e-3);
+        assert!(air_density(0.) > air_density(20.));
+    }
+
+    #[test]
+    fn test_psychrometrics() {
+        assert!((saturation_pressure(20.) - 2339.).abs() < 5.);
+        // Dew point of 9.3 C is about 50% relative humidity at 20 C
+        let w = humidity_ratio(9.3);
+        assert!((w - 0.00726).abs() < 0.0002);
+        assert!((relative_humidity(20., w) - 0.5).abs() < 0.01);
+        // Saturated
+        assert!((relative_humidity(15., humidity_ratio(15.)) - 1.).abs() < 1e-9);
+        // dew_point inverts humidity_ratio
+        assert!((dew_point(humidity_ratio(12.)) - 12.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_enthalpy() {
+        // 20 C and 50% relative humidity: about 38.5 kJ/kg
+        let w = humidity_ratio(9.3);
+        assert!((enthalpy(20., w) - 38_500.).abs() < 500.);
+        // Dry air at 0 C has no enthalpy
+        assert!(enthalpy(0., 0.).abs() < 1e-12);
+        let cp = moist_air_specific_heat(w);
+        assert!(((enthalpy(21., w) - enthalpy(20., w)) - cp).abs() < 1e-6);
+    }
+}
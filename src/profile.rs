@@ -0,0 +1,97 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Values that change with the hour of the day (e.g., schedules and
+//! occupancy profiles).
+
+use crate::Float;
+
+/// The number of values in an [`HourlyProfile`]
+pub const HOURS_PER_DAY: usize = 24;
+
+/// A value for each hour of the day (i.e., 24 values, the first one from
+/// 00:00 to 01:00)
+#[derive(Debug, Clone, PartialEq)]
+pub struct HourlyProfile(pub Vec<Float>);
+
+impl From<Vec<Float>> for HourlyProfile {
+    fn from(values: Vec<Float>) -> Self {
+        Self(values)
+    }
+}
+
+impl HourlyProfile {
+    /// A profile with the same value at every hour
+    pub fn constant(value: Float) -> Self {
+        Self(vec![value; HOURS_PER_DAY])
+    }
+
+    /// Returns the value at a certain hour of the day, or 0 if the
+    /// profile is empty
+    pub fn at(&self, hour: Float) -> Float {
+        if self.0.is_empty() {
+            return 0.0;
+        }
+        self.0[(hour.max(0.0).floor() as usize) % self.0.len()]
+    }
+
+    /// Checks that the profile has a value for each hour of the day.
+    /// The `name` describes the profile in the error message (e.g.,
+    /// "The schedule of the DOAS").
+    pub(crate) fn check(&self, name: &str) -> Result<(), String> {
+        if self.0.len() != HOURS_PER_DAY {
+            return Err(format!(
+                "{} has {} values... it must have {}",
+                name,
+                self.0.len(),
+                HOURS_PER_DAY
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_at() {
+        let mut values = vec![0.0; 24];
+        values[7] = 0.25;
+        let profile = HourlyProfile::from(values);
+        assert!(profile.check("The profile").is_ok());
+        assert!((profile.at(7.5) - 0.25).abs() < 1e-12);
+        assert!((profile.at(31.2) - 0.25).abs() < 1e-12);
+        assert!(profile.at(-3.).abs() < 1e-12);
+        assert!((HourlyProfile::constant(2.).at(13.) - 2.).abs() < 1e-12);
+        assert!(HourlyProfile::from(Vec::new()).at(13.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_check() {
+        let profile = HourlyProfile::from(vec![1.; 2]);
+        let err = profile.check("The schedule of the DOAS").unwrap_err();
+        assert_eq!(
+            err,
+            "The schedule of the DOAS has 2 values... it must have 24"
+        );
+    }
+}
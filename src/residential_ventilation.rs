@@ -0,0 +1,354 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Whole-house and local exhaust ventilation of dwellings, after
+//! ASHRAE Standard 62.2.
+//!
+//! The total required ventilation is $`Q_{tot} = 0.15 A_{floor} + 3.5 (N_{br} + 1)`$
+//! (in L/s), part of which can be credited to infiltration. At design time,
+//! the credit is estimated from the airtightness of the dwelling. When
+//! simulating, the whole-house fan delivers whatever the infiltration
+//! calculated for the Space does not.
+
+use crate::catalogue::{ela_to_power_law, DEFAULT_LEAKAGE_EXPONENT};
+use crate::mechanical::Fan;
+use crate::Float;
+
+/// The reference height of ASHRAE 62.2's infiltration credit, in m
+const REFERENCE_HEIGHT: Float = 2.5;
+
+/// The exponent of the height correction of the infiltration credit
+const HEIGHT_EXPONENT: Float = 0.4;
+
+/// How leaky a dwelling is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DwellingLeakage {
+    /// The effective leakage area, in $`cm^2`$ at 4 Pa (e.g., as used by
+    /// `Infiltration::EffectiveAirLeakageArea` or given by the
+    /// [`catalogue`](crate::catalogue))
+    EffectiveLeakageArea(Float),
+    /// The flow at 50 Pa measured by a blower-door test, in m3/s
+    BlowerDoor(Float),
+}
+
+impl DwellingLeakage {
+    /// The flow through the envelope at 50 Pa, in m3/s
+    pub fn flow_at_50pa(&self) -> Float {
+        match self {
+            DwellingLeakage::EffectiveLeakageArea(area) => {
+                let p = ela_to_power_law(*area, DEFAULT_LEAKAGE_EXPONENT);
+                p.coefficient * (50. as Float).powf(p.exponent)
+            }
+            DwellingLeakage::BlowerDoor(q50) => *q50,
+        }
+    }
+}
+
+/// How the whole-house fan moves air
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WholeHouseFanKind {
+    /// Supply and exhaust fans moving the same flow
+    Balanced,
+    /// An exhaust-only fan
+    Exhaust,
+    /// A supply-only fan
+    Supply,
+}
+
+/// The fan delivering the whole-house ventilation of a dwelling
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WholeHouseFan {
+    /// How the fan moves air
+    pub kind: WholeHouseFanKind,
+
+    /// The largest flow the fan can move, in m3/s. If `None`, it can
+    /// move any flow.
+    pub capacity: Option<Float>,
+
+    /// The fan, if its power is to be accounted for. Balanced
+    /// systems have two of them.
+    pub fan: Option<Fan>,
+}
+
+impl WholeHouseFan {
+    /// The supply and exhaust flows (in m3/s) of the fan when moving a
+    /// certain flow (in m3/s). They are to be superposed with the
+    /// infiltration and the other fans of the Space.
+    pub fn flows(&self, flow: Float) -> (Float, Float) {
+        match self.kind {
+            WholeHouseFanKind::Balanced => (flow, flow),
+            WholeHouseFanKind::Exhaust => (0.0, flow),
+            WholeHouseFanKind::Supply => (flow, 0.0),
+        }
+    }
+
+    /// The electric power (in W) of the fan when moving a certain flow (in m3/s)
+    pub fn power(&self, flow: Float) -> Float {
+        let n_fans = match self.kind {
+            WholeHouseFanKind::Balanced => 2.,
+            _ => 1.,
+        };
+        self.fan.map_or(0.0, |f| n_fans * f.power(flow))
+    }
+
+    /// The temperature rise (in K) of the air supplied by the fan at a
+    /// certain temperature (in C). Exhaust fans supply no air.
+    pub fn temperature_rise(&self, temperature: Float) -> Float {
+        match (self.kind, &self.fan) {
+            (WholeHouseFanKind::Exhaust, _) | (_, None) => 0.0,
+            (_, Some(fan)) => fan.temperature_rise(temperature),
+        }
+    }
+}
+
+/// The rooms that need local exhaust
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LocalExhaustRoom {
+    /// A kitchen, with its volume in m3
+    Kitchen {
+        /// The volume of the kitchen, in m3
+        volume: Float,
+    },
+    /// A bathroom
+    Bathroom,
+}
+
+/// A local exhaust fan of a dwelling
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocalExhaust {
+    /// The room served by the fan
+    pub room: LocalExhaustRoom,
+
+    /// Whether the fan runs continuously (`true`) or on demand (`false`)
+    pub continuous: bool,
+
+    /// The flow of the fan, in m3/s
+    pub flow: Float,
+}
+
+impl LocalExhaust {
+    /// The flow (in m3/s) required by ASHRAE 62.2: 50 L/s for demand controlled
+    /// kitchen fans and 5 air changes per hour for continuous ones; 25 L/s for
+    /// demand controlled bathroom fans and 10 L/s for continuous ones.
+    pub fn required_flow(&self) -> Float {
+        match (self.room, self.continuous) {
+            (LocalExhaustRoom::Kitchen { .. }, false) => 0.050,
+            (LocalExhaustRoom::Kitchen { volume }, true) => 5. * volume / 3600.,
+            (LocalExhaustRoom::Bathroom, false) => 0.025,
+            (LocalExhaustRoom::Bathroom, true) => 0.010,
+        }
+    }
+
+    /// Whether the fan moves at least the required flow
+    pub fn complies(&self) -> bool {
+        self.flow >= self.required_flow()
+    }
+}
+
+/// A dwelling ventilated according to ASHRAE 62.2
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dwelling {
+    /// The name of the Space that represents the dwelling
+    pub space: String,
+
+    /// The floor area, in m2
+    pub floor_area: Float,
+
+    /// The number of bedrooms
+    pub bedrooms: usize,
+
+    /// The vertical distance between the lowest and the highest
+    /// above-grade points of the pressure boundary, in m
+    pub height: Float,
+
+    /// The weather and shielding factor of the location of the dwelling
+    pub weather_shielding_factor: Float,
+
+    /// How leaky the dwelling is. If `None`, the `AirFlowModel` takes it
+    /// from the `Infiltration::EffectiveAirLeakageArea` of the Space or from
+    /// the leaks of its envelope (e.g., the `airtightness` of its Building).
+    /// If there is none of them either, no infiltration is credited at
+    /// design time.
+    pub leakage: Option<DwellingLeakage>,
+
+    /// The fraction of the envelope that is exposed to outdoors (1 for
+    /// detached dwellings)
+    pub exposed_fraction: Float,
+
+    /// The whole-house ventilation fan
+    pub fan: WholeHouseFan,
+
+    /// The local exhaust fans of kitchens and bathrooms
+    pub local_exhaust: Vec<LocalExhaust>,
+}
+
+impl Dwelling {
+    /// The total required ventilation $`Q_{tot}`$, in m3/s
+    pub fn total_required_ventilation(&self) -> Float {
+        (0.15 * self.floor_area + 3.5 * (self.bedrooms as Float + 1.)) / 1000.
+    }
+
+    /// The effective annual average infiltration
+    /// $`Q_{inf} = 0.052\ Q_{50}\ wsf\ (H/H_r)^z`$, in m3/s
+    pub fn effective_infiltration(&self) -> Float {
+        self.leakage.map_or(0.0, |leakage| {
+            0.052
+                * leakage.flow_at_50pa()
+                * self.weather_shielding_factor
+                * (self.height / REFERENCE_HEIGHT).powf(HEIGHT_EXPONENT)
+        })
+    }
+
+    /// The flow (in m3/s) the whole-house fan must move at design time,
+    /// $`Q_{fan} = Q_{tot} - \Phi\ Q_{inf} A_{ext}`$, where $`\Phi`$ is 1
+    /// for balanced fans and $`Q_{inf}/Q_{tot}`$ for unbalanced ones
+    pub fn required_fan_flow(&self) -> Float {
+        let q_tot = self.total_required_ventilation();
+        let q_inf = self.effective_infiltration() * self.exposed_fraction;
+        let phi = match self.fan.kind {
+            WholeHouseFanKind::Balanced => 1.0,
+            _ => q_inf / q_tot,
+        };
+        (q_tot - phi * q_inf).max(0.0)
+    }
+
+    /// The flow (in m3/s) the whole-house fan must move so that, combined
+    /// with a certain infiltration (in m3/s), the dwelling receives the
+    /// total required ventilation
+    pub fn fan_flow(&self, infiltration: Float) -> Float {
+        let q_tot = self.total_required_ventilation();
+        let flow = match self.fan.kind {
+            WholeHouseFanKind::Balanced => (q_tot - infiltration).max(0.0),
+            _ => (q_tot * q_tot - infiltration * infiltration)
+                .max(0.0)
+                .sqrt(),
+        };
+        match self.fan.capacity {
+            Some(capacity) => flow.min(capacity),
+            None => flow,
+        }
+    }
+
+    /// Checks whether the dwelling makes sense
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.exposed_fraction) {
+            return Err(format!(
+                "The exposed fraction of the envelope of dwelling '{}' must be between 0 and 1",
+                self.space
+            ));
+        }
+        if self.floor_area <= 0.0 || self.height <= 0.0 {
+            return Err(format!(
+                "Dwelling '{}' must have a positive floor area and height",
+                self.space
+            ));
+        }
+        if let Some(fan) = &self.fan.fan {
+            fan.check(&format!("the dwelling in Space '{}'", self.space))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalogue::ELA_REFERENCE_PRESSURE;
+    use crate::elements::REFERENCE_DENSITY;
+    use crate::mechanical::superpose;
+
+    fn dwelling(kind: WholeHouseFanKind) -> Dwelling {
+        Dwelling {
+            space: "house".to_string(),
+            floor_area: 150.,
+            bedrooms: 3,
+            height: 2.5,
+            weather_shielding_factor: 0.5,
+            leakage: Some(DwellingLeakage::BlowerDoor(0.5)),
+            exposed_fraction: 1.0,
+            fan: WholeHouseFan {
+                kind,
+                capacity: None,
+                fan: None,
+            },
+            local_exhaust: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_required_ventilation() {
+        let house = dwelling(WholeHouseFanKind::Balanced);
+        assert!(house.check().is_ok());
+        // 22.5 + 14 L/s
+        assert!((house.total_required_ventilation() - 0.0365).abs() < 1e-12);
+        // 0.052 * 0.5 * 0.5
+        assert!((house.effective_infiltration() - 0.013).abs() < 1e-12);
+        assert!((house.required_fan_flow() - 0.0235).abs() < 1e-12);
+
+        // Unbalanced fans get a smaller credit
+        let house = dwelling(WholeHouseFanKind::Exhaust);
+        let exp = 0.0365 - 0.013 * 0.013 / 0.0365;
+        assert!((house.required_fan_flow() - exp).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_leakage() {
+        let ela = DwellingLeakage::EffectiveLeakageArea(500.);
+        let q4 = 0.05 * (2. * ELA_REFERENCE_PRESSURE / REFERENCE_DENSITY).sqrt();
+        let exp = q4 * (50. / ELA_REFERENCE_PRESSURE).powf(DEFAULT_LEAKAGE_EXPONENT);
+        assert!((ela.flow_at_50pa() - exp).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_fan_flow() {
+        let mut house = dwelling(WholeHouseFanKind::Balanced);
+        assert!((house.fan_flow(0.0165) - 0.02).abs() < 1e-12);
+        assert!(house.fan_flow(0.05).abs() < 1e-12);
+        let (supply, exhaust) = house.fan.flows(house.fan_flow(0.0165));
+        let s = superpose(0.0165, supply, exhaust);
+        assert!((s.total() - 0.0365).abs() < 1e-12);
+
+        house.fan.kind = WholeHouseFanKind::Exhaust;
+        let (supply, exhaust) = house.fan.flows(house.fan_flow(0.0165));
+        assert!(supply.abs() < 1e-12);
+        let s = superpose(0.0165, supply, exhaust);
+        assert!((s.total() - 0.0365).abs() < 1e-12);
+
+        house.fan.capacity = Some(0.01);
+        assert!((house.fan_flow(0.0) - 0.01).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_local_exhaust() {
+        let kitchen = LocalExhaust {
+            room: LocalExhaustRoom::Kitchen { volume: 36. },
+            continuous: true,
+            flow: 0.04,
+        };
+        assert!((kitchen.required_flow() - 0.05).abs() < 1e-12);
+        assert!(!kitchen.complies());
+        let bathroom = LocalExhaust {
+            room: LocalExhaustRoom::Bathroom,
+            continuous: false,
+            flow: 0.025,
+        };
+        assert!(bathroom.complies());
+    }
+}
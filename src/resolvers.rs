@@ -1,8 +1,9 @@
-use crate::model::Resolver;
+use crate::model::{CoefficientSource, LeakageCoefficients, Resolver};
+use crate::options::{AirFlowModelOptions, BuildingAirFlowOptions, LblParameters};
 use crate::Float;
 use std::sync::Arc;
 
-use simple_model::{Building, ShelterClass, SimpleModel, SimulationState, Space};
+use simple_model::{Building, SimpleModel, SimulationState, Space};
 
 use crate::eplus::*;
 use weather::CurrentWeather;
@@ -89,127 +90,218 @@ pub fn design_flow_rate_resolver(
     ))
 }
 
-fn resolve_stack_coefficient(space: &Arc<Space>, building: &Arc<Building>) -> Result<Float, String> {
-    let cs = match building.stack_coefficient() {
-        Ok(v)=>*v,
-        Err(_)=>{
-            match building.n_storeys(){
-                Ok(storeys)=>{
-                    let n_storeys = *storeys;
-                    if n_storeys == 0{
-                        return Err(format!("Building '{}' has 0 storeys", building.name));
-                    }else if n_storeys == 1 {
-                        0.000145
-                    }else if n_storeys == 2 {
-                        0.000290
-                    }else if n_storeys == 3 {
-                        0.000435
-                    }else {
-                        eprintln!("The Infiltration::EffectiveAirLeakageArea object (used in Space '{}') is appropriate for Building up to about 3 storeys... Building is {} storeys", space.name, storeys);
-                        0.000435
-                    }
-                },
-                Err(_)=>return Err(format!("Space '{}' has been assigned an Infiltration::EffectiveAirLeakageArea but its associated building has not enough data... Please assign values to the Building's stack_coefficient or n_storey fields", space.name))
+/// How the complete Sherman-Grimsrud model is referred to in error messages
+const LBL_MODEL_NAME: &str = "the Sherman-Grimsrud infiltration model";
+
+/// Infers the height of a building when its stack and wind coefficients cannot
+/// be taken from the ASHRAE tables (i.e., it is not made of 1 to 3 storeys
+/// of standard height).
+fn building_height(building: &Building, options: &BuildingAirFlowOptions) -> Option<Float> {
+    if let Some(height) = options.height {
+        return Some(height);
+    }
+    match building.n_storeys() {
+        Ok(storeys) if *storeys > 0 => {
+            let n_storeys = *storeys;
+            if let Some(storey_height) = options.storey_height {
+                Some(n_storeys as Float * storey_height)
+            } else if n_storeys > 3 {
+                Some(n_storeys as Float * DEFAULT_STOREY_HEIGHT)
+            } else {
+                None
             }
         }
-    };
-    Ok(cs)
+        _ => None,
+    }
 }
 
-fn resolve_wind_coefficient(space: &Arc<Space>, building: &Arc<Building>) -> Result<Float, String> {
-    let cw = match building.wind_coefficient() {
-        Ok(v) => *v,
-        Err(_) => {
-            let n_storeys = match building.n_storeys(){
-                Ok(storeys)=>*storeys,
-                Err(_)=>{return Err(format!("Building '{}', associated with Space '{}' has not been assigned an n_storeys field... Cannot resolve Wind Coefficient for EffectiveAirLeakageArea infiltration", building.name, space.name))}
-            };
-            match building.shelter_class(){
-                Ok(shelter)=>{
-                    match shelter{
-                        ShelterClass::NoObstructions=>{
-                            if n_storeys == 1 {
-                                0.000319
-                            }else if n_storeys == 2 {
-                                0.000420
-                            }else{
-                                0.000494
-                            }
-                        },
-                        ShelterClass::IsolatedRural=>{
-                            if n_storeys == 1 {
-                                0.000246
-                            }else if n_storeys == 2 {
-                                0.000325
-                            }else{
-                                0.000382
-                            }
-                        },
-                        ShelterClass::Urban=>{
-                            if n_storeys == 1 {
-                                0.000172
-                            }else if n_storeys == 2 {
-                                0.000231
-                            }else{
-                                0.000271
-                            }
-                        },
-                        ShelterClass::LargeLotUrban=>{
-                            if n_storeys == 1 {
-                                0.000104
-                            }else if n_storeys == 2 {
-                                0.000137
-                            }else{
-                                0.000161
-                            }
-                        },
-                        ShelterClass::SmallLotUrban=>{
-                            if n_storeys == 1 {
-                                0.000032
-                            }else if n_storeys == 2 {
-                                0.000042
-                            }else{
-                                0.000049
-                            }
-                        },
-                    }
-                },
-                Err(_)=>return Err(format!("Space '{}' has been assigned an Infiltration::EffectiveAirLeakageArea but its associated building has not enough data... Please assign values to the Building's wind_coefficient or shelter_class and n_storeys fields", space.name))
+/// Finds the height of a building to be used by a model that—unlike the
+/// tables—always needs one (e.g., "the Sherman-Grimsrud infiltration model")
+pub(crate) fn required_building_height(
+    building: &Building,
+    options: &BuildingAirFlowOptions,
+    model_name: &str,
+) -> Result<Float, String> {
+    let height = match building_height(building, options) {
+        Some(h) => h,
+        None => match building.n_storeys() {
+            Ok(storeys) => *storeys as Float * DEFAULT_STOREY_HEIGHT,
+            Err(_) => {
+                return Err(format!(
+                    "Building '{}' uses {} but has no height nor n_storeys",
+                    building.name, model_name
+                ))
             }
+        },
+    };
+    if height <= 0.0 {
+        return Err(format!(
+            "Building '{}' has a non-positive height of {} m",
+            building.name, height
+        ));
+    }
+    Ok(height)
+}
+
+/// Checks that the leakage fractions of the Sherman-Grimsrud model make sense
+fn check_lbl_parameters(building: &Building, lbl: &LblParameters) -> Result<(), String> {
+    let ceiling = lbl.ceiling_leakage_fraction;
+    let floor = lbl.floor_leakage_fraction;
+    if ceiling < 0.0 || floor < 0.0 || ceiling + floor > 1.0 {
+        return Err(format!("Building '{}' has invalid ceiling ({}) and floor ({}) leakage fractions... they must be positive and add up to 1 or less", building.name, ceiling, floor));
+    }
+    if lbl.met_station_height <= 0.0 {
+        return Err(format!(
+            "Building '{}' has a non-positive meteorological station height of {} m",
+            building.name, lbl.met_station_height
+        ));
+    }
+    Ok(())
+}
+
+fn resolve_stack_coefficient(
+    space: &Arc<Space>,
+    building: &Arc<Building>,
+    options: &BuildingAirFlowOptions,
+) -> Result<(Float, CoefficientSource), String> {
+    if let Ok(v) = building.stack_coefficient() {
+        return Ok((*v, CoefficientSource::Input));
+    }
+    if let Some(lbl) = &options.lbl {
+        let height = required_building_height(building, options, LBL_MODEL_NAME)?;
+        let cs = lbl_stack_coefficient(
+            height,
+            lbl.ceiling_leakage_fraction,
+            lbl.floor_leakage_fraction,
+            lbl.indoor_temperature,
+        );
+        return Ok((cs, CoefficientSource::Lbl));
+    }
+    if let Some(height) = building_height(building, options) {
+        if height <= 0.0 {
+            return Err(format!(
+                "Building '{}' has a non-positive height of {} m",
+                building.name, height
+            ));
         }
+        return Ok((
+            stack_coefficient_from_height(height),
+            CoefficientSource::Height,
+        ));
+    }
+    match building.n_storeys() {
+        Ok(storeys) => match tabulated_stack_coefficient(*storeys) {
+            Some(cs) => Ok((cs, CoefficientSource::Table)),
+            None => Err(format!("Building '{}' has {} storeys", building.name, storeys)),
+        },
+        Err(_) => Err(format!("Space '{}' has been assigned an Infiltration::EffectiveAirLeakageArea but its associated building has not enough data... Please assign values to the Building's stack_coefficient or n_storey fields", space.name)),
+    }
+}
+
+fn resolve_wind_coefficient(
+    space: &Arc<Space>,
+    building: &Arc<Building>,
+    options: &BuildingAirFlowOptions,
+) -> Result<(Float, CoefficientSource), String> {
+    if let Ok(v) = building.wind_coefficient() {
+        return Ok((*v, CoefficientSource::Input));
+    }
+    let no_data_err = format!("Space '{}' has been assigned an Infiltration::EffectiveAirLeakageArea but its associated building has not enough data... Please assign values to the Building's wind_coefficient or shelter_class and n_storeys fields", space.name);
+    if let Some(lbl) = &options.lbl {
+        let height = required_building_height(building, options, LBL_MODEL_NAME)?;
+        let shielding = match lbl.shielding_coefficient {
+            Some(c) => c,
+            None => shielding_coefficient(building.shelter_class().map_err(|_| no_data_err)?),
+        };
+        let cw = lbl_wind_coefficient(
+            height,
+            shielding,
+            lbl.ceiling_leakage_fraction + lbl.floor_leakage_fraction,
+            &options.terrain,
+            &lbl.met_station_terrain,
+            lbl.met_station_height,
+        );
+        return Ok((cw, CoefficientSource::Lbl));
+    }
+    if let Some(height) = building_height(building, options) {
+        if height <= 0.0 {
+            return Err(format!(
+                "Building '{}' has a non-positive height of {} m",
+                building.name, height
+            ));
+        }
+        let shelter = building.shelter_class().map_err(|_| no_data_err)?;
+        let cw = wind_coefficient_from_height(height, shelter, &options.terrain);
+        return Ok((cw, CoefficientSource::Height));
+    }
+    let n_storeys = match building.n_storeys() {
+        Ok(storeys) => *storeys,
+        Err(_) => return Err(format!("Building '{}', associated with Space '{}' has not been assigned an n_storeys field... Cannot resolve Wind Coefficient for EffectiveAirLeakageArea infiltration", building.name, space.name)),
     };
-    Ok(cw)
+    let shelter = building.shelter_class().map_err(|_| no_data_err)?;
+    match tabulated_wind_coefficient(shelter, n_storeys) {
+        Some(cw) => Ok((cw, CoefficientSource::Table)),
+        None => Err(format!(
+            "Building '{}' has {} storeys",
+            building.name, n_storeys
+        )),
+    }
 }
 
-pub fn effective_air_leakage_resolver(
+/// Finds the stack and wind coefficients to use for a Space that has been
+/// assigned an `Infiltration::EffectiveAirLeakageArea`.
+pub fn resolve_leakage_coefficients(
+    space_index: usize,
     space: &Arc<Space>,
     model: &SimpleModel,
-    al: Float,
-) -> Result<Resolver, String> {
+    options: &AirFlowModelOptions,
+) -> Result<LeakageCoefficients, String> {
     // We need data from the building.
     if let Ok(b_name) = space.building() {
         let building = model.get_building(b_name)?;
-        let cs = resolve_stack_coefficient(space, &building)?;
-        let cw = resolve_wind_coefficient(space, &building)?;
-
-        let space_clone = Arc::clone(space);
-        Ok(Box::new(
-            move |current_weather: &CurrentWeather, state: &mut SimulationState| {
-                // Set temperature
-                let outdoor_temperature = current_weather
-                    .dry_bulb_temperature
-                    .expect("Weather does not have dry bulb temperature");
-                space_clone
-                    .set_infiltration_temperature(state, outdoor_temperature)
-                    .unwrap();
-
-                // Set volume
-                let volume =
-                    effective_leakage_area(current_weather, &space_clone, state, al, cw, cs);
-                space_clone.set_infiltration_volume(state, volume).unwrap();
-            },
-        ))
+        let default_options = BuildingAirFlowOptions::default();
+        let building_options = options
+            .buildings
+            .get(&building.name)
+            .unwrap_or(&default_options);
+        if let Some(lbl) = &building_options.lbl {
+            check_lbl_parameters(&building, lbl)?;
+        }
+        let (stack, stack_source) = resolve_stack_coefficient(space, &building, building_options)?;
+        let (wind, wind_source) = resolve_wind_coefficient(space, &building, building_options)?;
+        Ok(LeakageCoefficients {
+            space_index,
+            stack,
+            stack_source,
+            wind,
+            wind_source,
+        })
     } else {
         Err(format!("Space '{}' has been assigned an Infiltration::EffectiveAirLeakageArea but no building... Assign a Building to it.", space.name))
     }
 }
+
+pub fn effective_air_leakage_resolver(
+    space: &Arc<Space>,
+    al: Float,
+    coefficients: &LeakageCoefficients,
+) -> Result<Resolver, String> {
+    let cs = coefficients.stack;
+    let cw = coefficients.wind;
+    let space_clone = Arc::clone(space);
+    Ok(Box::new(
+        move |current_weather: &CurrentWeather, state: &mut SimulationState| {
+            // Set temperature
+            let outdoor_temperature = current_weather
+                .dry_bulb_temperature
+                .expect("Weather does not have dry bulb temperature");
+            space_clone
+                .set_infiltration_temperature(state, outdoor_temperature)
+                .unwrap();
+
+            // Set volume
+            let volume = effective_leakage_area(current_weather, &space_clone, state, al, cw, cs);
+            space_clone.set_infiltration_volume(state, volume).unwrap();
+        },
+    ))
+}
@@ -0,0 +1,221 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Stack driven ventilation of tall connected volumes (e.g., atria,
+//! stairwells or solar chimneys) with openings at different heights.
+
+use crate::physics::{air_density, GRAVITY};
+use crate::Float;
+
+/// An opening of a tall connected volume
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackOpening {
+    /// The name of the Space into which the air entering through
+    /// this opening flows
+    pub space: String,
+
+    /// The height of the centre of the opening, in m, measured from
+    /// any reference (e.g., the ground floor)
+    pub height: Float,
+
+    /// The area of the opening, in m2
+    pub area: Float,
+
+    /// The discharge coefficient of the opening
+    pub discharge_coefficient: Float,
+}
+
+/// A tall volume made of connected Spaces, ventilated through openings
+/// at different heights. The air within the volume is assumed to be
+/// well mixed, at the mean temperature of its Spaces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackVentilation {
+    /// The openings of the volume
+    pub openings: Vec<StackOpening>,
+}
+
+/// The result of solving the stack driven flows of a volume
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackFlows {
+    /// The height of the neutral pressure level, in m. It is `None` when
+    /// there is no temperature difference.
+    pub neutral_pressure_level: Option<Float>,
+
+    /// The volumetric flow through each opening, in m3/s. Positive values
+    /// mean air entering the volume.
+    pub flows: Vec<Float>,
+}
+
+/// Calculates the mass flow (kg/s) through an opening for a certain
+/// outdoor-minus-indoor pressure difference (Pa). Positive means inwards.
+fn mass_flow(
+    opening: &StackOpening,
+    delta_p: Float,
+    outdoor_density: Float,
+    indoor_density: Float,
+) -> Float {
+    let rho = if delta_p > 0.0 {
+        outdoor_density
+    } else {
+        indoor_density
+    };
+    let m = opening.discharge_coefficient * opening.area * (2. * rho * delta_p.abs()).sqrt();
+    if delta_p > 0.0 {
+        m
+    } else {
+        -m
+    }
+}
+
+/// Finds the pressure difference at height zero that balances the
+/// mass flows entering and leaving the volume, and the resulting flows.
+///
+/// The outdoor-minus-indoor pressure difference at height $`z`$ is
+/// $`\Delta P(z) = \Delta P_0 - (\rho_{out} - \rho_{in}) g z`$, and each
+/// opening follows the orifice equation $`\dot{m} = C_d A \sqrt{2\rho|\Delta P|}`$.
+/// The neutral pressure level is where $`\Delta P = 0`$.
+pub fn solve_stack(
+    openings: &[StackOpening],
+    indoor_temperature: Float,
+    outdoor_temperature: Float,
+) -> StackFlows {
+    let rho_in = air_density(indoor_temperature);
+    let rho_out = air_density(outdoor_temperature);
+    let delta_rho_g = (rho_out - rho_in) * GRAVITY;
+
+    if openings.len() < 2 || delta_rho_g.abs() < 1e-12 {
+        return StackFlows {
+            neutral_pressure_level: None,
+            flows: vec![0.0; openings.len()],
+        };
+    }
+
+    let balance = |p0: Float| -> Float {
+        openings
+            .iter()
+            .map(|o| mass_flow(o, p0 - delta_rho_g * o.height, rho_out, rho_in))
+            .sum()
+    };
+
+    // The balance grows with p0, and changes sign between the
+    // pressures that put the NPL at the lowest and highest openings
+    let mut lo = Float::INFINITY;
+    let mut hi = Float::NEG_INFINITY;
+    for o in openings {
+        lo = lo.min(delta_rho_g * o.height);
+        hi = hi.max(delta_rho_g * o.height);
+    }
+    for _ in 0..200 {
+        let mid = (lo + hi) / 2.;
+        if balance(mid) > 0.0 {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    let p0 = (lo + hi) / 2.;
+
+    let flows = openings
+        .iter()
+        .map(|o| {
+            let delta_p = p0 - delta_rho_g * o.height;
+            let m = mass_flow(o, delta_p, rho_out, rho_in);
+            if m > 0.0 {
+                m / rho_out
+            } else {
+                m / rho_in
+            }
+        })
+        .collect();
+
+    StackFlows {
+        neutral_pressure_level: Some(p0 / delta_rho_g),
+        flows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opening(height: Float, area: Float) -> StackOpening {
+        StackOpening {
+            space: "some space".to_string(),
+            height,
+            area,
+            discharge_coefficient: 0.6,
+        }
+    }
+
+    #[test]
+    fn test_two_openings() {
+        let h = 10.;
+        let openings = vec![opening(0., 1.), opening(h, 1.)];
+        let (t_in, t_out) = (25., 5.);
+        let res = solve_stack(&openings, t_in, t_out);
+
+        // Equal openings: rho_out z_n = rho_in (H - z_n)
+        let rho_in = air_density(t_in);
+        let rho_out = air_density(t_out);
+        let exp = rho_in * h / (rho_in + rho_out);
+        let npl = res.neutral_pressure_level.unwrap();
+        assert!((npl - exp).abs() < 1e-6);
+
+        // Warm inside: air enters at the bottom and leaves at the top
+        assert!(res.flows[0] > 0.);
+        assert!(res.flows[1] < 0.);
+        // ... conserving mass
+        assert!((res.flows[0] * rho_out + res.flows[1] * rho_in).abs() < 1e-6);
+
+        // Cold inside: the other way around
+        let res = solve_stack(&openings, t_out, t_in);
+        assert!(res.flows[0] < 0.);
+        assert!(res.flows[1] > 0.);
+    }
+
+    #[test]
+    fn test_per_storey_distribution() {
+        // One opening per storey and a large one at the top
+        let openings = vec![
+            opening(1.5, 0.1),
+            opening(4.5, 0.1),
+            opening(7.5, 0.1),
+            opening(10.5, 0.1),
+            opening(13.5, 0.4),
+        ];
+        let res = solve_stack(&openings, 22., 0.);
+        let npl = res.neutral_pressure_level.unwrap();
+        for (o, q) in openings.iter().zip(res.flows.iter()) {
+            // Inflow below the NPL, outflow above it
+            assert_eq!(*q > 0., o.height < npl);
+        }
+        // Lower storeys get more air
+        assert!(res.flows[0] > res.flows[1]);
+        assert!(res.flows[1] > res.flows[2]);
+    }
+
+    #[test]
+    fn test_no_temperature_difference() {
+        let openings = vec![opening(0., 1.), opening(10., 1.)];
+        let res = solve_stack(&openings, 20., 20.);
+        assert!(res.neutral_pressure_level.is_none());
+        assert!(res.flows.iter().all(|q| q.abs() < 1e-12));
+    }
+}
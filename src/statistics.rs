@@ -0,0 +1,291 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Statistics of the outdoor air entering each Space, accumulated over a
+//! simulation. Air coming from other Spaces (e.g., through internal openings)
+//! is not accounted for.
+//!
+//! CO2 concentrations are estimated from the number of occupants and the
+//! outdoor air entering the Space, assuming it is well mixed. When the volume of
+//! the Space is unknown, the steady-state concentration is used instead.
+//! Spaces without an occupancy profile are always occupied, but by an
+//! unknown number of people, so their CO2 concentration is not estimated.
+
+use crate::physics::{air_density, AIR_SPECIFIC_HEAT};
+use crate::Float;
+
+/// The CO2 concentration of the outdoor air, in ppm
+pub const OUTDOOR_CO2: Float = 400.;
+
+/// The CO2 generated by a sedentary adult, in m3/s
+pub const CO2_GENERATION: Float = 5.2e-6;
+
+/// The conditions of a Space during a timestep
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimestepConditions {
+    /// The outdoor air entering the Space, in m3/s
+    pub airflow: Float,
+
+    /// The temperature of the outdoor air entering the Space (e.g., after
+    /// being heated by a supply fan), in C
+    pub airflow_temperature: Float,
+
+    /// The temperature of the Space, in C
+    pub space_temperature: Float,
+
+    /// The number of occupants, if known
+    pub occupants: Option<Float>,
+
+    /// Whether the windows of the Space are open
+    pub windows_open: bool,
+
+    /// The length of the timestep, in s
+    pub dt: Float,
+}
+
+/// The statistics of a Space, accumulated over the timesteps of a simulation
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpaceStatistics {
+    /// The name of the Space
+    pub space: String,
+
+    /// The volume of the Space, in m3, if known
+    pub volume: Option<Float>,
+
+    /// The minimum airflow the Space should receive, in m3/s, if any
+    pub minimum_airflow: Option<Float>,
+
+    /// The smallest airflow entering the Space, in m3/s
+    pub min_airflow: Float,
+
+    /// The largest airflow entering the Space, in m3/s
+    pub max_airflow: Float,
+
+    /// The sum of the airflows of every timestep, multiplied by their
+    /// length, in m3
+    pub total_volume: Float,
+
+    /// The time simulated, in s
+    pub time: Float,
+
+    /// The time during which the airflow was below the minimum, in s
+    pub time_below_minimum: Float,
+
+    /// The time during which the windows were open, in s
+    pub time_windows_open: Float,
+
+    /// The heat lost (positive) or gained (negative) by the Space because
+    /// of the air entering it, in J
+    pub infiltration_energy: Float,
+
+    /// The current CO2 concentration, in ppm
+    pub co2: Float,
+
+    /// The highest CO2 concentration, in ppm
+    pub peak_co2: Float,
+
+    /// Whether the number of occupants was known during every timestep,
+    /// so the CO2 concentration could be estimated
+    pub co2_estimated: bool,
+}
+
+/// The summary of the statistics of a Space at the end of a simulation
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpaceSummary {
+    /// The name of the Space
+    pub space: String,
+
+    /// The minimum, maximum and mean air changes per hour, if the volume
+    /// of the Space is known
+    pub air_changes: Option<(Float, Float, Float)>,
+
+    /// The minimum, maximum and mean airflow, in m3/s
+    pub airflow: (Float, Float, Float),
+
+    /// The hours during which the airflow was below the minimum
+    pub hours_below_minimum: Float,
+
+    /// The hours during which the windows were open
+    pub hours_windows_open: Float,
+
+    /// The heat lost (positive) or gained (negative) by the Space because
+    /// of the air entering it, in kWh
+    pub infiltration_energy: Float,
+
+    /// The highest CO2 concentration, in ppm, if the number of occupants
+    /// was known
+    pub peak_co2: Option<Float>,
+}
+
+impl SpaceStatistics {
+    /// Creates empty statistics
+    pub fn new(space: String, volume: Option<Float>, minimum_airflow: Option<Float>) -> Self {
+        Self {
+            space,
+            volume,
+            minimum_airflow,
+            min_airflow: Float::INFINITY,
+            max_airflow: Float::NEG_INFINITY,
+            total_volume: 0.0,
+            time: 0.0,
+            time_below_minimum: 0.0,
+            time_windows_open: 0.0,
+            infiltration_energy: 0.0,
+            co2: OUTDOOR_CO2,
+            peak_co2: OUTDOOR_CO2,
+            co2_estimated: true,
+        }
+    }
+
+    /// Adds the conditions of a timestep
+    pub fn record(&mut self, conditions: &TimestepConditions) {
+        let q = conditions.airflow;
+        let dt = conditions.dt;
+        self.min_airflow = self.min_airflow.min(q);
+        self.max_airflow = self.max_airflow.max(q);
+        self.total_volume += q * dt;
+        self.time += dt;
+        if let Some(minimum) = self.minimum_airflow {
+            if q < minimum {
+                self.time_below_minimum += dt;
+            }
+        }
+        if conditions.windows_open {
+            self.time_windows_open += dt;
+        }
+        let rho_cp = air_density(conditions.airflow_temperature) * AIR_SPECIFIC_HEAT;
+        self.infiltration_energy +=
+            rho_cp * q * (conditions.space_temperature - conditions.airflow_temperature) * dt;
+
+        // Well mixed CO2 balance: V dC/dt = G + Q (C_out - C)
+        let occupants = match conditions.occupants {
+            Some(occupants) => occupants,
+            None => {
+                self.co2_estimated = false;
+                return;
+            }
+        };
+        let generation = occupants * CO2_GENERATION * 1e6;
+        self.co2 = match self.volume {
+            Some(volume) if q > 0.0 => {
+                let steady = OUTDOOR_CO2 + generation / q;
+                steady + (self.co2 - steady) * (-q * dt / volume).exp()
+            }
+            Some(volume) => self.co2 + generation * dt / volume,
+            None if q > 0.0 => OUTDOOR_CO2 + generation / q,
+            // Without volume or airflow, nothing can be said
+            None => self.co2,
+        };
+        self.peak_co2 = self.peak_co2.max(self.co2);
+    }
+
+    /// Summarises the statistics
+    pub fn summary(&self) -> SpaceSummary {
+        let (min, max, mean) = if self.time > 0.0 {
+            (
+                self.min_airflow,
+                self.max_airflow,
+                self.total_volume / self.time,
+            )
+        } else {
+            (0.0, 0.0, 0.0)
+        };
+        SpaceSummary {
+            space: self.space.clone(),
+            air_changes: self
+                .volume
+                .map(|v| (min * 3600. / v, max * 3600. / v, mean * 3600. / v)),
+            airflow: (min, max, mean),
+            hours_below_minimum: self.time_below_minimum / 3600.,
+            hours_windows_open: self.time_windows_open / 3600.,
+            infiltration_energy: self.infiltration_energy / 3.6e6,
+            peak_co2: if self.co2_estimated {
+                Some(self.peak_co2)
+            } else {
+                None
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conditions(airflow: Float, occupants: Float) -> TimestepConditions {
+        TimestepConditions {
+            airflow,
+            airflow_temperature: 0.,
+            space_temperature: 20.,
+            occupants: Some(occupants),
+            windows_open: false,
+            dt: 3600.,
+        }
+    }
+
+    #[test]
+    fn test_airflow_statistics() {
+        let mut stats = SpaceStatistics::new("room".to_string(), Some(50.), Some(0.01));
+        stats.record(&conditions(0.005, 0.));
+        let mut open = conditions(0.02, 0.);
+        open.windows_open = true;
+        stats.record(&open);
+
+        let summary = stats.summary();
+        assert!((summary.airflow.0 - 0.005).abs() < 1e-12);
+        assert!((summary.airflow.1 - 0.02).abs() < 1e-12);
+        assert!((summary.airflow.2 - 0.0125).abs() < 1e-12);
+        let (min_ach, _, mean_ach) = summary.air_changes.unwrap();
+        assert!((min_ach - 0.36).abs() < 1e-12);
+        assert!((mean_ach - 0.9).abs() < 1e-12);
+        assert!((summary.hours_below_minimum - 1.).abs() < 1e-12);
+        assert!((summary.hours_windows_open - 1.).abs() < 1e-12);
+        let exp = air_density(0.) * AIR_SPECIFIC_HEAT * 0.025 * 20. * 3600. / 3.6e6;
+        assert!((summary.infiltration_energy - exp).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_co2() {
+        // Without volume: steady state
+        let mut stats = SpaceStatistics::new("room".to_string(), None, None);
+        stats.record(&conditions(0.01, 2.));
+        let exp = OUTDOOR_CO2 + 2. * CO2_GENERATION * 1e6 / 0.01;
+        assert!((stats.peak_co2 - exp).abs() < 1e-9);
+
+        // With volume: approaches the steady state
+        let mut stats = SpaceStatistics::new("room".to_string(), Some(50.), None);
+        stats.record(&conditions(0.01, 2.));
+        assert!(stats.co2 > OUTDOOR_CO2 && stats.co2 < exp);
+        for _ in 0..100 {
+            stats.record(&conditions(0.01, 2.));
+        }
+        assert!((stats.peak_co2 - exp).abs() < 1e-6);
+        // ... and decays when the occupants leave
+        stats.record(&conditions(0.01, 0.));
+        assert!(stats.co2 < stats.peak_co2);
+        assert!(stats.summary().peak_co2.is_some());
+
+        // An unknown number of occupants
+        let mut unknown = conditions(0.01, 0.);
+        unknown.occupants = None;
+        stats.record(&unknown);
+        assert!(stats.summary().peak_co2.is_none());
+    }
+}
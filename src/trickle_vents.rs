@@ -0,0 +1,263 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Trickle vents (i.e., small air inlets in the facade) whose free area
+//! responds to the indoor relative humidity (e.g., the French "hygro-B"
+//! systems) or that regulate their flow based on the pressure
+//! difference across them. Humidity controlled vents follow the relative
+//! humidity of their Space, as found in the simulation state.
+
+use crate::elements::SelfRegulatingVent;
+use crate::physics::{air_density, GRAVITY};
+use crate::Float;
+
+/// How the flow through a trickle vent is controlled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrickleVentControl {
+    /// The free area grows linearly with the indoor relative humidity,
+    /// from `min_area` at `min_humidity` to `max_area` at `max_humidity`
+    Humidity {
+        /// The free area at low humidity, in m2
+        min_area: Float,
+        /// The free area at high humidity, in m2
+        max_area: Float,
+        /// The relative humidity (0 to 1) below which the area is `min_area`
+        min_humidity: Float,
+        /// The relative humidity (0 to 1) above which the area is `max_area`
+        max_humidity: Float,
+        /// The discharge coefficient of the vent
+        discharge_coefficient: Float,
+    },
+
+    /// The vent keeps its flow (nearly) constant above a certain pressure
+    Pressure(SelfRegulatingVent),
+}
+
+/// A trickle vent in the facade of a Space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrickleVent {
+    /// How the vent is controlled
+    pub control: TrickleVentControl,
+
+    /// The wind pressure coefficient of the facade in which the vent is
+    pub pressure_coefficient: Float,
+
+    /// The height of the vent above the neutral pressure level, in m
+    pub height_from_npl: Float,
+}
+
+impl TrickleVent {
+    /// Returns the free area (m2) of a humidity controlled vent at a certain
+    /// indoor relative humidity, or `None` if the vent is controlled by pressure
+    pub fn free_area(&self, relative_humidity: Float) -> Option<Float> {
+        match self.control {
+            TrickleVentControl::Humidity {
+                min_area,
+                max_area,
+                min_humidity,
+                max_humidity,
+                ..
+            } => {
+                let x = if max_humidity > min_humidity {
+                    ((relative_humidity - min_humidity) / (max_humidity - min_humidity))
+                        .max(0.0)
+                        .min(1.0)
+                } else if relative_humidity >= max_humidity {
+                    1.0
+                } else {
+                    0.0
+                };
+                Some(min_area + x * (max_area - min_area))
+            }
+            TrickleVentControl::Pressure(_) => None,
+        }
+    }
+
+    /// Calculates the pressure difference (in Pa) driving outdoor air into
+    /// the Space through the vent. Negative values (e.g., on the leeward side,
+    /// or above the neutral pressure level of a heated Space) push air out.
+    pub fn driving_pressure(
+        &self,
+        wind_speed: Float,
+        indoor_temperature: Float,
+        outdoor_temperature: Float,
+    ) -> Float {
+        let rho_out = air_density(outdoor_temperature);
+        let rho_in = air_density(indoor_temperature);
+        let wind = 0.5 * rho_out * self.pressure_coefficient * wind_speed * wind_speed;
+        let stack = (rho_in - rho_out) * GRAVITY * self.height_from_npl;
+        wind + stack
+    }
+
+    /// Calculates the airflow (in m3/s) entering through the vent for a certain
+    /// pressure difference (in Pa), indoor relative humidity and outdoor
+    /// temperature (in C). No air enters when the pressure pushes it out.
+    pub fn airflow(
+        &self,
+        delta_p: Float,
+        relative_humidity: Float,
+        outdoor_temperature: Float,
+    ) -> Float {
+        if delta_p <= 0.0 {
+            return 0.0;
+        }
+        match self.control {
+            TrickleVentControl::Humidity {
+                discharge_coefficient,
+                ..
+            } => {
+                let area = self.free_area(relative_humidity).unwrap_or(0.0);
+                let rho = air_density(outdoor_temperature);
+                discharge_coefficient * area * (2. * delta_p / rho).sqrt()
+            }
+            TrickleVentControl::Pressure(vent) => {
+                vent.mass_flow(delta_p, outdoor_temperature).0 / air_density(outdoor_temperature)
+            }
+        }
+    }
+}
+
+/// The conditions used for calculating the flow through the trickle
+/// vents of a Space
+#[derive(Debug, Clone, Copy)]
+pub struct TrickleVentConditions {
+    /// The wind speed, in m/s
+    pub wind_speed: Float,
+    /// The indoor temperature, in C
+    pub indoor_temperature: Float,
+    /// The outdoor temperature, in C
+    pub outdoor_temperature: Float,
+    /// The indoor relative humidity, between 0 and 1
+    pub relative_humidity: Float,
+}
+
+/// Calculates the total airflow (in m3/s) entering through a set of trickle vents
+pub fn trickle_vents_airflow(vents: &[TrickleVent], conditions: &TrickleVentConditions) -> Float {
+    vents
+        .iter()
+        .map(|v| {
+            let delta_p = v.driving_pressure(
+                conditions.wind_speed,
+                conditions.indoor_temperature,
+                conditions.outdoor_temperature,
+            );
+            v.airflow(
+                delta_p,
+                conditions.relative_humidity,
+                conditions.outdoor_temperature,
+            )
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hygro_vent() -> TrickleVent {
+        TrickleVent {
+            control: TrickleVentControl::Humidity {
+                min_area: 0.0005,
+                max_area: 0.003,
+                min_humidity: 0.3,
+                max_humidity: 0.7,
+                discharge_coefficient: 0.6,
+            },
+            pressure_coefficient: 0.5,
+            height_from_npl: -1.,
+        }
+    }
+
+    #[test]
+    fn test_free_area() {
+        let vent = hygro_vent();
+        assert!((vent.free_area(0.2).unwrap() - 0.0005).abs() < 1e-12);
+        assert!((vent.free_area(0.5).unwrap() - 0.00175).abs() < 1e-12);
+        assert!((vent.free_area(0.9).unwrap() - 0.003).abs() < 1e-12);
+
+        let vent = TrickleVent {
+            control: TrickleVentControl::Pressure(SelfRegulatingVent {
+                design_flow: 0.008,
+                regulation_pressure: 10.,
+            }),
+            pressure_coefficient: 0.5,
+            height_from_npl: -1.,
+        };
+        assert!(vent.free_area(0.5).is_none());
+    }
+
+    #[test]
+    fn test_pressure_controlled() {
+        let vent = TrickleVent {
+            control: TrickleVentControl::Pressure(SelfRegulatingVent {
+                design_flow: 0.008,
+                regulation_pressure: 10.,
+            }),
+            pressure_coefficient: 0.5,
+            height_from_npl: -1.,
+        };
+        // Windy: regulated
+        let dp = vent.driving_pressure(10., 20., 0.);
+        assert!(dp > 10.);
+        assert!((vent.airflow(dp, 0.5, 0.) - 0.008).abs() < 1e-9);
+        // Calm: below the regulation pressure
+        let dp = vent.driving_pressure(1., 20., 0.);
+        assert!(vent.airflow(dp, 0.5, 0.) < 0.008);
+    }
+
+    #[test]
+    fn test_driving_pressure() {
+        let vent = hygro_vent();
+        // Windward and below the neutral pressure level: air enters
+        let dp = vent.driving_pressure(3., 20., 5.);
+        let wind = 0.5 * air_density(5.) * 0.5 * 9.;
+        let stack = (air_density(5.) - air_density(20.)) * GRAVITY;
+        assert!((dp - wind - stack).abs() < 1e-9);
+
+        // Leeward and above the neutral pressure level: air leaves
+        let mut leeward = vent;
+        leeward.pressure_coefficient = -0.3;
+        leeward.height_from_npl = 1.;
+        assert!(leeward.driving_pressure(3., 20., 5.) < 0.0);
+        assert!(leeward.airflow(-1., 0.5, 5.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_humidity_control() {
+        let vents = vec![hygro_vent(); 2];
+        let mut conditions = TrickleVentConditions {
+            wind_speed: 3.,
+            indoor_temperature: 20.,
+            outdoor_temperature: 5.,
+            relative_humidity: 0.2,
+        };
+        // Dry: the vents stay at their minimum area
+        let dry_flow = trickle_vents_airflow(&vents, &conditions);
+        let dp = vents[0].driving_pressure(3., 20., 5.);
+        let rho = air_density(5.);
+        let exp = 2. * 0.6 * 0.0005 * (2. * dp / rho).sqrt();
+        assert!((dry_flow - exp).abs() < 1e-9);
+
+        // Humid air opens the vents
+        conditions.relative_humidity = 0.9;
+        let wet_flow = trickle_vents_airflow(&vents, &conditions);
+        assert!((wet_flow - 6. * dry_flow).abs() < 1e-9);
+    }
+}
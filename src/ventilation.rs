@@ -0,0 +1,626 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Natural ventilation through operable openings (e.g., windows)
+
+use crate::options::Terrain;
+use crate::physics::GRAVITY;
+use crate::window_control::WindowControl;
+use crate::Float;
+use weather::CurrentWeather;
+
+/// An operable opening in the envelope of a Space
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Opening {
+    /// The area of the opening when fully open, in m2
+    pub area: Float,
+
+    /// The azimuth of the outward normal of the opening, in degrees
+    /// clockwise from North.
+    pub azimuth: Float,
+
+    /// The height difference between the middle of the opening and
+    /// the neutral pressure level, in m
+    pub height_from_npl: Float,
+
+    /// The wind coefficient. If `None`, it is calculated from the
+    /// angle between the wind and the opening's normal.
+    pub wind_coefficient: Option<Float>,
+
+    /// The discharge coefficient. If `None`, it is calculated from
+    /// the indoor-outdoor temperature difference.
+    pub discharge_coefficient: Option<Float>,
+}
+
+impl Opening {
+    /// Calculates the wind coefficient as in EnergyPlus'
+    /// `ZoneVentilation:WindandStackOpenArea`, this is:
+    /// $`C_w = 0.55 - 0.25 \frac{|\theta_{opening} - \theta_{wind}|}{180}`$.
+    ///
+    /// If the wind direction is unknown, the wind is assumed to be
+    /// perpendicular to the opening's normal.
+    pub fn wind_coefficient(&self, wind_direction: Option<Float>) -> Float {
+        if let Some(c) = self.wind_coefficient {
+            return c;
+        }
+        let delta = match wind_direction {
+            Some(dir) => angle_difference(self.azimuth, dir),
+            None => 90.,
+        };
+        0.55 - 0.25 * delta / 180.
+    }
+
+    /// Calculates the discharge coefficient as in EnergyPlus'
+    /// `ZoneVentilation:WindandStackOpenArea`, this is:
+    /// $`C_d = 0.4 + 0.0045 |T_{space} - T_{out}|`$.
+    pub fn discharge_coefficient(&self, delta_t: Float) -> Float {
+        match self.discharge_coefficient {
+            Some(c) => c,
+            None => 0.4 + 0.0045 * delta_t.abs(),
+        }
+    }
+}
+
+/// Returns the absolute difference between two angles, in degrees,
+/// in the range $`[0, 180]`$
+pub fn angle_difference(a: Float, b: Float) -> Float {
+    let d = (a - b).abs() % 360.;
+    if d > 180. {
+        360. - d
+    } else {
+        d
+    }
+}
+
+/// Correlations for single-sided ventilation, this is, through openings
+/// located on a single facade. The wind speed is the local one, at the
+/// height of the openings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SingleSided {
+    /// de Gids and Phaff (1982):
+    /// $`Q = \frac{A}{2}\sqrt{C_1 U^2 + C_2 H |\Delta T| + C_3}`$, with
+    /// $`C_1 = 0.001`$, $`C_2 = 0.0035`$ and $`C_3 = 0.01`$
+    DeGidsPhaff {
+        /// The area of the opening, in m2
+        area: Float,
+        /// The height of the opening, in m
+        height: Float,
+    },
+
+    /// Warren and Parkins (1985), which is also the single opening method
+    /// of BS 5925: the largest of the wind driven flow $`Q_w = 0.025 A U`$
+    /// and the stack driven flow
+    /// $`Q_s = \frac{C_d A}{3}\sqrt{\frac{g H |\Delta T|}{\bar{T}}}`$
+    WarrenParkins {
+        /// The area of the opening, in m2
+        area: Float,
+        /// The height of the opening, in m
+        height: Float,
+        /// The discharge coefficient of the opening
+        discharge_coefficient: Float,
+    },
+
+    /// BS 5925, two openings at different heights on the same facade:
+    /// the largest of the wind driven flow $`Q_w = 0.025 A U`$ and the
+    /// stack driven flow
+    /// $`Q_s = C_d A \frac{\epsilon\sqrt{2}}{(1+\epsilon)\sqrt{1+\epsilon^2}}\sqrt{\frac{g H_1 |\Delta T|}{\bar{T}}}`$,
+    /// where $`A = A_1 + A_2`$ and $`\epsilon = A_1/A_2`$
+    Bs5925TwoOpenings {
+        /// The area of the lower opening, in m2
+        lower_area: Float,
+        /// The area of the upper opening, in m2
+        upper_area: Float,
+        /// The vertical distance between the centres of the openings, in m
+        vertical_separation: Float,
+        /// The discharge coefficient of the openings
+        discharge_coefficient: Float,
+    },
+}
+
+/// The wind driven single-sided ventilation suggested by Warren and
+/// Parkins and by BS 5925, in m3/s
+fn single_sided_wind(area: Float, wind_speed: Float) -> Float {
+    0.025 * area * wind_speed
+}
+
+/// The single-sided ventilation of de Gids and Phaff, in m3/s, through an
+/// opening of a certain area (in m2) and height (in m), under a certain
+/// wind speed (in m/s) and indoor-outdoor temperature difference (in K)
+pub(crate) fn de_gids_phaff_airflow(
+    area: Float,
+    height: Float,
+    wind_speed: Float,
+    delta_t: Float,
+) -> Float {
+    area / 2. * (0.001 * wind_speed * wind_speed + 0.0035 * height * delta_t.abs() + 0.01).sqrt()
+}
+
+/// Calculates the single-sided ventilation airflow (in m3/s) through
+/// an opening, when opened by `opening_fraction`, for a certain local
+/// wind speed (in m/s).
+pub fn single_sided_airflow(
+    weather: &CurrentWeather,
+    wind_speed: Float,
+    space_temperature: Float,
+    opening: &SingleSided,
+    opening_fraction: Float,
+) -> Float {
+    let t_out = weather
+        .dry_bulb_temperature
+        .expect("Weather does not have dry bulb temperature");
+    let delta_t = (space_temperature - t_out).abs();
+    let t_mean = (space_temperature + t_out) / 2. + 273.15;
+
+    match opening {
+        SingleSided::DeGidsPhaff { area, height } => {
+            de_gids_phaff_airflow(area * opening_fraction, *height, wind_speed, delta_t)
+        }
+        SingleSided::WarrenParkins {
+            area,
+            height,
+            discharge_coefficient,
+        } => {
+            let area = area * opening_fraction;
+            let q_wind = single_sided_wind(area, wind_speed);
+            let q_stack =
+                discharge_coefficient * area / 3. * (GRAVITY * height * delta_t / t_mean).sqrt();
+            q_wind.max(q_stack)
+        }
+        SingleSided::Bs5925TwoOpenings {
+            lower_area,
+            upper_area,
+            vertical_separation,
+            discharge_coefficient,
+        } => {
+            let lower_area = lower_area * opening_fraction;
+            let upper_area = upper_area * opening_fraction;
+            let area = lower_area + upper_area;
+            let q_wind = single_sided_wind(area, wind_speed);
+            if upper_area <= 0.0 {
+                return q_wind;
+            }
+            let e = lower_area / upper_area;
+            let geometry = e * (2. as Float).sqrt() / ((1. + e) * (1. + e * e).sqrt());
+            let q_stack = discharge_coefficient
+                * area
+                * geometry
+                * (GRAVITY * vertical_separation * delta_t / t_mean).sqrt();
+            q_wind.max(q_stack)
+        }
+    }
+}
+
+/// Calculates the normalised pressure coefficient $`C_p(\theta)/C_p(0)`$ of a
+/// wall of a low-rise building with a square plan, as a function of the angle
+/// between the wind and the wall's normal (in degrees), following
+/// Swami and Chandra (1988)
+pub fn normalised_pressure_coefficient(incidence: Float) -> Float {
+    let theta = angle_difference(incidence, 0.).to_radians();
+    let half = theta / 2.;
+    (1.248 - 0.703 * half.sin() - 1.175 * theta.sin().powi(2)
+        + 0.769 * half.cos()
+        + 0.717 * half.cos().powi(2))
+    .ln()
+}
+
+/// An opening on a facade, used for cross-ventilation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FacadeOpening {
+    /// The area of the opening when fully open, in m2
+    pub area: Float,
+
+    /// The azimuth of the outward normal of the facade, in degrees
+    /// clockwise from North.
+    pub azimuth: Float,
+
+    /// The height of the centre of the opening, in m
+    pub height: Float,
+
+    /// The discharge coefficient of the opening
+    pub discharge_coefficient: Float,
+
+    /// The pressure coefficient of the facade when the wind is
+    /// normal to it
+    pub pressure_coefficient: Float,
+}
+
+impl FacadeOpening {
+    /// Returns the pressure coefficient of the facade for a certain wind
+    /// direction (in degrees clockwise from North).
+    pub fn pressure_coefficient(&self, wind_direction: Float) -> Float {
+        self.pressure_coefficient
+            * normalised_pressure_coefficient(angle_difference(self.azimuth, wind_direction))
+    }
+}
+
+/// Two openings on different (usually opposing) facades of a Space,
+/// through which air flows across it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrossVentilation {
+    /// The opening on one facade
+    pub first: FacadeOpening,
+    /// The opening on the other facade
+    pub second: FacadeOpening,
+}
+
+/// Combines the effective areas ($`C_d A`$) of two openings in series:
+/// $`\frac{1}{A_{eff}^2} = \frac{1}{(C_{d,1}A_1)^2} + \frac{1}{(C_{d,2}A_2)^2}`$
+pub fn openings_in_series(a: Float, b: Float) -> Float {
+    if a <= 0.0 || b <= 0.0 {
+        return 0.0;
+    }
+    1. / (1. / (a * a) + 1. / (b * b)).sqrt()
+}
+
+/// Calculates the cross-ventilation airflow (in m3/s) through two openings,
+/// opened by `opening_fraction`.
+///
+/// The wind driven flow is $`Q_w = A_{eff} U \sqrt{|C_{p,1} - C_{p,2}|}`$ and the
+/// stack driven flow is $`Q_s = A_{eff}\sqrt{2 g \Delta H \frac{|\Delta T|}{\bar{T}}}`$,
+/// where $`A_{eff}`$ combines both openings in series. They are combined as
+/// $`Q = \sqrt{Q_w^2 + Q_s^2}`$. If the wind direction is unknown, the wind
+/// is assumed to be parallel to the openings (i.e., only the stack effect
+/// drives the flow).
+pub fn cross_ventilation_airflow(
+    openings: &CrossVentilation,
+    space_temperature: Float,
+    outdoor_temperature: Float,
+    wind_speed: Float,
+    wind_direction: Option<Float>,
+    opening_fraction: Float,
+) -> Float {
+    let a = openings.first.discharge_coefficient * openings.first.area * opening_fraction;
+    let b = openings.second.discharge_coefficient * openings.second.area * opening_fraction;
+    let area = openings_in_series(a, b);
+
+    let q_wind = match wind_direction {
+        Some(dir) => {
+            let delta_cp = openings.first.pressure_coefficient(dir)
+                - openings.second.pressure_coefficient(dir);
+            area * wind_speed * delta_cp.abs().sqrt()
+        }
+        None => 0.0,
+    };
+
+    let delta_t = (space_temperature - outdoor_temperature).abs();
+    let t_mean = (space_temperature + outdoor_temperature) / 2. + 273.15;
+    let delta_h = (openings.first.height - openings.second.height).abs();
+    let q_stack = area * (2. * GRAVITY * delta_h * delta_t / t_mean).sqrt();
+
+    (q_wind * q_wind + q_stack * q_stack).sqrt()
+}
+
+/// The method used for calculating the airflow through the
+/// openings of a Space
+#[derive(Debug, Clone, PartialEq)]
+pub enum VentilationMethod {
+    /// The method used by EnergyPlus' `ZoneVentilation:WindandStackOpenArea`.
+    /// Wind and stack driven flows are calculated for each opening
+    /// and added in quadrature.
+    WindAndStackOpenArea(Vec<Opening>),
+
+    /// Single-sided ventilation correlations, calculated for each
+    /// opening and added.
+    SingleSided(Vec<SingleSided>),
+
+    /// Cross-ventilation through pairs of openings on different
+    /// facades, calculated for each pair and added.
+    CrossVentilation(Vec<CrossVentilation>),
+}
+
+/// The natural ventilation of a Space: the openings through which air
+/// flows and the way they are operated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NaturalVentilation {
+    /// How the airflow is calculated
+    pub method: VentilationMethod,
+
+    /// How the openings are operated
+    pub control: WindowControl,
+
+    /// The height of the openings above the ground, in m, at which the
+    /// local wind speed is calculated
+    pub height: Float,
+}
+
+/// Calculates the airflow (in m3/s) through an opening following
+/// EnergyPlus' `ZoneVentilation:WindandStackOpenArea`.
+///
+/// The wind driven flow is $`Q_w = C_w A F U`$ and the stack driven flow
+/// is $`Q_s = C_d A F \sqrt{2 g \Delta H_{NPL} \frac{|T_{space} - T_{out}|}{T_{space}}}`$,
+/// where $`F`$ is the opening fraction and $`U`$ is the local wind speed
+/// (in m/s). They are combined as $`Q = \sqrt{Q_w^2 + Q_s^2}`$.
+pub fn wind_and_stack_open_area(
+    weather: &CurrentWeather,
+    wind_speed: Float,
+    space_temperature: Float,
+    opening: &Opening,
+    opening_fraction: Float,
+) -> Float {
+    let t_out = weather
+        .dry_bulb_temperature
+        .expect("Weather does not have dry bulb temperature");
+    let delta_t = space_temperature - t_out;
+    let area = opening.area * opening_fraction;
+
+    let cw = opening.wind_coefficient(weather.wind_direction);
+    let q_wind = cw * area * wind_speed;
+
+    let cd = opening.discharge_coefficient(delta_t);
+    let t_space = space_temperature + 273.15;
+    let q_stack =
+        cd * area * (2. * GRAVITY * opening.height_from_npl.abs() * delta_t.abs() / t_space).sqrt();
+
+    (q_wind * q_wind + q_stack * q_stack).sqrt()
+}
+
+impl NaturalVentilation {
+    /// Calculates the airflow (in m3/s) entering the space when the
+    /// openings are opened by `opening_fraction`. The wind speed of the
+    /// weather is brought to the `height` of the openings within the
+    /// `terrain` surrounding the building.
+    pub fn airflow(
+        &self,
+        weather: &CurrentWeather,
+        terrain: &Terrain,
+        space_temperature: Float,
+        opening_fraction: Float,
+    ) -> Float {
+        if opening_fraction <= 0.0 {
+            return 0.0;
+        }
+        let wind_speed = terrain.local_wind_speed(weather.wind_speed.unwrap_or(0.0), self.height);
+        match &self.method {
+            VentilationMethod::WindAndStackOpenArea(openings) => openings
+                .iter()
+                .map(|o| {
+                    wind_and_stack_open_area(
+                        weather,
+                        wind_speed,
+                        space_temperature,
+                        o,
+                        opening_fraction,
+                    )
+                })
+                .sum(),
+            VentilationMethod::SingleSided(openings) => openings
+                .iter()
+                .map(|o| {
+                    single_sided_airflow(
+                        weather,
+                        wind_speed,
+                        space_temperature,
+                        o,
+                        opening_fraction,
+                    )
+                })
+                .sum(),
+            VentilationMethod::CrossVentilation(pairs) => {
+                let t_out = weather
+                    .dry_bulb_temperature
+                    .expect("Weather does not have dry bulb temperature");
+                pairs
+                    .iter()
+                    .map(|pair| {
+                        cross_ventilation_airflow(
+                            pair,
+                            space_temperature,
+                            t_out,
+                            wind_speed,
+                            weather.wind_direction,
+                            opening_fraction,
+                        )
+                    })
+                    .sum()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use calendar::Date;
+    use schedule::ScheduleConstant;
+    use weather::{SyntheticWeather, Weather};
+
+    fn get_weather(temperature: Float, wind_speed: Float) -> CurrentWeather {
+        let mut weather = SyntheticWeather::default();
+        weather.dry_bulb_temperature = Box::new(ScheduleConstant::new(temperature));
+        weather.wind_speed = Box::new(ScheduleConstant::new(wind_speed));
+        let date = Date {
+            month: 1,
+            day: 1,
+            hour: 1.,
+        };
+        weather.get_weather_data(date)
+    }
+
+    #[test]
+    fn test_angle_difference() {
+        assert!((angle_difference(10., 350.) - 20.).abs() < 1e-9);
+        assert!((angle_difference(0., 180.) - 180.).abs() < 1e-9);
+        assert!((angle_difference(90., 45.) - 45.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_wind_and_stack_open_area() {
+        let opening = Opening {
+            area: 1.,
+            azimuth: 0.,
+            height_from_npl: 1.,
+            wind_coefficient: Some(0.5),
+            discharge_coefficient: Some(0.6),
+        };
+
+        // Only wind
+        let weather = get_weather(20., 2.);
+        let q = wind_and_stack_open_area(&weather, 2., 20., &opening, 1.);
+        assert!((q - 1.).abs() < 1e-9);
+        let q = wind_and_stack_open_area(&weather, 2., 20., &opening, 0.5);
+        assert!((q - 0.5).abs() < 1e-9);
+
+        // Only stack
+        let weather = get_weather(10., 0.);
+        let q = wind_and_stack_open_area(&weather, 0., 20., &opening, 1.);
+        let exp = 0.6 * (2. * GRAVITY * 10. / 293.15 as Float).sqrt();
+        assert!((q - exp).abs() < 1e-9);
+
+        // Closed
+        let ventilation = NaturalVentilation {
+            method: VentilationMethod::WindAndStackOpenArea(vec![opening]),
+            control: WindowControl::AlwaysOpen,
+            height: 10.,
+        };
+        let terrain = Terrain::default();
+        assert!(ventilation.airflow(&weather, &terrain, 20., 0.0) < 1e-9);
+
+        // The wind speed is brought to the height of the openings
+        let weather = get_weather(20., 2.);
+        let q = ventilation.airflow(&weather, &Terrain::Flat, 20., 1.0);
+        assert!((q - 1.).abs() < 1e-9);
+        let q = ventilation.airflow(&weather, &Terrain::CityCentre, 20., 1.0);
+        assert!((q - 0.47).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_de_gids_phaff() {
+        let opening = SingleSided::DeGidsPhaff {
+            area: 1.,
+            height: 1.5,
+        };
+        let weather = get_weather(15., 3.);
+        let q = single_sided_airflow(&weather, 3., 20., &opening, 1.);
+        let exp = 0.5 * (0.001 * 9. + 0.0035 * 1.5 * 5. + 0.01 as Float).sqrt();
+        assert!((q - exp).abs() < 1e-9);
+        // Opening fraction reduces flow proportionally
+        let q2 = single_sided_airflow(&weather, 3., 20., &opening, 0.5);
+        assert!((q2 - exp / 2.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_warren_parkins() {
+        let opening = SingleSided::WarrenParkins {
+            area: 1.,
+            height: 1.5,
+            discharge_coefficient: 0.61,
+        };
+
+        // Stack dominated
+        let weather = get_weather(15., 3.);
+        let q = single_sided_airflow(&weather, 3., 20., &opening, 1.);
+        let exp = 0.61 / 3. * (GRAVITY * 1.5 * 5. / 290.65 as Float).sqrt();
+        assert!((q - exp).abs() < 1e-9);
+
+        // Wind dominated
+        let weather = get_weather(20., 8.);
+        let q = single_sided_airflow(&weather, 8., 20., &opening, 1.);
+        assert!((q - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_bs5925_two_openings() {
+        // Two equal openings: geometry factor is 1/2
+        let opening = SingleSided::Bs5925TwoOpenings {
+            lower_area: 0.5,
+            upper_area: 0.5,
+            vertical_separation: 1.,
+            discharge_coefficient: 0.61,
+        };
+        let weather = get_weather(10., 0.);
+        let q = single_sided_airflow(&weather, 0., 20., &opening, 1.);
+        let exp = 0.61 * 0.5 * (GRAVITY * 10. / 288.15 as Float).sqrt();
+        assert!((q - exp).abs() < 1e-9);
+
+        // It is more effective than a single opening of the same area and height
+        let single = SingleSided::WarrenParkins {
+            area: 1.,
+            height: 1.,
+            discharge_coefficient: 0.61,
+        };
+        assert!(q > single_sided_airflow(&weather, 0., 20., &single, 1.));
+    }
+
+    #[test]
+    fn test_normalised_pressure_coefficient() {
+        assert!((normalised_pressure_coefficient(0.) - 1.).abs() < 0.01);
+        assert!(normalised_pressure_coefficient(90.) < 0.);
+        assert!(normalised_pressure_coefficient(180.) < 0.);
+        // Symmetric
+        let a = normalised_pressure_coefficient(30.);
+        let b = normalised_pressure_coefficient(-30.);
+        assert!((a - b).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_openings_in_series() {
+        // Two equal openings
+        assert!((openings_in_series(1., 1.) - (0.5 as Float).sqrt()).abs() < 1e-9);
+        // A very large opening does not restrict the flow
+        assert!((openings_in_series(1., 1e6) - 1.).abs() < 1e-6);
+        assert!(openings_in_series(0., 1.) < 1e-9);
+    }
+
+    #[test]
+    fn test_cross_ventilation() {
+        let north = FacadeOpening {
+            area: 1.,
+            azimuth: 0.,
+            height: 1.5,
+            discharge_coefficient: 0.6,
+            pressure_coefficient: 0.6,
+        };
+        let pair = CrossVentilation {
+            first: north,
+            second: FacadeOpening {
+                azimuth: 180.,
+                ..north
+            },
+        };
+        let area = openings_in_series(0.6, 0.6);
+
+        // Wind normal to the openings... no stack
+        let q = cross_ventilation_airflow(&pair, 20., 20., 3., Some(0.), 1.);
+        let delta_cp =
+            0.6 * (normalised_pressure_coefficient(0.) - normalised_pressure_coefficient(180.));
+        assert!((q - area * 3. * delta_cp.sqrt()).abs() < 1e-9);
+
+        // Wind from the other side gives the same flow
+        let q2 = cross_ventilation_airflow(&pair, 20., 20., 3., Some(180.), 1.);
+        assert!((q - q2).abs() < 1e-9);
+
+        // Wind parallel to the openings... no flow
+        let q = cross_ventilation_airflow(&pair, 20., 20., 3., Some(90.), 1.);
+        assert!(q < 1e-9);
+
+        // Stack only (openings at different heights)
+        let pair = CrossVentilation {
+            first: north,
+            second: FacadeOpening {
+                azimuth: 180.,
+                height: 3.5,
+                ..north
+            },
+        };
+        let q = cross_ventilation_airflow(&pair, 25., 15., 3., Some(90.), 1.);
+        let exp = area * (2. * GRAVITY * 2. * 10. / 293.15 as Float).sqrt();
+        assert!((q - exp).abs() < 1e-9);
+    }
+}
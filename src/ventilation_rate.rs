@@ -0,0 +1,593 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! The Ventilation Rate Procedure of ASHRAE Standard 62.1, which gives the
+//! minimum outdoor airflow of the Spaces served by a ventilation system
+//! based on their floor area, their occupancy and what they are used for.
+//!
+//! The breathing zone outdoor airflow of each zone is
+//! $`V_{bz} = R_p P_z + R_a A_z`$, and the zone outdoor airflow is
+//! $`V_{oz} = V_{bz} / E_z`$. How these add up into the outdoor air intake
+//! of the system depends on the kind of system.
+
+use crate::mechanical::MechanicalVentilation;
+use crate::options::AirFlowModelOptions;
+use crate::Float;
+
+/// What a Space is used for, which sets its outdoor air rates
+/// (after Table 6-1 of ASHRAE 62.1)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OccupancyCategory {
+    /// Office space
+    Office,
+    /// Reception area
+    Reception,
+    /// Conference or meeting room
+    Conference,
+    /// Classroom (ages 5 to 8)
+    PrimaryClassroom,
+    /// Classroom (ages 9 and older)
+    Classroom,
+    /// Lecture classroom
+    LectureClassroom,
+    /// Library
+    Library,
+    /// Retail sales
+    Retail,
+    /// Restaurant dining room
+    RestaurantDining,
+    /// Cafeteria or fast-food dining
+    Cafeteria,
+    /// Bar or cocktail lounge
+    Bar,
+    /// Hotel, motel or dormitory bedroom
+    HotelBedroom,
+    /// Lobby
+    Lobby,
+    /// Corridor
+    Corridor,
+    /// Health club aerobics room
+    Gym,
+    /// Museum or gallery
+    Museum,
+    /// Warehouse
+    Warehouse,
+    /// Custom outdoor air rates and default occupant density
+    Custom {
+        /// The people outdoor air rate, in L/s per person
+        people_rate: Float,
+        /// The area outdoor air rate, in L/s per m2
+        area_rate: Float,
+        /// The default occupant density, in people per 100 m2
+        occupant_density: Float,
+    },
+}
+
+impl OccupancyCategory {
+    /// Returns the people outdoor air rate $`R_p`$ (in L/s per person), the
+    /// area outdoor air rate $`R_a`$ (in L/s per m2) and the default occupant
+    /// density (in people per 100 m2) of the category
+    pub fn rates(&self) -> (Float, Float, Float) {
+        match self {
+            OccupancyCategory::Office => (2.5, 0.3, 5.),
+            OccupancyCategory::Reception => (2.5, 0.3, 30.),
+            OccupancyCategory::Conference => (2.5, 0.3, 50.),
+            OccupancyCategory::PrimaryClassroom => (5.0, 0.6, 25.),
+            OccupancyCategory::Classroom => (5.0, 0.6, 35.),
+            OccupancyCategory::LectureClassroom => (3.8, 0.3, 65.),
+            OccupancyCategory::Library => (2.5, 0.6, 10.),
+            OccupancyCategory::Retail => (3.8, 0.6, 15.),
+            OccupancyCategory::RestaurantDining => (3.8, 0.9, 70.),
+            OccupancyCategory::Cafeteria => (3.8, 0.9, 100.),
+            OccupancyCategory::Bar => (3.8, 0.9, 100.),
+            OccupancyCategory::HotelBedroom => (2.5, 0.3, 10.),
+            OccupancyCategory::Lobby => (3.8, 0.3, 30.),
+            OccupancyCategory::Corridor => (0.0, 0.3, 0.),
+            OccupancyCategory::Gym => (10., 0.3, 40.),
+            OccupancyCategory::Museum => (3.8, 0.6, 40.),
+            OccupancyCategory::Warehouse => (0.0, 0.3, 0.),
+            OccupancyCategory::Custom {
+                people_rate,
+                area_rate,
+                occupant_density,
+            } => (*people_rate, *area_rate, *occupant_density),
+        }
+    }
+}
+
+/// How air is supplied to and returned from a zone, which sets its zone
+/// air distribution effectiveness $`E_z`$ (after Table 6-4 of ASHRAE 62.1)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AirDistribution {
+    /// Ceiling supply of cool air
+    CeilingSupplyCoolAir,
+    /// Ceiling supply of warm air and floor return
+    CeilingSupplyWarmAirFloorReturn,
+    /// Ceiling supply of warm air (8 K or more above the Space) and ceiling return
+    CeilingSupplyWarmAirCeilingReturn,
+    /// Floor supply of cool air and ceiling return
+    FloorSupplyCoolAirCeilingReturn,
+    /// Low velocity displacement ventilation
+    Displacement,
+    /// Floor supply of warm air and ceiling return
+    FloorSupplyWarmAirCeilingReturn,
+    /// Make-up air drawn in on the opposite side of the room from the exhaust
+    MakeUpAirOppositeExhaust,
+    /// Make-up air drawn in near the exhaust
+    MakeUpAirNearExhaust,
+    /// A custom effectiveness
+    Custom(Float),
+}
+
+impl AirDistribution {
+    /// The zone air distribution effectiveness $`E_z`$
+    pub fn effectiveness(&self) -> Float {
+        match self {
+            AirDistribution::CeilingSupplyCoolAir => 1.0,
+            AirDistribution::CeilingSupplyWarmAirFloorReturn => 1.0,
+            AirDistribution::CeilingSupplyWarmAirCeilingReturn => 0.8,
+            AirDistribution::FloorSupplyCoolAirCeilingReturn => 1.0,
+            AirDistribution::Displacement => 1.2,
+            AirDistribution::FloorSupplyWarmAirCeilingReturn => 0.7,
+            AirDistribution::MakeUpAirOppositeExhaust => 0.8,
+            AirDistribution::MakeUpAirNearExhaust => 0.5,
+            AirDistribution::Custom(e) => *e,
+        }
+    }
+}
+
+/// A Space served by a ventilation system
+#[derive(Debug, Clone, PartialEq)]
+pub struct VentilationZone {
+    /// The name of the Space
+    pub space: String,
+
+    /// What the Space is used for
+    pub category: OccupancyCategory,
+
+    /// The floor area of the Space, in m2
+    pub floor_area: Float,
+
+    /// The design number of occupants. If `None`, the default occupant
+    /// density of the `category` is used.
+    pub occupants: Option<Float>,
+
+    /// How air is supplied to and returned from the Space
+    pub distribution: AirDistribution,
+
+    /// The primary airflow supplied to the Space by a multi-zone system,
+    /// in m3/s. It is only needed by `VentilationSystemKind::MultiZone`.
+    pub primary_airflow: Option<Float>,
+}
+
+impl VentilationZone {
+    /// The design number of occupants $`P_z`$
+    pub fn population(&self) -> Float {
+        let (_, _, density) = self.category.rates();
+        self.occupants.unwrap_or(density * self.floor_area / 100.)
+    }
+
+    /// The outdoor air needed by the occupants, $`R_p P_z`$, in m3/s
+    pub fn people_outdoor_airflow(&self) -> Float {
+        let (rp, _, _) = self.category.rates();
+        rp * self.population() / 1000.
+    }
+
+    /// The outdoor air needed by the floor area, $`R_a A_z`$, in m3/s
+    pub fn area_outdoor_airflow(&self) -> Float {
+        let (_, ra, _) = self.category.rates();
+        ra * self.floor_area / 1000.
+    }
+
+    /// The breathing zone outdoor airflow $`V_{bz}`$, in m3/s
+    pub fn breathing_zone_outdoor_airflow(&self) -> Float {
+        self.people_outdoor_airflow() + self.area_outdoor_airflow()
+    }
+
+    /// The zone outdoor airflow $`V_{oz} = V_{bz} / E_z`$, in m3/s
+    pub fn zone_outdoor_airflow(&self) -> Float {
+        self.breathing_zone_outdoor_airflow() / self.distribution.effectiveness()
+    }
+}
+
+/// The kinds of ventilation systems
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VentilationSystemKind {
+    /// A system serving a single zone, whose intake is $`V_{oz}`$
+    SingleZone,
+    /// A system supplying only outdoor air to several zones, whose intake
+    /// is $`\sum V_{oz}`$
+    OutdoorAir,
+    /// A system supplying a mixture of outdoor and recirculated air
+    /// to several zones, whose intake is $`V_{ou} / E_v`$
+    MultiZone,
+}
+
+/// A ventilation system serving one or more Spaces
+#[derive(Debug, Clone, PartialEq)]
+pub struct VentilationSystem {
+    /// The kind of system
+    pub kind: VentilationSystemKind,
+
+    /// The Spaces served by the system
+    pub zones: Vec<VentilationZone>,
+
+    /// The largest number of people expected in all the zones at the same
+    /// time $`P_s`$, which is used for accounting for occupant diversity
+    /// in multi-zone systems. If `None`, all zones are assumed to be fully
+    /// occupied at the same time.
+    pub system_population: Option<Float>,
+}
+
+/// The outdoor airflows of a zone, as calculated by the Ventilation Rate Procedure
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneVentilation {
+    /// The name of the Space
+    pub space: String,
+
+    /// The breathing zone outdoor airflow $`V_{bz}`$, in m3/s
+    pub breathing_zone_outdoor_airflow: Float,
+
+    /// The zone outdoor airflow $`V_{oz}`$, in m3/s
+    pub zone_outdoor_airflow: Float,
+
+    /// The primary outdoor air fraction $`Z_{pz}`$ (multi-zone systems only)
+    pub primary_outdoor_air_fraction: Option<Float>,
+
+    /// The share of the system's outdoor air intake delivered to the zone,
+    /// in m3/s. It is proportional to $`V_{oz}`$.
+    pub outdoor_airflow: Float,
+}
+
+/// The result of applying the Ventilation Rate Procedure to a system
+#[derive(Debug, Clone, PartialEq)]
+pub struct VentilationReport {
+    /// The results of each zone
+    pub zones: Vec<ZoneVentilation>,
+
+    /// The uncorrected outdoor air intake $`V_{ou}`$, in m3/s
+    pub uncorrected_outdoor_airflow: Float,
+
+    /// The system ventilation efficiency $`E_v`$
+    pub system_ventilation_efficiency: Float,
+
+    /// The outdoor air intake $`V_{ot}`$, in m3/s
+    pub outdoor_air_intake: Float,
+}
+
+impl VentilationSystem {
+    /// Checks whether the system makes sense
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if self.zones.is_empty() {
+            return Err("A ventilation system does not serve any zone".to_string());
+        }
+        if self.kind == VentilationSystemKind::SingleZone && self.zones.len() != 1 {
+            return Err(format!(
+                "A single-zone ventilation system serves {} zones",
+                self.zones.len()
+            ));
+        }
+        for zone in self.zones.iter() {
+            if zone.distribution.effectiveness() <= 0.0 {
+                return Err(format!(
+                    "The air distribution effectiveness of Space '{}' must be positive",
+                    zone.space
+                ));
+            }
+            if zone.floor_area < 0.0 || zone.population() < 0.0 {
+                return Err(format!(
+                    "Space '{}' has a negative floor area or number of occupants",
+                    zone.space
+                ));
+            }
+            if self.kind == VentilationSystemKind::MultiZone {
+                match zone.primary_airflow {
+                    Some(v) if v > 0.0 => {}
+                    _ => {
+                        return Err(format!(
+                            "Space '{}' is served by a multi-zone system but has no (positive) primary airflow",
+                            zone.space
+                        ))
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the Ventilation Rate Procedure.
+    ///
+    /// The system ventilation efficiency of multi-zone systems is calculated
+    /// as in Normative Appendix A, $`E_v = \min(1 + X_s - Z_{pz})`$, assuming
+    /// that all the primary air reaches the breathing zone and that there is
+    /// no secondary recirculation.
+    pub fn calculate(&self) -> Result<VentilationReport, String> {
+        self.check()?;
+
+        let mut zones: Vec<ZoneVentilation> = self
+            .zones
+            .iter()
+            .map(|z| ZoneVentilation {
+                space: z.space.clone(),
+                breathing_zone_outdoor_airflow: z.breathing_zone_outdoor_airflow(),
+                zone_outdoor_airflow: z.zone_outdoor_airflow(),
+                primary_outdoor_air_fraction: None,
+                outdoor_airflow: 0.0,
+            })
+            .collect();
+        let total_zone_airflow: Float = zones.iter().map(|z| z.zone_outdoor_airflow).sum();
+
+        let (uncorrected_outdoor_airflow, system_ventilation_efficiency) = match self.kind {
+            VentilationSystemKind::SingleZone | VentilationSystemKind::OutdoorAir => {
+                (total_zone_airflow, 1.0)
+            }
+            VentilationSystemKind::MultiZone => {
+                let people: Float = self.zones.iter().map(|z| z.population()).sum();
+                let diversity = match self.system_population {
+                    Some(ps) if people > 0.0 => (ps / people).min(1.0),
+                    _ => 1.0,
+                };
+                let vou = diversity
+                    * self
+                        .zones
+                        .iter()
+                        .map(|z| z.people_outdoor_airflow())
+                        .sum::<Float>()
+                    + self
+                        .zones
+                        .iter()
+                        .map(|z| z.area_outdoor_airflow())
+                        .sum::<Float>();
+
+                let primary: Float = self.zones.iter().filter_map(|z| z.primary_airflow).sum();
+                let xs = vou / primary;
+                let mut ev = Float::MAX;
+                for (zone, result) in self.zones.iter().zip(zones.iter_mut()) {
+                    // Checked above
+                    let zp = result.zone_outdoor_airflow / zone.primary_airflow.unwrap();
+                    result.primary_outdoor_air_fraction = Some(zp);
+                    ev = ev.min(1. + xs - zp);
+                }
+                if ev <= 0.0 {
+                    return Err(
+                        "The primary airflow of a multi-zone ventilation system is too low to ventilate its zones".to_string(),
+                    );
+                }
+                (vou, ev)
+            }
+        };
+        let outdoor_air_intake = uncorrected_outdoor_airflow / system_ventilation_efficiency;
+
+        for zone in zones.iter_mut() {
+            zone.outdoor_airflow = if total_zone_airflow > 0.0 {
+                outdoor_air_intake * zone.zone_outdoor_airflow / total_zone_airflow
+            } else {
+                0.0
+            };
+        }
+
+        Ok(VentilationReport {
+            zones,
+            uncorrected_outdoor_airflow,
+            system_ventilation_efficiency,
+            outdoor_air_intake,
+        })
+    }
+}
+
+/// Makes sure that the Spaces served by the `ventilation_systems` of some
+/// options receive at least their share of the outdoor air intake.
+///
+/// The minimum is applied after the schedules, so the Spaces are ventilated
+/// even when their fans would be off. Spaces served by a DOAS get it as the
+/// `minimum_flow` of their (first) outlet. Other Spaces get it as the
+/// `minimum_supply_flow` of their `mechanical_ventilation` (a balanced one
+/// if they had none).
+pub fn apply_minimum_ventilation(
+    options: &mut AirFlowModelOptions,
+) -> Result<Vec<VentilationReport>, String> {
+    let mut reports = Vec::with_capacity(options.ventilation_systems.len());
+    for system in options.ventilation_systems.iter() {
+        let report = system.calculate()?;
+        for zone in report.zones.iter() {
+            let outlet = options
+                .doas
+                .iter_mut()
+                .flat_map(|doas| doas.outlets.iter_mut())
+                .find(|outlet| outlet.space == zone.space);
+            if let Some(outlet) = outlet {
+                outlet.minimum_flow = outlet.minimum_flow.max(zone.outdoor_airflow);
+                continue;
+            }
+            let space_options = options.spaces.entry(zone.space.clone()).or_default();
+            let ventilation = space_options
+                .mechanical_ventilation
+                .get_or_insert_with(MechanicalVentilation::default);
+            ventilation.minimum_supply_flow =
+                ventilation.minimum_supply_flow.max(zone.outdoor_airflow);
+        }
+        reports.push(report);
+    }
+    Ok(reports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doas::{Doas, DoasOutlet, DoasSetpoint};
+
+    fn office(distribution: AirDistribution) -> VentilationZone {
+        VentilationZone {
+            space: "office".to_string(),
+            category: OccupancyCategory::Office,
+            floor_area: 100.,
+            occupants: None,
+            distribution,
+            primary_airflow: Some(0.4),
+        }
+    }
+
+    fn conference() -> VentilationZone {
+        VentilationZone {
+            space: "conference".to_string(),
+            category: OccupancyCategory::Conference,
+            floor_area: 50.,
+            occupants: Some(25.),
+            distribution: AirDistribution::CeilingSupplyCoolAir,
+            primary_airflow: Some(0.2),
+        }
+    }
+
+    #[test]
+    fn test_zone() {
+        // Default density: 5 people in 100 m2
+        let zone = office(AirDistribution::CeilingSupplyCoolAir);
+        assert!((zone.population() - 5.).abs() < 1e-12);
+        let vbz = (2.5 * 5. + 0.3 * 100.) / 1000.;
+        assert!((zone.breathing_zone_outdoor_airflow() - vbz).abs() < 1e-12);
+        assert!((zone.zone_outdoor_airflow() - vbz).abs() < 1e-12);
+
+        let zone = office(AirDistribution::CeilingSupplyWarmAirCeilingReturn);
+        assert!((zone.zone_outdoor_airflow() - vbz / 0.8).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_single_zone_and_outdoor_air() {
+        let system = VentilationSystem {
+            kind: VentilationSystemKind::SingleZone,
+            zones: vec![office(AirDistribution::CeilingSupplyCoolAir)],
+            system_population: None,
+        };
+        let report = system.calculate().unwrap();
+        assert!((report.outdoor_air_intake - 0.0425).abs() < 1e-12);
+        assert!((report.zones[0].outdoor_airflow - 0.0425).abs() < 1e-12);
+
+        let mut system = VentilationSystem {
+            kind: VentilationSystemKind::SingleZone,
+            zones: vec![office(AirDistribution::CeilingSupplyCoolAir), conference()],
+            system_population: None,
+        };
+        assert!(system.calculate().is_err());
+
+        system.kind = VentilationSystemKind::OutdoorAir;
+        let report = system.calculate().unwrap();
+        assert!((report.outdoor_air_intake - (0.0425 + 0.0775)).abs() < 1e-12);
+        assert!((report.zones[1].outdoor_airflow - 0.0775).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_multi_zone() {
+        let mut system = VentilationSystem {
+            kind: VentilationSystemKind::MultiZone,
+            zones: vec![office(AirDistribution::CeilingSupplyCoolAir), conference()],
+            system_population: None,
+        };
+        let report = system.calculate().unwrap();
+        assert!((report.uncorrected_outdoor_airflow - 0.12).abs() < 1e-12);
+        // Xs = 0.12 / 0.6 = 0.2, and the conference room is critical
+        let zp = report.zones[1].primary_outdoor_air_fraction.unwrap();
+        assert!((zp - 0.3875).abs() < 1e-12);
+        assert!((report.system_ventilation_efficiency - 0.8125).abs() < 1e-12);
+        assert!((report.outdoor_air_intake - 0.12 / 0.8125).abs() < 1e-12);
+        let total: Float = report.zones.iter().map(|z| z.outdoor_airflow).sum();
+        assert!((total - report.outdoor_air_intake).abs() < 1e-12);
+
+        // Occupant diversity: 20 out of 30 people at the same time
+        system.system_population = Some(20.);
+        let report = system.calculate().unwrap();
+        let vou = 2. / 3. * (0.0125 + 0.0625) + 0.045;
+        assert!((report.uncorrected_outdoor_airflow - vou).abs() < 1e-12);
+
+        // Primary airflows are needed
+        system.zones[0].primary_airflow = None;
+        assert!(system.calculate().is_err());
+    }
+
+    #[test]
+    fn test_apply_minimum_ventilation() {
+        let mut options = AirFlowModelOptions::default();
+        options.ventilation_systems.push(VentilationSystem {
+            kind: VentilationSystemKind::OutdoorAir,
+            zones: vec![office(AirDistribution::CeilingSupplyCoolAir), conference()],
+            system_population: None,
+        });
+        let mut conference_options = crate::options::SpaceAirFlowOptions::default();
+        conference_options.mechanical_ventilation = Some(MechanicalVentilation {
+            supply_flow: 0.05,
+            exhaust_flow: 0.07,
+            ..MechanicalVentilation::default()
+        });
+        options
+            .spaces
+            .insert("conference".to_string(), conference_options);
+
+        let reports = apply_minimum_ventilation(&mut options).unwrap();
+        assert_eq!(reports.len(), 1);
+
+        // Balanced ventilation where there was none
+        let ventilation = options.spaces["office"]
+            .mechanical_ventilation
+            .as_ref()
+            .unwrap();
+        let (supply, exhaust) = ventilation.flows(1.0);
+        assert!((supply - 0.0425).abs() < 1e-12);
+        assert!((exhaust - 0.0425).abs() < 1e-12);
+
+        // The imbalance is kept
+        let ventilation = options.spaces["conference"]
+            .mechanical_ventilation
+            .as_ref()
+            .unwrap();
+        let (supply, exhaust) = ventilation.flows(1.0);
+        assert!((supply - 0.0775).abs() < 1e-12);
+        assert!((exhaust - 0.0975).abs() < 1e-12);
+
+        // ... also when the fans are off
+        let (supply, exhaust) = ventilation.flows(0.0);
+        assert!((supply - 0.0775).abs() < 1e-12);
+        assert!((exhaust - 0.0775).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_apply_minimum_ventilation_doas() {
+        let mut options = AirFlowModelOptions::default();
+        options.ventilation_systems.push(VentilationSystem {
+            kind: VentilationSystemKind::SingleZone,
+            zones: vec![office(AirDistribution::CeilingSupplyCoolAir)],
+            system_population: None,
+        });
+        options.doas.push(Doas {
+            setpoint: DoasSetpoint::neutral(18., 22.),
+            outlets: vec![DoasOutlet {
+                space: "office".to_string(),
+                flow: 0.03,
+                minimum_flow: 0.0,
+            }],
+            fan: None,
+            schedule: None,
+        });
+
+        apply_minimum_ventilation(&mut options).unwrap();
+        assert!(options.spaces.get("office").is_none());
+        let doas = &options.doas[0];
+        assert!((doas.outlets[0].minimum_flow - 0.0425).abs() < 1e-12);
+        assert!((doas.flow(1.0) - 0.0425).abs() < 1e-12);
+    }
+}
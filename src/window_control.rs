@@ -0,0 +1,619 @@
+/*
+MIT License
+Copyright (c) 2021 Germán Molina
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Controllers that decide how much the openings of a Space are opened
+
+use crate::comfort::AdaptiveComfort;
+use crate::random::Rng;
+use crate::Float;
+use weather::CurrentWeather;
+
+/// The conditions under which the openings of a Space are operated
+/// during a timestep
+#[derive(Debug, Clone, Copy)]
+pub struct ControlConditions<'a> {
+    /// The dry bulb temperature of the Space, in C
+    pub space_temperature: Float,
+
+    /// The current weather
+    pub weather: &'a CurrentWeather,
+
+    /// The running mean outdoor temperature, in C
+    pub outdoor_running_mean: Float,
+
+    /// Whether the Space is occupied
+    pub occupied: bool,
+
+    /// The timestep, in seconds
+    pub dt: Float,
+}
+
+/// Opens the windows when the Space is too warm and the outdoor air
+/// is cooler than the indoor air.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TemperatureControl {
+    /// The Space dry bulb temperature above which the windows are opened, in C
+    pub setpoint: Float,
+
+    /// The windows close again when the Space temperature drops below
+    /// `setpoint - hysteresis`, in K
+    pub hysteresis: Float,
+
+    /// The minimum difference between indoor and outdoor temperature
+    /// for opening the windows, in K
+    pub min_outdoor_difference: Float,
+
+    /// The minimum time the windows stay open once opened, in seconds
+    pub min_open_time: Float,
+
+    /// The minimum time the windows stay closed once closed, in seconds
+    pub min_closed_time: Float,
+
+    /// The wind speed above which the windows are closed, in m/s
+    pub max_wind_speed: Option<Float>,
+
+    /// The opening fraction of the windows when open
+    pub max_opening_fraction: Float,
+}
+
+impl Default for TemperatureControl {
+    fn default() -> Self {
+        Self {
+            setpoint: 24.,
+            hysteresis: 1.,
+            min_outdoor_difference: 0.,
+            min_open_time: 0.,
+            min_closed_time: 0.,
+            max_wind_speed: None,
+            max_opening_fraction: 1.,
+        }
+    }
+}
+
+/// Opens the windows when the Space temperature exceeds the upper
+/// limit of an adaptive comfort model.
+///
+/// The dry bulb temperature of the Space is used as an approximation
+/// of its operative temperature.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveComfortControl {
+    /// The adaptive comfort model that defines the upper limit
+    pub comfort: AdaptiveComfort,
+
+    /// How the windows are operated around the comfort limit. Its
+    /// `setpoint` is replaced by the upper limit of the comfort model.
+    pub control: TemperatureControl,
+}
+
+/// A logistic regression of the probability of an action as a function of
+/// the indoor and outdoor temperatures:
+/// $`p = \frac{1}{1 + e^{-(a + b\theta_{in} + c\theta_{out})}}`$
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogisticModel {
+    /// The intercept $`a`$
+    pub intercept: Float,
+    /// The coefficient $`b`$ of the indoor temperature, in 1/K
+    pub indoor: Float,
+    /// The coefficient $`c`$ of the outdoor temperature, in 1/K
+    pub outdoor: Float,
+}
+
+impl LogisticModel {
+    /// Calculates the probability for certain indoor and outdoor
+    /// temperatures, in C
+    pub fn probability(&self, indoor_temperature: Float, outdoor_temperature: Float) -> Float {
+        let z =
+            self.intercept + self.indoor * indoor_temperature + self.outdoor * outdoor_temperature;
+        1. / (1. + (-z).exp())
+    }
+}
+
+/// Converts the probability of an action during `reference_dt` seconds into
+/// the probability of that same action during `dt` seconds
+fn scale_probability(p: Float, dt: Float, reference_dt: Float) -> Float {
+    1. - (1. - p).powf(dt / reference_dt)
+}
+
+/// The stochastic window opening model of Haldi and Robinson (2009), in which
+/// the openings are a Markov chain whose transition probabilities depend on the
+/// indoor and outdoor temperature and on whether occupants are arriving,
+/// staying or leaving.
+///
+/// The default coefficients are those of the original paper, dropping the terms
+/// that depend on rain and on the duration of the absences.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HaldiRobinsonControl {
+    /// The probability of opening the windows when occupants arrive
+    pub opening_at_arrival: LogisticModel,
+
+    /// The probability of opening the windows during each `regression_timestep`
+    /// while the Space is occupied
+    pub opening_during_presence: LogisticModel,
+
+    /// The probability of closing the windows during each `regression_timestep`
+    /// while the Space is occupied
+    pub closing_during_presence: LogisticModel,
+
+    /// The probability of closing the windows when occupants leave
+    pub closing_at_departure: LogisticModel,
+
+    /// The timestep for which the probabilities during presence were
+    /// fitted, in seconds
+    pub regression_timestep: Float,
+
+    /// The opening fraction of the windows when open
+    pub max_opening_fraction: Float,
+}
+
+impl Default for HaldiRobinsonControl {
+    fn default() -> Self {
+        Self {
+            opening_at_arrival: LogisticModel {
+                intercept: -13.88,
+                indoor: 0.312,
+                outdoor: 0.0433,
+            },
+            opening_during_presence: LogisticModel {
+                intercept: -12.23,
+                indoor: 0.281,
+                outdoor: 0.0271,
+            },
+            closing_during_presence: LogisticModel {
+                intercept: 3.97,
+                indoor: -0.286,
+                outdoor: -0.0505,
+            },
+            closing_at_departure: LogisticModel {
+                intercept: -8.75,
+                indoor: 0.,
+                outdoor: -0.1371,
+            },
+            regression_timestep: 300.,
+            max_opening_fraction: 1.,
+        }
+    }
+}
+
+/// The Humphreys algorithm (Rijal et al., 2007): when occupants are too
+/// warm (or too cool) relative to the adaptive comfort temperature, they open
+/// (or close) the windows with a probability given by
+/// $`logit(p) = 0.171\theta_{op} + 0.166\theta_{out} - 6.4`$.
+///
+/// The dry bulb temperature of the Space is used as an approximation of
+/// its operative temperature.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HumphreysControl {
+    /// The occupants act when the Space temperature is more than this
+    /// away from the comfort temperature, in K
+    pub comfort_band: Float,
+
+    /// The opening fraction of the windows when open
+    pub max_opening_fraction: Float,
+}
+
+impl Default for HumphreysControl {
+    fn default() -> Self {
+        Self {
+            comfort_band: 2.,
+            max_opening_fraction: 1.,
+        }
+    }
+}
+
+impl HaldiRobinsonControl {
+    /// Updates the `window` state and returns its opening fraction
+    fn opening_fraction(&self, window: &mut WindowState, conditions: &ControlConditions) -> Float {
+        let t_in = conditions.space_temperature;
+        let t_out = conditions
+            .weather
+            .dry_bulb_temperature
+            .expect("Weather does not have dry bulb temperature");
+        let arriving = conditions.occupied && !window.was_occupied;
+        let departing = !conditions.occupied && window.was_occupied;
+        let scale = |p| scale_probability(p, conditions.dt, self.regression_timestep);
+
+        // Always draw, so the sequence does not depend on the path
+        let u = window.rng.uniform();
+        if window.open {
+            let p = if departing {
+                self.closing_at_departure.probability(t_in, t_out)
+            } else if conditions.occupied {
+                scale(self.closing_during_presence.probability(t_in, t_out))
+            } else {
+                0.0
+            };
+            if u < p {
+                window.set_open(false);
+            }
+        } else {
+            let p = if arriving {
+                self.opening_at_arrival.probability(t_in, t_out)
+            } else if conditions.occupied {
+                scale(self.opening_during_presence.probability(t_in, t_out))
+            } else {
+                0.0
+            };
+            if u < p {
+                window.set_open(true);
+            }
+        }
+
+        if window.open {
+            self.max_opening_fraction
+        } else {
+            0.0
+        }
+    }
+}
+
+impl HumphreysControl {
+    /// Updates the `window` state and returns its opening fraction
+    fn opening_fraction(&self, window: &mut WindowState, conditions: &ControlConditions) -> Float {
+        let t_in = conditions.space_temperature;
+        let t_out = conditions
+            .weather
+            .dry_bulb_temperature
+            .expect("Weather does not have dry bulb temperature");
+        let t_comfort = 0.33 * conditions.outdoor_running_mean + 18.8;
+        let p_open = 1. / (1. + (-(0.171 * t_in + 0.166 * t_out - 6.4)).exp());
+
+        // Always draw, so the sequence does not depend on the path
+        let u = window.rng.uniform();
+        if conditions.occupied {
+            if !window.open && t_in > t_comfort + self.comfort_band && u < p_open {
+                window.set_open(true);
+            } else if window.open && t_in < t_comfort - self.comfort_band && u < 1. - p_open {
+                window.set_open(false);
+            }
+        }
+
+        if window.open {
+            self.max_opening_fraction
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The way in which the openings of a Space are operated
+#[derive(Debug, Clone, PartialEq)]
+pub enum WindowControl {
+    /// The openings are always fully open
+    AlwaysOpen,
+
+    /// The openings are opened when the Space is too warm
+    Temperature(TemperatureControl),
+
+    /// The openings are opened when the Space is warmer than
+    /// the adaptive comfort limit
+    AdaptiveComfort(AdaptiveComfortControl),
+
+    /// The openings are operated by occupants following the stochastic
+    /// model of Haldi and Robinson
+    HaldiRobinson(HaldiRobinsonControl),
+
+    /// The openings are operated by occupants following the
+    /// Humphreys algorithm
+    Humphreys(HumphreysControl),
+}
+
+/// The state of the openings of a Space, which persists
+/// between timesteps
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowState {
+    /// Whether the openings are open
+    pub open: bool,
+
+    /// The time the openings have spent in their current state, in seconds
+    pub time_in_state: Float,
+
+    /// Whether the Space was occupied during the previous timestep
+    pub was_occupied: bool,
+
+    /// The random number generator used by the stochastic controls
+    rng: Rng,
+}
+
+impl Default for WindowState {
+    /// Closed for long enough to be opened at any time
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl WindowState {
+    /// Creates a new (closed) state, seeding the random number generator
+    /// used by the stochastic controls
+    pub fn new(seed: u64) -> Self {
+        Self {
+            open: false,
+            time_in_state: Float::INFINITY,
+            was_occupied: false,
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Changes the state of the openings, if needed
+    fn set_open(&mut self, open: bool) {
+        if self.open != open {
+            self.open = open;
+            self.time_in_state = 0.0;
+        }
+    }
+}
+
+impl TemperatureControl {
+    /// Updates the `window` state and returns its opening fraction
+    fn opening_fraction(
+        &self,
+        window: &mut WindowState,
+        space_temperature: Float,
+        weather: &CurrentWeather,
+    ) -> Float {
+        let t_out = weather
+            .dry_bulb_temperature
+            .expect("Weather does not have dry bulb temperature");
+
+        let too_windy = match (self.max_wind_speed, weather.wind_speed) {
+            (Some(max), Some(wind_speed)) => wind_speed > max,
+            _ => false,
+        };
+
+        if too_windy {
+            // Safety first... ignore the minimum open time
+            window.set_open(false);
+        } else if window.open {
+            let should_close =
+                space_temperature < self.setpoint - self.hysteresis || t_out >= space_temperature;
+            if should_close && window.time_in_state >= self.min_open_time {
+                window.set_open(false);
+            }
+        } else {
+            let should_open = space_temperature > self.setpoint
+                && t_out < space_temperature - self.min_outdoor_difference;
+            if should_open && window.time_in_state >= self.min_closed_time {
+                window.set_open(true);
+            }
+        }
+
+        if window.open {
+            self.max_opening_fraction
+        } else {
+            0.0
+        }
+    }
+}
+
+impl WindowControl {
+    /// Advances the `window` state by one timestep and returns the
+    /// fraction by which the openings are opened
+    pub fn opening_fraction(
+        &self,
+        window: &mut WindowState,
+        conditions: &ControlConditions,
+    ) -> Float {
+        window.time_in_state += conditions.dt;
+        let fraction = match self {
+            WindowControl::AlwaysOpen => {
+                window.set_open(true);
+                1.0
+            }
+            WindowControl::Temperature(control) => {
+                control.opening_fraction(window, conditions.space_temperature, conditions.weather)
+            }
+            WindowControl::AdaptiveComfort(adaptive) => {
+                let mut control = adaptive.control;
+                control.setpoint = adaptive
+                    .comfort
+                    .upper_limit(conditions.outdoor_running_mean);
+                control.opening_fraction(window, conditions.space_temperature, conditions.weather)
+            }
+            WindowControl::HaldiRobinson(control) => control.opening_fraction(window, conditions),
+            WindowControl::Humphreys(control) => control.opening_fraction(window, conditions),
+        };
+        window.was_occupied = conditions.occupied;
+        fraction
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comfort::AdaptiveComfort;
+    use calendar::Date;
+    use schedule::ScheduleConstant;
+    use weather::{SyntheticWeather, Weather};
+
+    fn get_weather(temperature: Float, wind_speed: Float) -> CurrentWeather {
+        let mut weather = SyntheticWeather::default();
+        weather.dry_bulb_temperature = Box::new(ScheduleConstant::new(temperature));
+        weather.wind_speed = Box::new(ScheduleConstant::new(wind_speed));
+        let date = Date {
+            month: 7,
+            day: 1,
+            hour: 14.,
+        };
+        weather.get_weather_data(date)
+    }
+
+    fn conditions(
+        space_temperature: Float,
+        weather: &CurrentWeather,
+        dt: Float,
+    ) -> ControlConditions {
+        ControlConditions {
+            space_temperature,
+            weather,
+            outdoor_running_mean: 20.,
+            occupied: true,
+            dt,
+        }
+    }
+
+    #[test]
+    fn test_temperature_control() {
+        let control = WindowControl::Temperature(TemperatureControl {
+            setpoint: 25.,
+            hysteresis: 2.,
+            min_outdoor_difference: 0.,
+            min_open_time: 1800.,
+            min_closed_time: 0.,
+            max_wind_speed: Some(10.),
+            max_opening_fraction: 0.5,
+        });
+        let mut window = WindowState::default();
+        let cool = get_weather(18., 2.);
+        let dt = 900.;
+
+        // Not warm enough
+        let f = control.opening_fraction(&mut window, &conditions(24., &cool, dt));
+        assert!(f < 1e-9);
+
+        // Warm enough and cooler outside
+        let f = control.opening_fraction(&mut window, &conditions(26., &cool, dt));
+        assert!((f - 0.5).abs() < 1e-9);
+
+        // Within the hysteresis band: stays open
+        let f = control.opening_fraction(&mut window, &conditions(24., &cool, dt));
+        assert!((f - 0.5).abs() < 1e-9);
+
+        // Cold, but not open for long enough (900s < 1800s)... stays open
+        let f = control.opening_fraction(&mut window, &conditions(22., &cool, 0.));
+        assert!((f - 0.5).abs() < 1e-9);
+
+        // Now it closes
+        let f = control.opening_fraction(&mut window, &conditions(22., &cool, dt));
+        assert!(f < 1e-9);
+
+        // Warmer outside than inside does not open
+        let hot = get_weather(30., 2.);
+        let f = control.opening_fraction(&mut window, &conditions(26., &hot, dt));
+        assert!(f < 1e-9);
+
+        // Too windy closes immediately
+        let f = control.opening_fraction(&mut window, &conditions(26., &cool, dt));
+        assert!((f - 0.5).abs() < 1e-9);
+        let windy = get_weather(18., 12.);
+        let f = control.opening_fraction(&mut window, &conditions(26., &windy, 0.));
+        assert!(f < 1e-9);
+    }
+
+    #[test]
+    fn test_adaptive_comfort_control() {
+        let control = WindowControl::AdaptiveComfort(AdaptiveComfortControl {
+            comfort: AdaptiveComfort::En16798CategoryII,
+            control: TemperatureControl::default(),
+        });
+        let weather = get_weather(18., 2.);
+        let mut window = WindowState::default();
+
+        // Limit is 0.33*20 + 18.8 + 3 = 28.4
+        let conditions = ControlConditions {
+            space_temperature: 27.,
+            weather: &weather,
+            outdoor_running_mean: 20.,
+            occupied: true,
+            dt: 900.,
+        };
+        assert!(control.opening_fraction(&mut window, &conditions) < 1e-9);
+
+        // A colder running mean lowers the limit: 0.33*10 + 18.8 + 3 = 25.1
+        let conditions = ControlConditions {
+            outdoor_running_mean: 10.,
+            ..conditions
+        };
+        assert!((control.opening_fraction(&mut window, &conditions) - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_logistic_model() {
+        let m = LogisticModel {
+            intercept: -2.,
+            indoor: 0.1,
+            outdoor: 0.,
+        };
+        assert!((m.probability(20., 5.) - 0.5).abs() < 1e-9);
+        assert!(m.probability(30., 5.) > 0.5);
+
+        // Scaling the timestep
+        let p = 0.1;
+        assert!((scale_probability(p, 300., 300.) - p).abs() < 1e-9);
+        let twice = scale_probability(p, 600., 300.);
+        assert!((twice - (1. - 0.9 * 0.9)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_haldi_robinson() {
+        let control = WindowControl::HaldiRobinson(HaldiRobinsonControl::default());
+        let weather = get_weather(18., 2.);
+        let run = |seed: u64, space_temperature: Float| {
+            let mut window = WindowState::new(seed);
+            let mut n_open = 0;
+            for step in 0..2000 {
+                let c = ControlConditions {
+                    occupied: step % 96 < 40,
+                    ..conditions(space_temperature, &weather, 300.)
+                };
+                if control.opening_fraction(&mut window, &c) > 0. {
+                    n_open += 1;
+                }
+            }
+            n_open
+        };
+
+        // Reproducible
+        assert_eq!(run(7, 28.), run(7, 28.));
+
+        // Warmer spaces have their windows open more often
+        assert!(run(7, 30.) > run(7, 20.));
+    }
+
+    #[test]
+    fn test_humphreys() {
+        let control = WindowControl::Humphreys(HumphreysControl::default());
+        let weather = get_weather(18., 2.);
+        let mut window = WindowState::new(3);
+
+        // Comfortable (t_comf = 0.33*20 + 18.8 = 25.4)... nothing happens
+        for _ in 0..100 {
+            let f = control.opening_fraction(&mut window, &conditions(25., &weather, 300.));
+            assert!(f < 1e-9);
+        }
+
+        // Too warm... it eventually opens
+        let mut opened = false;
+        for _ in 0..100 {
+            if control.opening_fraction(&mut window, &conditions(30., &weather, 300.)) > 0. {
+                opened = true;
+                break;
+            }
+        }
+        assert!(opened);
+
+        // Unoccupied... nothing changes
+        for _ in 0..100 {
+            let c = ControlConditions {
+                occupied: false,
+                ..conditions(15., &weather, 300.)
+            };
+            assert!(control.opening_fraction(&mut window, &c) > 0.);
+        }
+    }
+}